@@ -47,4 +47,17 @@ impl FieldElement {
             _parameters: PhantomData,
         }
     }
+
+    /// Big-endian bytes of the canonical value, i.e. of [`Self::to_uint`].
+    pub fn to_canonical_bytes_be(&self) -> [u8; 32] {
+        self.to_uint().to_bytes_be()
+    }
+
+    /// Big-endian bytes of the internal Montgomery representation, i.e. of
+    /// [`Self::as_montgomery`].
+    ///
+    /// This is the encoding used in proofs and by the Solidity verifier.
+    pub fn to_montgomery_bytes_be(&self) -> [u8; 32] {
+        self.as_montgomery().to_bytes_be()
+    }
 }
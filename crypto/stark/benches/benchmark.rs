@@ -1,8 +1,11 @@
 #![warn(clippy::all)]
 use criterion::{black_box, Criterion};
 use zkp_macros_decl::field_element;
-use zkp_primefield::{FieldElement, Root};
-use zkp_stark::{prove, verify, Constraints, Provable, RationalExpression, TraceTable, Verifiable};
+use zkp_primefield::{FieldElement, One, Root};
+use zkp_stark::{
+    check_constraints, par_check_constraints, prove, verify, Constraints, Provable,
+    RationalExpression, TraceTable, Verifiable,
+};
 use zkp_u256::U256;
 
 #[derive(Clone, PartialEq, Debug)]
@@ -90,9 +93,75 @@ fn bench_verify(crit: &mut Criterion) {
     });
 }
 
+fn bench_evaluate_domain(crit: &mut Criterion) {
+    use RationalExpression::*;
+    let size = 1 << 16;
+    let claim = Claim {
+        index: size - 1,
+        value: FieldElement::one(),
+    };
+    let witness = Witness {
+        secret: field_element!("cafebabe"),
+    };
+    let trace = claim.trace(&witness);
+    let expression = (Trace(1, 1) - Trace(0, 0) - Trace(1, 0)) * (X - 7)
+        / (X.pow(size) - 1)
+        + Trace(0, 1).pow(3) * X;
+    let generator = FieldElement::root(size).unwrap();
+    let mut domain = vec![FieldElement::from(3)];
+    for i in 1..size {
+        let next = &domain[i - 1] * &generator;
+        domain.push(next);
+    }
+
+    let compiled = expression.compile();
+    crit.bench_function("Evaluating a compiled expression on 2^16 rows", |bench| {
+        bench.iter(|| black_box(compiled.evaluate_domain(&trace, &domain)))
+    });
+    crit.bench_function("Evaluating an expression row by row on 2^16 rows", |bench| {
+        bench.iter(|| {
+            black_box(
+                domain
+                    .iter()
+                    .enumerate()
+                    .map(|(row, x)| {
+                        expression.evaluate(x, &|i, j| {
+                            trace[((row as isize + j).rem_euclid(size as isize) as usize, i)]
+                                .clone()
+                        })
+                    })
+                    .collect::<Vec<_>>(),
+            )
+        })
+    });
+}
+
+fn bench_check_constraints(crit: &mut Criterion) {
+    let claim = Claim {
+        index: (1 << 18) - 1,
+        value: FieldElement::one(),
+    };
+    let witness = Witness {
+        secret: field_element!("cafebabe"),
+    };
+    let constraints = claim.constraints();
+    // The claimed value is wrong, so only the last row fails and all rows are
+    // checked.
+    let trace = claim.trace(&witness);
+
+    crit.bench_function("Checking constraints on 2^18 rows", |bench| {
+        bench.iter(|| black_box(check_constraints(&constraints, &trace)))
+    });
+    crit.bench_function("Checking constraints on 2^18 rows in parallel", |bench| {
+        bench.iter(|| black_box(par_check_constraints(&constraints, &trace)))
+    });
+}
+
 fn main() {
     let crit = &mut Criterion::default().configure_from_args();
     bench_verify(crit);
     bench_prove(crit);
+    bench_evaluate_domain(crit);
+    bench_check_constraints(crit);
     crit.final_summary();
 }
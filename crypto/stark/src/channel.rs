@@ -1,438 +1,3343 @@
+//! The Fiat-Shamir transcript shared by prover and verifier.
+//!
+//! A [`PublicCoin`] hashes everything written to the proof and draws the
+//! verifier's challenges from it. [`ProverChannel`] writes items to the proof
+//! and the coin, [`VerifierChannel`] replays them from a proof. Both work with
+//! any [`HashFunction`].
 // TODO: Naming?
 #![allow(clippy::module_name_repetitions)]
 use crate::proof_of_work;
-use std::{convert::TryInto, prelude::v1::*};
+use std::{
+    convert::{TryFrom, TryInto},
+    fmt,
+    marker::PhantomData,
+    prelude::v1::*,
+};
+#[cfg(feature = "std")]
+use std::{
+    error,
+    io::{self, Read},
+};
 use tiny_keccak::{Hasher, Keccak};
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
 use zkp_hash::Hash;
-use zkp_macros_decl::u256h;
+use zkp_macros_decl::hex;
 use zkp_primefield::FieldElement;
-use zkp_u256::U256;
+use zkp_u256::{DivRem, U256};
 
-pub(crate) trait RandomGenerator<T> {
+/// Draws values of type `T` from the transcript.
+pub trait RandomGenerator<T> {
     fn get_random(&mut self) -> T;
 }
 
-pub(crate) trait Writable<T> {
+/// Writes values of type `T` to the proof and the transcript.
+pub trait Writable<T> {
     fn write(&mut self, data: T);
 }
 
-pub(crate) trait Replayable<T> {
-    fn replay(&mut self) -> T;
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Error {
+    ProofTooShort,
+    InvalidEncoding,
+    TrailingBytes(usize),
+    CoinDesync,
+    SeedMismatch,
+    NonCanonicalFieldElement,
+    ReadFailed,
+    InvalidPow,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Error::*;
+        match *self {
+            ProofTooShort => write!(f, "Attempted to read past the end of the proof"),
+            InvalidEncoding => write!(f, "The proof contains an invalid encoding of a value"),
+            TrailingBytes(n) => write!(f, "The proof has {} unread bytes at the end", n),
+            CoinDesync => write!(f, "Reading would desynchronize the public coin"),
+            SeedMismatch => write!(f, "The seed differs from the one the prover used"),
+            NonCanonicalFieldElement => {
+                write!(
+                    f,
+                    "The proof contains a field element not less than the modulus"
+                )
+            }
+            ReadFailed => write!(f, "Reading the proof failed"),
+            InvalidPow => write!(f, "The proof of work nonce is invalid"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for Error {}
+
+/// Reads values of type `T` written with [`Writable`] back from the proof,
+/// absorbing the same bytes into the transcript.
+pub trait Replayable<T> {
+    /// Read the next item from the proof, failing if the proof is too short.
+    fn try_replay(&mut self) -> Result<T, Error>;
 
+    /// # Panics
+    ///
+    /// Panics if the proof is too short, see [`Replayable::try_replay`].
+    fn replay(&mut self) -> T {
+        self.try_replay().expect("Unexpected end of proof")
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the proof is too short, see [`Replayable::try_replay`].
     fn replay_many(&mut self, count: usize) -> Vec<T> {
         (0..count).map(|_| self.replay()).collect()
     }
+
+    fn try_replay_many(&mut self, count: usize) -> Result<Vec<T>, Error> {
+        (0..count).map(|_| self.try_replay()).collect()
+    }
 }
 
-// TODO: Limit to crate
-#[derive(PartialEq, Eq, Clone, Default)]
-#[cfg_attr(feature = "std", derive(Debug))]
-pub(crate) struct PublicCoin {
-    pub(crate) digest: [u8; 32],
-    counter:           u64,
+/// A 256-bit hash function used to drive the public coin.
+///
+/// The coin only ever feeds the hash a few consecutive byte strings, so
+/// implementations provide an incremental [`Hasher`]. The one-shot `hash` is
+/// derived from it.
+///
+/// Implement this to run a transcript on another hash. [`prove`](crate::prove)
+/// and [`verify`](crate::verify) only support the hashes listed in
+/// [`TranscriptHash`].
+pub trait HashFunction: Clone + Default + Eq {
+    type Hasher: Hasher + Clone;
+
+    fn hasher() -> Self::Hasher;
+
+    /// Hashes `digest || [0; 24] || counter` for [`COUNTER_BATCH`] consecutive
+    /// counters starting at `first`. These are the random values drawn from a
+    /// coin.
+    fn hash_counters(digest: &[u8; 32], first: u64) -> [[u8; 32]; COUNTER_BATCH] {
+        let mut prefix = Self::hasher();
+        prefix.update(digest);
+        prefix.update(&[0_u8; 24]);
+        let mut result = [[0; 32]; COUNTER_BATCH];
+        for (counter, output) in (first..).zip(result.iter_mut()) {
+            let mut hasher = prefix.clone();
+            hasher.update(&counter.to_be_bytes());
+            hasher.finalize(output);
+        }
+        result
+    }
+
+    fn hash(input: &[u8]) -> [u8; 32] {
+        let mut result = [0; 32];
+        let mut hasher = Self::hasher();
+        hasher.update(input);
+        hasher.finalize(&mut result);
+        result
+    }
 }
 
-#[derive(PartialEq, Eq, Clone, Default)]
+/// Number of random values computed at once by
+/// [`HashFunction::hash_counters`].
+pub const COUNTER_BATCH: usize = 4;
+
+/// Keccak256 as used by Ethereum. This is the default for all channels.
+#[derive(PartialEq, Eq, Clone, Copy, Default, Hash)]
 #[cfg_attr(feature = "std", derive(Debug))]
-pub(crate) struct ProverChannel {
-    pub(crate) coin:  PublicCoin,
-    pub(crate) proof: Vec<u8>,
+pub struct Keccak256;
+
+impl HashFunction for Keccak256 {
+    type Hasher = Keccak;
+
+    fn hasher() -> Keccak {
+        Keccak::v256()
+    }
+
+    #[cfg(feature = "keccak-simd")]
+    fn hash_counters(digest: &[u8; 32], first: u64) -> [[u8; 32]; COUNTER_BATCH] {
+        crate::keccak_simd::hash_counters(digest, first)
+    }
 }
 
-#[derive(PartialEq, Eq, Clone, Default)]
+/// Blake2s with a 256-bit output and no key.
+///
+/// Cheaper than Keccak to verify inside systems that have a native Blake2s
+/// gadget. The transcript structure is identical to the Keccak256 coin, only
+/// the hash is swapped.
+#[derive(PartialEq, Eq, Clone, Copy, Default, Hash)]
 #[cfg_attr(feature = "std", derive(Debug))]
-pub(crate) struct VerifierChannel {
-    pub(crate) coin:  PublicCoin,
-    pub(crate) proof: Vec<u8>,
-    proof_index:      usize,
-}
+pub struct Blake2s256;
 
-impl PublicCoin {
-    pub(crate) fn seed(&mut self, seed: &[u8]) {
-        let mut keccak = Keccak::v256();
-        keccak.update(seed);
-        keccak.finalize(&mut self.digest);
-        self.counter = 0;
+/// The incremental hasher of [`Blake2s256`].
+#[derive(Clone)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Blake2sHasher(blake2s_simd::State);
+
+impl Hasher for Blake2sHasher {
+    fn update(&mut self, input: &[u8]) {
+        let _ = self.0.update(input);
     }
-}
 
-impl From<Vec<u8>> for ProverChannel {
-    fn from(proof_data: Vec<u8>) -> Self {
-        Self {
-            coin:  PublicCoin::default(),
-            proof: proof_data,
-        }
+    fn finalize(self, output: &mut [u8]) {
+        output.copy_from_slice(self.0.finalize().as_bytes());
     }
 }
 
-#[cfg(feature = "prover")]
-impl ProverChannel {
-    pub(crate) fn initialize(&mut self, seed: &[u8]) {
-        self.coin.seed(seed);
+impl HashFunction for Blake2s256 {
+    type Hasher = Blake2sHasher;
+
+    fn hasher() -> Blake2sHasher {
+        Blake2sHasher(blake2s_simd::Params::new().hash_length(32).to_state())
     }
 }
 
-impl VerifierChannel {
-    pub(crate) fn new(proof: Vec<u8>) -> Self {
-        Self {
-            coin: PublicCoin::default(),
-            proof,
-            proof_index: 0,
-        }
+/// The hash function of the public coin used by [`prove`](crate::prove) and
+/// [`verify`](crate::verify), see [`Constraints::transcript_hash`].
+///
+/// [`Constraints::transcript_hash`]: crate::Constraints::transcript_hash
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum TranscriptHash {
+    /// Keccak256 as used by Ethereum. This is the only hash supported by the
+    /// Solidity verifier.
+    #[default]
+    Keccak256,
+    /// Blake2s with a 256-bit output and no key.
+    Blake2s256,
+}
+
+// TODO: Limit to crate
+#[derive(PartialEq, Eq, Clone, Hash)]
+pub struct PublicCoin<H: HashFunction = Keccak256> {
+    pub(crate) digest: [u8; 32],
+    counter:           u64,
+    grinding_prefix:   Vec<u8>,
+    hash:              PhantomData<H>,
+    sealed:            bool,
+    /// Total bytes absorbed and the resulting digest, for every write.
+    #[cfg(feature = "trace-transcript")]
+    log:               Vec<(usize, [u8; 32])>,
+    #[cfg(feature = "transcript-log")]
+    events:            Vec<TranscriptEvent>,
+}
+
+/// Destination for the proof bytes written to a [`ProverChannel`].
+pub trait ProofSink {
+    fn write_bytes(&mut self, data: &[u8]);
+
+    /// Hint that at least `additional` more bytes will be written.
+    fn reserve(&mut self, _additional: usize) {}
+
+    /// Overwrite any retained proof bytes. Sinks that forward the bytes
+    /// elsewhere have nothing to wipe.
+    #[cfg(feature = "zeroize")]
+    fn wipe(&mut self) {}
+}
+
+impl ProofSink for Vec<u8> {
+    fn write_bytes(&mut self, data: &[u8]) {
+        self.extend_from_slice(data);
     }
 
-    pub(crate) fn initialize(&mut self, seed: &[u8]) {
-        self.coin.seed(seed);
+    fn reserve(&mut self, additional: usize) {
+        Vec::reserve(self, additional);
     }
 
-    pub(crate) fn at_end(self) -> bool {
-        self.proof_index == self.proof.len()
+    #[cfg(feature = "zeroize")]
+    fn wipe(&mut self) {
+        self.zeroize();
     }
+}
+
+/// Forwards proof bytes to an [`io::Write`] instead of retaining them.
+///
+/// Writes to the channel can not fail, so the first error is stored and
+/// returned from [`ProverChannel::finish`]. Later bytes are dropped.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct StreamingSink<W: io::Write> {
+    // Only `None` after `ProverChannel::finish` took it.
+    writer: Option<W>,
+    error:  Option<io::Error>,
+}
 
-    pub(crate) fn get_coefficients(&mut self, n: usize) -> Vec<FieldElement> {
-        (0..n).map(|_| self.get_random()).collect()
+#[cfg(feature = "std")]
+impl<W: io::Write> ProofSink for StreamingSink<W> {
+    fn write_bytes(&mut self, data: &[u8]) {
+        if let (None, Some(writer)) = (&self.error, &mut self.writer) {
+            self.error = writer.write_all(data).err();
+        }
     }
+}
 
-    // This differs from Replayable::<FieldElement>::replay_many in that it only
-    // updates the public coin once, with the contents of the entire layer, instead
-    // of onces for each FieldElement in the layer.
-    pub(crate) fn replay_fri_layer(&mut self, size: usize) -> Vec<FieldElement> {
-        let start_index = self.proof_index;
-        self.proof_index += 32 * size;
-        let layer_contents = &self.proof[start_index..self.proof_index];
+/// Source of the proof bytes read by a [`VerifierChannel`].
+pub trait ProofSource {
+    /// Returns the `count` bytes of the proof starting at `from`.
+    fn read_at(&mut self, from: usize, count: usize) -> Result<&[u8], Error>;
 
-        self.coin.write(layer_contents);
+    /// Returns the number of bytes in the proof after `from`.
+    fn remaining(&mut self, from: usize) -> Result<usize, Error>;
+}
 
-        layer_contents
-            .chunks_exact(32)
-            .map(|bytes| {
-                FieldElement::from_montgomery(U256::from_bytes_be(bytes.try_into().unwrap()))
-            })
-            .collect()
+impl ProofSource for Vec<u8> {
+    fn read_at(&mut self, from: usize, count: usize) -> Result<&[u8], Error> {
+        let to = from.checked_add(count).ok_or(Error::ProofTooShort)?;
+        self.get(from..to).ok_or(Error::ProofTooShort)
     }
 
-    fn read_32_bytes(&mut self) -> [u8; 32] {
-        let mut holder = [0_u8; 32];
-        let from = self.proof_index;
-        let to = from + 32;
-        self.proof_index = to;
-        // OPT: Use arrayref crate or similar to avoid copy
-        holder.copy_from_slice(&self.proof[from..to]);
-        self.coin.write(&holder[..]);
-        holder
+    fn remaining(&mut self, from: usize) -> Result<usize, Error> {
+        Ok(self.len().saturating_sub(from))
     }
 }
 
-impl RandomGenerator<proof_of_work::ChallengeSeed> for PublicCoin {
-    fn get_random(&mut self) -> proof_of_work::ChallengeSeed {
-        self.counter += 1;
-        // FIX: Use get_random::<[u8;32]>();
-        proof_of_work::ChallengeSeed::from_bytes(self.digest)
-    }
+/// Pulls proof bytes from an [`io::Read`] as they are replayed, instead of
+/// holding the entire proof in memory.
+///
+/// Only the bytes of the current read are buffered, so bytes can only be read
+/// in order. The buffer grows as bytes arrive, so a length read from the proof
+/// can not make it allocate more than the reader provides. A premature end of
+/// the reader fails with `ProofTooShort`, and any other error with
+/// `ReadFailed`. After an error the reader is in an unknown position and the
+/// channel can not be used further. Counting the remaining bytes, as in
+/// `VerifierChannel::finalize`, reads the reader to the end.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct StreamingSource<R: Read> {
+    reader:   R,
+    position: usize,
+    buffer:   Vec<u8>,
 }
 
-impl Writable<proof_of_work::Response> for ProverChannel {
-    fn write(&mut self, data: proof_of_work::Response) {
-        self.write(&data.nonce().to_be_bytes()[..]);
+#[cfg(feature = "std")]
+impl<R: Read> ProofSource for StreamingSource<R> {
+    fn read_at(&mut self, from: usize, count: usize) -> Result<&[u8], Error> {
+        debug_assert_eq!(from, self.position, "Streaming proofs are read in order");
+        self.buffer.clear();
+        let read = self
+            .reader
+            .by_ref()
+            .take(count as u64)
+            .read_to_end(&mut self.buffer)
+            .map_err(|_| Error::ReadFailed)?;
+        if read < count {
+            return Err(Error::ProofTooShort);
+        }
+        self.position += count;
+        Ok(&self.buffer)
     }
-}
 
-impl Replayable<proof_of_work::Response> for VerifierChannel {
-    fn replay(&mut self) -> proof_of_work::Response {
-        let mut holder = [0_u8; 8];
-        let from = self.proof_index;
-        let to = from + 8;
-        self.proof_index = to;
-        holder.copy_from_slice(&self.proof[from..to]);
-        self.coin.write(&holder[..]);
-        let nonce = u64::from_be_bytes(holder);
-        proof_of_work::Response::from_nonce(nonce)
+    fn remaining(&mut self, from: usize) -> Result<usize, Error> {
+        debug_assert_eq!(from, self.position, "Streaming proofs are read in order");
+        let count = io::copy(&mut self.reader, &mut io::sink()).map_err(|_| Error::ReadFailed)?;
+        let count = usize::try_from(count).map_err(|_| Error::InvalidEncoding)?;
+        self.position += count;
+        Ok(count)
     }
 }
 
-impl RandomGenerator<FieldElement> for PublicCoin {
-    fn get_random(&mut self) -> FieldElement {
-        const MASK: U256 =
-            u256h!("0FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF");
-        loop {
-            let number: U256 = self.get_random();
-            let seed = number & MASK;
-            if seed < FieldElement::MODULUS {
-                break FieldElement::from_montgomery(seed);
+/// First difference between a prover and a verifier transcript, see
+/// [`diff_transcripts`].
+#[cfg(feature = "trace-transcript")]
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct TranscriptDiff {
+    /// Offset of the first proof byte that differs.
+    pub offset:          usize,
+    /// Prover coin digest before absorbing the write containing `offset`.
+    pub prover_digest:   [u8; 32],
+    /// Verifier coin digest before absorbing the read containing `offset`.
+    pub verifier_digest: [u8; 32],
+}
+
+/// Compare the proof bytes of a prover and a verifier channel.
+///
+/// Returns `None` if the proofs are identical. Otherwise reports the first
+/// differing offset, or the length of the shorter proof if one is a prefix of
+/// the other. The digests are taken from the coin logs, so the channels need
+/// to be written and replayed with the `trace-transcript` feature enabled.
+#[cfg(all(feature = "trace-transcript", feature = "prover"))]
+pub fn diff_transcripts<H: HashFunction>(
+    prover: &ProverChannel<H>,
+    verifier: &VerifierChannel<H>,
+) -> Option<TranscriptDiff> {
+    let offset = prover
+        .proof
+        .iter()
+        .zip(verifier.proof.iter())
+        .position(|(a, b)| a != b)
+        .or_else(|| {
+            if prover.proof.len() == verifier.proof.len() {
+                None
+            } else {
+                Some(prover.proof.len().min(verifier.proof.len()))
             }
+        })?;
+    Some(TranscriptDiff {
+        offset,
+        prover_digest: prover.coin.digest_at(offset),
+        verifier_digest: verifier.coin.digest_at(offset),
+    })
+}
+
+/// An item of the transcript log kept with the `transcript-log` feature, see
+/// [`prove_with_transcript`](crate::prove_with_transcript).
+///
+/// Writes are logged as the bytes the coin absorbs in one update, so a grouped
+/// write is a single event with the concatenated bytes. Draws are logged once
+/// per value handed out, rejected samples are not logged.
+#[cfg(feature = "transcript-log")]
+#[derive(Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum TranscriptEvent {
+    /// Bytes absorbed by the coin. The kind is `"bytes"`, or `"labeled"` for
+    /// labeled writes, where the label is the first byte.
+    Write { kind: &'static str, bytes: Vec<u8> },
+    /// A random value drawn from the coin, as big-endian bytes. Field elements
+    /// are in Montgomery form, like in the proof.
+    Draw { kind: &'static str, value: Vec<u8> },
+    /// A proof of work nonce, logged after the write of the nonce.
+    Pow { bits: usize, nonce: u64 },
+}
+
+/// The root hash of a Merkle tree commitment.
+///
+/// Unlike a raw `[u8; 32]` or [`Hash`], the coin absorbs a label byte before
+/// the root, so a root can not be confused with another 32 byte value in the
+/// transcript. The proof contains only the 32 bytes of the root. Writing a
+/// `MerkleRoot` results in a different transcript than writing the same bytes
+/// untagged.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct MerkleRoot(pub [u8; 32]);
+
+impl MerkleRoot {
+    const LABEL: u8 = 0x01;
+}
+
+/// The sibling hashes of a Merkle authentication path, written as a single
+/// transcript item.
+///
+/// The proof contains the number of hashes as a `u64`, followed by the hashes.
+/// The coin absorbs all of it in one update, where writing the hashes
+/// individually updates the coin once per hash without a length. The proof
+/// bytes differ by the length prefix and the two result in different coin
+/// states, so prover and verifier need to agree on the encoding.
+///
+/// Proof decommitments do not use this encoding, as the Solidity verifier
+/// expects the hashes of a Merkle multi-proof without a length prefix.
+#[derive(Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct MerklePath(pub Vec<[u8; 32]>);
+
+/// Byte order of integers, `U256`s and field elements in the proof, see
+/// [`Constraints::endianness`].
+///
+/// Hashes, Merkle data and the length prefixes of variable length items are
+/// always written as is. Prover and verifier need to use the same byte order.
+///
+/// [`Constraints::endianness`]: crate::Constraints::endianness
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Endianness {
+    /// Big-endian words, as expected by the Solidity verifier.
+    #[default]
+    Big,
+    /// Little-endian words, as expected by some external verifiers.
+    Little,
+}
+
+impl Endianness {
+    /// Converts big-endian bytes to this byte order. The conversion is its own
+    /// inverse, so it also converts back to big-endian.
+    fn reorder<const N: usize>(self, mut bytes: [u8; N]) -> [u8; N] {
+        if self == Self::Little {
+            bytes.reverse();
         }
+        bytes
     }
 }
 
-impl RandomGenerator<U256> for PublicCoin {
-    fn get_random(&mut self) -> U256 {
-        U256::from_bytes_be(&self.get_random())
-    }
+/// Snapshot of the state of a [`PublicCoin`], see [`PublicCoin::checkpoint`].
+#[derive(PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct CoinState {
+    digest:  [u8; 32],
+    counter: u64,
 }
 
-impl RandomGenerator<[u8; 32]> for PublicCoin {
-    fn get_random(&mut self) -> [u8; 32] {
-        let mut result = [0; 32];
-        let mut keccak = Keccak::v256();
-        keccak.update(&self.digest);
-        keccak.update(&[0_u8; 24]);
-        keccak.update(&self.counter.to_be_bytes());
-        keccak.finalize(&mut result);
-        self.counter += 1;
-        result
+#[derive(PartialEq, Eq, Clone, Default, Hash)]
+pub struct ProverChannel<H: HashFunction = Keccak256, S: ProofSink = Vec<u8>> {
+    pub(crate) coin:  PublicCoin<H>,
+    pub(crate) proof: S,
+    endianness:       Endianness,
+    /// The coin digest right after seeding, see [`ProverChannel::into_verifier`].
+    seed_digest:      [u8; 32],
+}
+
+#[derive(PartialEq, Eq, Clone, Default, Hash)]
+pub struct VerifierChannel<H: HashFunction = Keccak256, S: ProofSource = Vec<u8>> {
+    pub(crate) coin:  PublicCoin<H>,
+    pub(crate) proof: S,
+    proof_index:      usize,
+    read_end:         usize,
+    endianness:       Endianness,
+}
+
+/// Shows the digest in hex and the counter, but not the internal state.
+#[cfg(feature = "std")]
+#[allow(clippy::missing_fields_in_debug)]
+impl<H: HashFunction> fmt::Debug for PublicCoin<H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PublicCoin")
+            .field("digest", &hex::encode(self.digest))
+            .field("counter", &self.counter)
+            .finish()
     }
 }
 
-impl<T> RandomGenerator<T> for ProverChannel
-where
-    PublicCoin: RandomGenerator<T>,
-{
-    fn get_random(&mut self) -> T {
-        self.coin.get_random()
+/// Shows the coin and the proof length instead of the proof bytes.
+#[cfg(feature = "std")]
+#[allow(clippy::missing_fields_in_debug)]
+impl<H: HashFunction> fmt::Debug for ProverChannel<H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProverChannel")
+            .field("coin", &self.coin)
+            .field("proof_length", &self.proof.len())
+            .field("endianness", &self.endianness)
+            .finish()
     }
 }
 
-impl<T> RandomGenerator<T> for VerifierChannel
-where
-    PublicCoin: RandomGenerator<T>,
-{
-    fn get_random(&mut self) -> T {
-        self.coin.get_random()
+/// Shows the coin and position instead of the proof bytes.
+#[cfg(feature = "std")]
+#[allow(clippy::missing_fields_in_debug)]
+impl<H: HashFunction> fmt::Debug for VerifierChannel<H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VerifierChannel")
+            .field("coin", &self.coin)
+            .field("proof_length", &self.proof.len())
+            .field("proof_index", &self.proof_index)
+            .field("endianness", &self.endianness)
+            .finish()
     }
 }
 
-impl Writable<&[u8]> for PublicCoin {
-    fn write(&mut self, data: &[u8]) {
-        let mut result: [u8; 32] = [0; 32];
-        let mut keccak = Keccak::v256();
-        keccak.update(&self.digest);
-        keccak.update(data);
-        keccak.finalize(&mut result);
-        // FIX: Hash counter into digest.
-        self.digest = result;
-        self.counter = 0;
+/// Clears the digest and counter. Used on drop with the `zeroize` feature.
+#[cfg(feature = "zeroize")]
+impl<H: HashFunction> Zeroize for PublicCoin<H> {
+    fn zeroize(&mut self) {
+        self.digest.zeroize();
+        self.counter.zeroize();
     }
 }
 
-// Note - that this default implementation allows writing a sequence of &[u8] to
-// the proof with the same encoding for the writing and the non writing. However
-// by writing directly to the coin, other writes for the channel could separate
-// encoding from random perturbation.
-impl Writable<&[u8]> for ProverChannel {
-    fn write(&mut self, data: &[u8]) {
-        self.proof.extend_from_slice(data);
-        self.coin.write(data);
+#[cfg(feature = "zeroize")]
+impl<H: HashFunction> Drop for PublicCoin<H> {
+    fn drop(&mut self) {
+        self.zeroize();
     }
 }
 
-impl Writable<&Hash> for ProverChannel {
-    fn write(&mut self, data: &Hash) {
-        self.write(data.as_bytes());
+/// Clears the coin and any proof bytes retained by the sink.
+#[cfg(feature = "zeroize")]
+impl<H: HashFunction, S: ProofSink> Zeroize for ProverChannel<H, S> {
+    fn zeroize(&mut self) {
+        self.coin.zeroize();
+        self.proof.wipe();
     }
 }
 
-impl Writable<&zkp_merkle_tree::Commitment> for ProverChannel {
-    fn write(&mut self, data: &zkp_merkle_tree::Commitment) {
-        self.write(data.hash())
+/// The coin wipes itself, so only the proof bytes need to be cleared here.
+#[cfg(feature = "zeroize")]
+impl<H: HashFunction, S: ProofSink> Drop for ProverChannel<H, S> {
+    fn drop(&mut self) {
+        self.proof.wipe();
     }
 }
 
-impl Writable<&zkp_merkle_tree::Proof> for ProverChannel {
-    fn write(&mut self, data: &zkp_merkle_tree::Proof) {
-        for hash in data.hashes() {
-            self.write(hash)
+impl<H: HashFunction> Default for PublicCoin<H> {
+    fn default() -> Self {
+        Self {
+            digest: [0; 32],
+            counter: 0,
+            grinding_prefix: proof_of_work::DEFAULT_PREFIX.to_vec(),
+            hash: PhantomData,
+            sealed: false,
+            #[cfg(feature = "trace-transcript")]
+            log: vec![(0, [0; 32])],
+            #[cfg(feature = "transcript-log")]
+            events: Vec::new(),
         }
     }
 }
 
-// OPT - Remove allocation of vectors
-impl Writable<&[FieldElement]> for ProverChannel {
-    fn write(&mut self, data: &[FieldElement]) {
-        let mut container = Vec::with_capacity(32 * data.len());
-        for element in data {
-            for byte in &element.as_montgomery().to_bytes_be() {
-                container.push(*byte);
+impl<H: HashFunction> PublicCoin<H> {
+    pub fn seed(&mut self, seed: &[u8]) {
+        self.seed_with_domain(&[], seed);
+    }
+
+    /// Seed the coin with `domain` absorbed before `seed`.
+    ///
+    /// This lets sub-protocols derive independent transcripts from the same
+    /// seed material. The domain and seed are simply concatenated, so the set
+    /// of domains in use should be prefix-free, for example by giving them a
+    /// fixed length. An empty domain is the same as [`PublicCoin::seed`].
+    pub fn seed_with_domain(&mut self, domain: &[u8], seed: &[u8]) {
+        self.digest = H::hash(&[domain, seed].concat());
+        self.counter = 0;
+        #[cfg(feature = "trace-transcript")]
+        {
+            self.log = vec![(0, self.digest)];
+        }
+        #[cfg(feature = "transcript-log")]
+        self.events.clear();
+    }
+
+    /// Mark the transcript as complete.
+    ///
+    /// In debug builds, any later write or random draw panics. This catches
+    /// verifiers that use more of the transcript than the proof provides.
+    pub fn seal(&mut self) {
+        self.sealed = true;
+    }
+
+    fn assert_unsealed(&self) {
+        debug_assert!(!self.sealed, "Public coin used after it was sealed");
+    }
+
+    /// Called after absorbing `len` more bytes. Logs the digest when tracing
+    /// the transcript.
+    fn record(&mut self, len: usize) {
+        self.assert_unsealed();
+        #[cfg(feature = "trace-transcript")]
+        {
+            let offset = self.log.last().map_or(0, |entry| entry.0) + len;
+            self.log.push((offset, self.digest));
+        }
+        #[cfg(not(feature = "trace-transcript"))]
+        let _ = len;
+    }
+
+    /// Appends to the transcript log, see [`TranscriptEvent`].
+    #[cfg(feature = "transcript-log")]
+    fn log_event(&mut self, event: TranscriptEvent) {
+        self.events.push(event);
+    }
+
+    /// The digest before absorbing the byte at `offset`.
+    #[cfg(all(feature = "trace-transcript", feature = "prover"))]
+    fn digest_at(&self, offset: usize) -> [u8; 32] {
+        self.log
+            .iter()
+            .take_while(|entry| entry.0 <= offset)
+            .last()
+            .map_or(self.digest, |entry| entry.1)
+    }
+
+    /// Number of random values drawn since the last write.
+    ///
+    /// Only for diagnostics, for example to log where two transcripts diverge.
+    pub fn counter(&self) -> u64 {
+        self.counter
+    }
+
+    /// Set the domain separation prefix for proof of work challenges.
+    ///
+    /// Prover and verifier need to agree on the prefix. It defaults to
+    /// [`proof_of_work::DEFAULT_PREFIX`].
+    pub fn set_grinding_prefix(&mut self, prefix: &[u8]) {
+        self.grinding_prefix = prefix.to_vec();
+    }
+
+    /// Checks a proof of work `nonce` for a coin with the given `digest` and
+    /// `grinding_prefix`, without a coin.
+    ///
+    /// The result is the same as drawing a
+    /// [`ChallengeSeed`](proof_of_work::ChallengeSeed) from such a coin and
+    /// verifying the nonce with difficulty `pow_bits`. This allows probing a
+    /// digest the coin has not reached yet, and stateless checks in reference
+    /// verifiers. A difficulty of zero accepts every nonce.
+    pub fn pow_verify_against(
+        digest: &[u8; 32],
+        grinding_prefix: &[u8],
+        nonce: u64,
+        pow_bits: u8,
+    ) -> bool {
+        if pow_bits == 0 {
+            return true;
+        }
+        proof_of_work::ChallengeSeed::from_bytes_with_prefix(*digest, grinding_prefix)
+            .with_difficulty(pow_bits.into())
+            .expect("Difficulty fits in a byte")
+            .verify(proof_of_work::Response::from_nonce(nonce))
+    }
+
+    /// Derive an independent child coin, bound to the current state and
+    /// domain separated by `label`.
+    ///
+    /// The child is seeded with the hash of the digest and the label. After
+    /// the fork, writes and draws on either coin do not affect the other.
+    pub fn fork(&self, label: &[u8]) -> Self {
+        let mut digest = [0; 32];
+        let mut hasher = H::hasher();
+        hasher.update(&self.digest);
+        hasher.update(label);
+        hasher.finalize(&mut digest);
+        Self {
+            digest,
+            counter: 0,
+            grinding_prefix: self.grinding_prefix.clone(),
+            hash: PhantomData,
+            sealed: false,
+            #[cfg(feature = "trace-transcript")]
+            log: vec![(0, digest)],
+            #[cfg(feature = "transcript-log")]
+            events: Vec::new(),
+        }
+    }
+
+    /// Derive `n` independent sub-coins and advance this coin past them.
+    ///
+    /// Sub-coin `i` is `self.fork(label(i))` where `label(i)` is the byte
+    /// `0x02` followed by `i` as a big-endian `u64`. Afterwards this coin
+    /// continues as `self.fork(label(n))`, so it is distinct from all the
+    /// sub-coins. Draws from the sub-coins can be done in parallel, the
+    /// result only depends on the state before the split.
+    pub fn split_off(&mut self, n: usize) -> Vec<Self> {
+        const SPLIT_LABEL: u8 = 0x02;
+        let label = |i: usize| [&[SPLIT_LABEL][..], &(i as u64).to_be_bytes()].concat();
+        let result = (0..n).map(|i| self.fork(&label(i))).collect();
+        self.digest = self.fork(&label(n)).digest;
+        self.counter = 0;
+        self.record(0);
+        result
+    }
+
+    /// Draw `count` random field elements.
+    ///
+    /// The result is identical to calling `get_random` `count` times, but the
+    /// random values are computed in batches using
+    /// [`HashFunction::hash_counters`]. Values left over from the last batch
+    /// are discarded and the counter only advances past the values used.
+    pub fn get_random_many(&mut self, count: usize) -> Vec<FieldElement> {
+        self.assert_unsealed();
+        let mut result = Vec::with_capacity(count);
+        while result.len() < count {
+            for bytes in &H::hash_counters(&self.digest, self.counter) {
+                self.counter += 1;
+                if let Some(element) = field_element_from_random(bytes) {
+                    #[cfg(feature = "transcript-log")]
+                    self.log_event(TranscriptEvent::Draw {
+                        kind:  "FieldElement",
+                        value: element.to_montgomery_bytes_be().to_vec(),
+                    });
+                    result.push(element);
+                    if result.len() == count {
+                        break;
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// Draw a uniformly random index in `0..bound`.
+    ///
+    /// Reducing a random `U256` modulo `bound` would slightly favour small
+    /// indices when `bound` does not divide `2^256`. Instead, values from the
+    /// incomplete last block of `bound` values are rejected and redrawn.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bound` is zero.
+    pub fn get_random_index(&mut self, bound: usize) -> usize {
+        assert!(bound > 0, "Index bound must be positive");
+        let bound = bound as u64;
+        // Number of values in `0..2^256` that do not fit in a complete block.
+        let excess = (U256::MAX.div_rem(bound).unwrap().1 + 1) % bound;
+        let limit = U256::MAX - U256::from(excess);
+        loop {
+            let number: U256 = self.get_random();
+            if number <= limit {
+                // The remainder is less than `bound`, which fits a `usize`.
+                break number.div_rem(bound).unwrap().1 as usize;
             }
         }
-        self.write(container.as_slice());
+    }
+
+    /// Take a snapshot of the coin state so random draws and writes can be
+    /// undone using [`PublicCoin::restore`].
+    ///
+    /// This is a prover-side convenience and does not affect the proof.
+    pub fn checkpoint(&self) -> CoinState {
+        CoinState {
+            digest:  self.digest,
+            counter: self.counter,
+        }
+    }
+
+    /// Rewind the coin to a state previously obtained from
+    /// [`PublicCoin::checkpoint`].
+    pub fn restore(&mut self, state: CoinState) {
+        self.digest = state.digest;
+        self.counter = state.counter;
     }
 }
 
-impl Writable<&FieldElement> for ProverChannel {
-    fn write(&mut self, data: &FieldElement) {
-        self.write(&data.as_montgomery().to_bytes_be()[..]);
+/// Big-endian encoding of `FieldElement::MODULUS`.
+const MODULUS_BYTES: [u8; 32] =
+    hex!("0800000000000011000000000000000000000000000000000000000000000001");
+
+/// Decodes a field element in Montgomery form read from a proof, failing if
+/// it is not less than the modulus.
+fn field_element_from_proof(bytes: [u8; 32]) -> Result<FieldElement, Error> {
+    // Big-endian byte arrays compare lexicographically like the numbers they
+    // encode.
+    if bytes < MODULUS_BYTES {
+        Ok(FieldElement::from_montgomery(U256::from_bytes_be(&bytes)))
+    } else {
+        Err(Error::NonCanonicalFieldElement)
     }
 }
 
-// Note -- This method of writing is distinct from the field element, and is
-// used in the decommitment when groups are decommited from the rows
-impl Writable<Vec<U256>> for ProverChannel {
-    fn write(&mut self, data: Vec<U256>) {
-        for element in data {
-            self.write(element)
-        }
+/// Rejection sampling of field elements from random bytes. Returns `None` if
+/// the value with the top four bits cleared is not less than the modulus.
+fn field_element_from_random(bytes: &[u8; 32]) -> Option<FieldElement> {
+    let mut masked = *bytes;
+    masked[0] &= 0x0f;
+    // Big-endian byte arrays compare lexicographically like the numbers they
+    // encode, so we can reject before converting to `U256`.
+    if masked < MODULUS_BYTES {
+        Some(FieldElement::from_montgomery(U256::from_bytes_be(&masked)))
+    } else {
+        None
     }
 }
 
-impl Writable<U256> for ProverChannel {
-    fn write(&mut self, data: U256) {
-        self.write(&data.to_bytes_be()[..]);
+#[cfg(feature = "std")]
+impl<H: HashFunction, W: io::Write> ProverChannel<H, StreamingSink<W>> {
+    /// Flushes the sink and returns it, or the first error that occurred.
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(error) = self.proof.error.take() {
+            return Err(error);
+        }
+        let mut writer = self.proof.writer.take().unwrap();
+        writer.flush()?;
+        Ok(writer)
     }
 }
 
-impl Replayable<Hash> for VerifierChannel {
-    fn replay(&mut self) -> Hash {
-        let hash: [u8; 32] = self.read_32_bytes();
-        Hash::new(hash)
+impl<H: HashFunction> From<Vec<u8>> for ProverChannel<H> {
+    fn from(proof_data: Vec<u8>) -> Self {
+        Self {
+            coin:        PublicCoin::default(),
+            proof:       proof_data,
+            endianness:  Endianness::default(),
+            seed_digest: [0; 32],
+        }
     }
 }
 
-impl Replayable<U256> for VerifierChannel {
-    fn replay(&mut self) -> U256 {
-        let big_endian_bytes: [u8; 32] = self.read_32_bytes();
-        U256::from_bytes_be(&big_endian_bytes)
+impl<H: HashFunction> ProverChannel<H> {
+    /// Creates a verifier for the proof written so far, seeded with `seed`.
+    ///
+    /// Fails with `SeedMismatch` if `seed` is not the one the prover was
+    /// initialized with. The seed is not part of the proof, so it is checked
+    /// against the coin state right after the prover was seeded.
+    pub fn into_verifier(mut self, seed: &[u8]) -> Result<VerifierChannel<H>, Error> {
+        let mut verifier = VerifierChannel::new(std::mem::take(&mut self.proof));
+        verifier.endianness = self.endianness;
+        verifier.initialize(seed);
+        if verifier.coin.digest != self.seed_digest {
+            return Err(Error::SeedMismatch);
+        }
+        Ok(verifier)
     }
 }
 
-impl Replayable<FieldElement> for VerifierChannel {
-    fn replay(&mut self) -> FieldElement {
-        let montgomery_modulus: U256 = self.replay();
-        FieldElement::from_montgomery(montgomery_modulus)
+#[cfg(feature = "prover")]
+impl<H: HashFunction, S: ProofSink> ProverChannel<H, S> {
+    pub fn initialize(&mut self, seed: &[u8]) {
+        self.coin.seed(seed);
+        self.seed_digest = self.coin.digest;
+    }
+
+    /// See [`PublicCoin::counter`].
+    pub fn counter(&self) -> u64 {
+        self.coin.counter()
+    }
+
+    /// Writes the number of elements as a `u64` followed by the elements, so
+    /// the verifier does not need to know the length in advance.
+    ///
+    /// The length is always big-endian, the elements follow the channel's
+    /// [`Endianness`]. Everything is absorbed in one coin update. Replay with
+    /// `VerifierChannel::replay_length_prefixed`.
+    pub fn write_with_length(&mut self, data: &[FieldElement]) {
+        let prefix = (data.len() as u64).to_be_bytes();
+        let elements: Vec<[u8; 32]> = data
+            .iter()
+            .map(|element| self.endianness.reorder(element.to_montgomery_bytes_be()))
+            .collect();
+        let mut slices: Vec<&[u8]> = Vec::with_capacity(data.len() + 1);
+        slices.push(&prefix);
+        slices.extend(elements.iter().map(|bytes| &bytes[..]));
+        self.write(&slices[..]);
+    }
+
+    /// Writes field elements to the proof, updating the coin once for every
+    /// `group_size` elements.
+    ///
+    /// The proof bytes do not depend on the grouping, but the coin does. A
+    /// `group_size` of `data.len()` is equivalent to writing `&[FieldElement]`
+    /// and a `group_size` of one is equivalent to writing each element (or
+    /// `Vec<U256>`) separately. The verifier must replay with the same
+    /// grouping.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `group_size` is zero.
+    pub fn write_elements_grouped(&mut self, data: &[FieldElement], group_size: usize) {
+        for group in data.chunks(group_size) {
+            self.write(group);
+        }
+    }
+
+    /// Writes a proof of work `response` with difficulty `pow_bits`.
+    ///
+    /// The proof and coin are the same as when writing the response directly,
+    /// but the transcript log also records the difficulty.
+    pub(crate) fn write_pow(&mut self, pow_bits: usize, response: proof_of_work::Response) {
+        self.write(response);
+        #[cfg(feature = "transcript-log")]
+        self.coin.log_event(TranscriptEvent::Pow {
+            bits:  pow_bits,
+            nonce: response.nonce(),
+        });
+        #[cfg(not(feature = "transcript-log"))]
+        let _ = pow_bits;
+    }
+
+    /// Every write and draw since the channel was initialized, see
+    /// [`TranscriptEvent`].
+    #[cfg(feature = "transcript-log")]
+    pub(crate) fn events(&self) -> &[TranscriptEvent] {
+        &self.coin.events
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use zkp_macros_decl::{hex, u256h};
+impl<H: HashFunction> VerifierChannel<H> {
+    pub fn new(proof: Vec<u8>) -> Self {
+        Self {
+            coin: PublicCoin::default(),
+            proof,
+            proof_index: 0,
+            read_end: 0,
+            endianness: Endianness::default(),
+        }
+    }
 
-    // Note - This test depends on the specific ordering of the subtests because of
-    // the nature of the channel
-    #[test]
-    fn test_channel_get_random() {
-        let mut source = ProverChannel::default();
-        source.initialize(hex!("0123456789abcded").to_vec().as_slice());
-        let rand_bytes: [u8; 32] = source.get_random();
-        assert_eq!(
-            rand_bytes,
-            hex!("7d84f75ca3e9328b92123c1790834ee0084e02c09b379c6f95c5d2ae8739b9c8")
-        );
-        let rand_int: U256 = source.get_random();
-        assert_eq!(
-            rand_int,
-            u256h!("4ed5f0fd8cffa8dec69beebab09ee881e7369d6d084b90208a079eedc67d2d45")
-        );
-        let rand_element: FieldElement = source.get_random();
-        assert_eq!(
-            rand_element,
-            FieldElement::from_montgomery(u256h!(
-                "0389a47fe0e1e5f9c05d8dcb27b069b67b1c7ec61a5c0a3f54d81aea83d2c8f0"
-            ))
-        );
+    /// Resume verification at `index` with a `coin` that has already absorbed
+    /// `proof[..index]`.
+    ///
+    /// The first `index` bytes count as read, so they can be revisited with
+    /// `seek` without updating the coin again. Intended for reference
+    /// verifiers and fuzzers that construct the coin state by hand.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is beyond the end of the proof.
+    pub fn from_coin(coin: PublicCoin<H>, proof: Vec<u8>, index: usize) -> Self {
+        assert!(index <= proof.len(), "Index beyond end of proof");
+        Self {
+            coin,
+            proof,
+            proof_index: index,
+            read_end: index,
+            endianness: Endianness::default(),
+        }
     }
 
-    // Note - This test depends on the specific ordering of the subtests because of
-    // the nature of the channel
-    #[test]
-    fn test_channel_write() {
-        let mut source = ProverChannel::default();
-        source.initialize(&hex!("0123456789abcded"));
-        let rand_bytes: [u8; 32] = source.get_random();
-        source.write(&rand_bytes[..]);
-        assert_eq!(
-            source.coin.digest,
+    pub fn bytes_remaining(&self) -> usize {
+        self.proof.len() - self.proof_index
+    }
+
+    pub fn at_end(&self) -> bool {
+        self.bytes_remaining() == 0
+    }
+
+    /// Move the read position to `index`.
+    ///
+    /// Every byte of the proof is written to the coin exactly once, when it is
+    /// first read. To keep the coin consistent, it is only possible to seek
+    /// within the part of the proof that has already been read. Reads in that
+    /// part do not update the coin, and must end at or before the end of it.
+    /// After seeking back to the end, reading resumes as normal.
+    pub fn seek(&mut self, index: usize) -> Result<(), Error> {
+        if index > self.read_end {
+            return Err(Error::CoinDesync);
+        }
+        self.proof_index = index;
+        Ok(())
+    }
+}
+
+impl<H: HashFunction, S: ProofSource> VerifierChannel<H, S> {
+    /// Consumes the channel and checks that the entire proof has been read.
+    /// Verifiers should call this to reject proofs with trailing data.
+    pub fn finalize(mut self) -> Result<(), Error> {
+        match self.proof.remaining(self.proof_index)? {
+            0 => Ok(()),
+            remaining => Err(Error::TrailingBytes(remaining)),
+        }
+    }
+
+    pub fn initialize(&mut self, seed: &[u8]) {
+        self.coin.seed(seed);
+    }
+
+    /// See [`PublicCoin::counter`].
+    pub fn counter(&self) -> u64 {
+        self.coin.counter()
+    }
+
+    pub fn get_coefficients(&mut self, n: usize) -> Vec<FieldElement> {
+        self.coin.get_random_many(n)
+    }
+
+    /// Lazily replays up to `count` items. Items are read from the proof and
+    /// written to the coin as the iterator is advanced, in the same order as
+    /// `replay_many`.
+    ///
+    /// # Panics
+    ///
+    /// The iterator panics if the proof ends before an item is read.
+    pub fn replay_iter<T>(&mut self, count: usize) -> impl Iterator<Item = T> + '_
+    where
+        Self: Replayable<T>,
+    {
+        (0..count).map(move |_| self.replay())
+    }
+
+    /// Replays `len` bytes of variable-length data. This is the counterpart
+    /// of writing a `&[u8]` to the `ProverChannel`.
+    ///
+    /// The length is not part of the proof, so prover and verifier need to
+    /// agree on it.
+    pub fn replay_bytes(&mut self, len: usize) -> Result<Vec<u8>, Error> {
+        self.read_bytes(len).map(<[u8]>::to_vec)
+    }
+
+    // This differs from Replayable::<FieldElement>::replay_many in that it only
+    // updates the public coin once, with the contents of the entire layer, instead
+    // of onces for each FieldElement in the layer. Like `try_replay_field`, it
+    // rejects non-canonical encodings.
+    /// # Panics
+    ///
+    /// Panics if the proof is too short or an element is not canonical, see
+    /// [`Self::try_replay_fri_layer`].
+    pub fn replay_fri_layer(&mut self, size: usize) -> Vec<FieldElement> {
+        self.try_replay_fri_layer(size)
+            .expect("Unexpected end of proof")
+    }
+
+    pub fn try_replay_fri_layer(&mut self, size: usize) -> Result<Vec<FieldElement>, Error> {
+        let len = size.checked_mul(32).ok_or(Error::ProofTooShort)?;
+        let endianness = self.endianness;
+        self.read_bytes(len)?
+            .chunks_exact(32)
+            .map(|bytes| field_element_from_proof(endianness.reorder(bytes.try_into().unwrap())))
+            .collect()
+    }
+
+    /// Replays a field element, failing with `NonCanonicalFieldElement` if the
+    /// encoded value is not less than the modulus.
+    ///
+    /// Unlike `Replayable::<FieldElement>::try_replay`, this rejects the
+    /// overflowing encodings that a prover can not produce.
+    pub fn try_replay_field(&mut self) -> Result<FieldElement, Error> {
+        let bytes: [u8; 32] = self.try_replay()?;
+        field_element_from_proof(self.endianness.reorder(bytes))
+    }
+
+    /// Replays `count` values written as a single `&[u64]`. Unlike
+    /// `Replayable::<u64>::replay_many`, this updates the coin once.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the proof ends before all values are read.
+    pub fn replay_u64_many(&mut self, count: usize) -> Vec<u64> {
+        self.try_replay_u64_many(count)
+            .expect("Unexpected end of proof")
+    }
+
+    pub fn try_replay_u64_many(&mut self, count: usize) -> Result<Vec<u64>, Error> {
+        let len = count.checked_mul(8).ok_or(Error::ProofTooShort)?;
+        let endianness = self.endianness;
+        Ok(self
+            .read_bytes(len)?
+            .chunks_exact(8)
+            .map(|bytes| u64::from_be_bytes(endianness.reorder(bytes.try_into().unwrap())))
+            .collect())
+    }
+
+    /// Draws a proof of work challenge with difficulty `pow_bits`, then replays
+    /// the nonce and checks it against the challenge.
+    ///
+    /// A difficulty of zero disables the check, but the challenge is still
+    /// drawn and the nonce read, see [`Constraints::pow_bits`].
+    ///
+    /// Fails with `InvalidPow` if the nonce does not solve the challenge or
+    /// the difficulty is out of range. The coin has absorbed the nonce in
+    /// either case. The check takes the same time for every nonce, see
+    /// [`Challenge::verify_ct`](proof_of_work::Challenge::verify_ct).
+    ///
+    /// [`Constraints::pow_bits`]: crate::Constraints::pow_bits
+    pub fn replay_pow(&mut self, pow_bits: usize) -> Result<u64, Error> {
+        let seed: proof_of_work::ChallengeSeed = self.get_random();
+        let response: proof_of_work::Response = self.try_replay()?;
+        if pow_bits > 0 {
+            let challenge = seed
+                .with_difficulty(pow_bits)
+                .map_err(|_| Error::InvalidPow)?;
+            if !challenge.verify_ct(response) {
+                return Err(Error::InvalidPow);
+            }
+        }
+        #[cfg(feature = "transcript-log")]
+        self.coin.log_event(TranscriptEvent::Pow {
+            bits:  pow_bits,
+            nonce: response.nonce(),
+        });
+        Ok(response.nonce())
+    }
+
+    /// Replays `count` Merkle roots written as a single `&[(usize, [u8; 32])]`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the proof ends before all roots are read, or an index does
+    /// not fit a `usize`.
+    pub fn replay_indexed_roots(&mut self, count: usize) -> Vec<(usize, [u8; 32])> {
+        self.try_replay_indexed_roots(count)
+            .expect("Unexpected end of proof")
+    }
+
+    /// Fails with `InvalidEncoding` if an index does not fit the platform
+    /// `usize`. The indices are not otherwise checked.
+    pub fn try_replay_indexed_roots(
+        &mut self,
+        count: usize,
+    ) -> Result<Vec<(usize, [u8; 32])>, Error> {
+        let len = count.checked_mul(40).ok_or(Error::ProofTooShort)?;
+        self.read_bytes(len)?
+            .chunks_exact(40)
+            .map(|bytes| {
+                let index = u64::from_be_bytes(bytes[..8].try_into().unwrap());
+                let index = usize::try_from(index).map_err(|_| Error::InvalidEncoding)?;
+                Ok((index, bytes[8..].try_into().unwrap()))
+            })
+            .collect()
+    }
+
+    /// The current read position in the proof.
+    pub fn tell(&self) -> usize {
+        self.proof_index
+    }
+
+    /// Replays field elements written with
+    /// `ProverChannel::write_with_length`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the proof ends before all elements are read.
+    pub fn replay_length_prefixed(&mut self) -> Vec<FieldElement> {
+        self.try_replay_length_prefixed()
+            .expect("Unexpected end of proof")
+    }
+
+    /// Fails with `ProofTooShort` if the proof has fewer elements than the
+    /// length prefix claims. Nothing is absorbed by the coin on failure.
+    pub fn try_replay_length_prefixed(&mut self) -> Result<Vec<FieldElement>, Error> {
+        let endianness = self.endianness;
+        self.read_length_prefixed(32)?
+            .chunks_exact(32)
+            .map(|bytes| field_element_from_proof(endianness.reorder(bytes.try_into().unwrap())))
+            .collect()
+    }
+
+    /// Reads the next `count` bytes from the proof and writes them to the
+    /// coin, unless they have been read before. The proof index is not
+    /// advanced on failure.
+    fn read_bytes(&mut self, count: usize) -> Result<&[u8], Error> {
+        self.read_bytes_labeled(None, count)
+    }
+
+    /// Reads a big-endian `u64` count followed by `count` items of `item_size`
+    /// bytes, and returns the items. The coin absorbs the count and the items
+    /// in one update.
+    ///
+    /// Both parts are read from the source in order, so this also works for
+    /// streaming sources. The proof index is not advanced and nothing is
+    /// absorbed on failure.
+    fn read_length_prefixed(&mut self, item_size: usize) -> Result<Vec<u8>, Error> {
+        let from = self.proof_index;
+        let prefix: [u8; 8] = self.proof.read_at(from, 8)?.try_into().unwrap();
+        let count =
+            usize::try_from(u64::from_be_bytes(prefix)).map_err(|_| Error::InvalidEncoding)?;
+        let len = count.checked_mul(item_size).ok_or(Error::ProofTooShort)?;
+        let to = (from + 8).checked_add(len).ok_or(Error::ProofTooShort)?;
+        let seen = from < self.read_end;
+        if seen && to > self.read_end {
+            return Err(Error::CoinDesync);
+        }
+        let items = self.proof.read_at(from + 8, len)?.to_vec();
+        self.proof_index = to;
+        if !seen {
+            self.coin.write(&[&prefix[..], &items].concat()[..]);
+            self.read_end = to;
+        }
+        Ok(items)
+    }
+
+    /// Like `read_bytes`, but the coin absorbs `label` before the bytes. See
+    /// [`PublicCoin::write_labeled`].
+    fn read_bytes_labeled(&mut self, label: Option<u8>, count: usize) -> Result<&[u8], Error> {
+        let from = self.proof_index;
+        let to = from.checked_add(count).ok_or(Error::ProofTooShort)?;
+        let seen = from < self.read_end;
+        if seen && to > self.read_end {
+            return Err(Error::CoinDesync);
+        }
+        let bytes = self.proof.read_at(from, count)?;
+        self.proof_index = to;
+        if !seen {
+            match label {
+                Some(label) => self.coin.write_labeled(label, bytes),
+                None => self.coin.write(bytes),
+            }
+            self.read_end = to;
+        }
+        Ok(bytes)
+    }
+}
+
+/// Configures the public coin of a prover or verifier channel.
+///
+/// The defaults are Keccak256, an empty seed and
+/// [`proof_of_work::DEFAULT_PREFIX`]. Building with only a seed gives the same
+/// channel as `default()` followed by `initialize(seed)`. Prover and verifier
+/// need to be built with the same configuration. [`prove`](crate::prove) and
+/// [`verify`](crate::verify) configure it from the [`Constraints`].
+///
+/// [`Constraints`]: crate::Constraints
+#[derive(PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct ChannelBuilder<H: HashFunction = Keccak256> {
+    grinding_prefix: Vec<u8>,
+    seed:            Vec<u8>,
+    endianness:      Endianness,
+    hash:            PhantomData<H>,
+}
+
+impl Default for ChannelBuilder {
+    fn default() -> Self {
+        Self {
+            grinding_prefix: proof_of_work::DEFAULT_PREFIX.to_vec(),
+            seed:            Vec::new(),
+            endianness:      Endianness::default(),
+            hash:            PhantomData,
+        }
+    }
+}
+
+impl<H: HashFunction> ChannelBuilder<H> {
+    /// Use `H2` as the hash function of the coin.
+    pub fn hash<H2: HashFunction>(self) -> ChannelBuilder<H2> {
+        ChannelBuilder {
+            grinding_prefix: self.grinding_prefix,
+            seed:            self.seed,
+            endianness:      self.endianness,
+            hash:            PhantomData,
+        }
+    }
+
+    /// See [`PublicCoin::set_grinding_prefix`].
+    pub fn grinding_prefix(mut self, prefix: &[u8]) -> Self {
+        self.grinding_prefix = prefix.to_vec();
+        self
+    }
+
+    pub fn seed(mut self, seed: &[u8]) -> Self {
+        self.seed = seed.to_vec();
+        self
+    }
+
+    /// See [`Endianness`].
+    pub fn endianness(mut self, endianness: Endianness) -> Self {
+        self.endianness = endianness;
+        self
+    }
+
+    pub fn build_coin(&self) -> PublicCoin<H> {
+        let mut coin = PublicCoin::default();
+        coin.set_grinding_prefix(&self.grinding_prefix);
+        coin.seed(&self.seed);
+        coin
+    }
+
+    pub fn build_prover(&self) -> ProverChannel<H> {
+        let coin = self.build_coin();
+        ProverChannel {
+            seed_digest: coin.digest,
+            coin,
+            proof: Vec::new(),
+            endianness: self.endianness,
+        }
+    }
+
+    /// Builds a prover that writes the proof to `sink` as it is produced.
+    /// The bytes and coin state are the same as for [`Self::build_prover`].
+    #[cfg(feature = "std")]
+    pub fn build_streaming_prover<W: io::Write>(
+        &self,
+        sink: W,
+    ) -> ProverChannel<H, StreamingSink<W>> {
+        let coin = self.build_coin();
+        ProverChannel {
+            seed_digest: coin.digest,
+            coin,
+            proof: StreamingSink {
+                writer: Some(sink),
+                error:  None,
+            },
+            endianness: self.endianness,
+        }
+    }
+
+    pub fn build_verifier(&self, proof: Vec<u8>) -> VerifierChannel<H> {
+        VerifierChannel {
+            coin: self.build_coin(),
+            proof,
+            proof_index: 0,
+            read_end: 0,
+            endianness: self.endianness,
+        }
+    }
+
+    /// Builds a verifier that reads the proof from `reader` as it is replayed.
+    /// The coin state is the same as for [`Self::build_verifier`].
+    #[cfg(feature = "std")]
+    pub fn build_streaming_verifier<R: Read>(
+        &self,
+        reader: R,
+    ) -> VerifierChannel<H, StreamingSource<R>> {
+        VerifierChannel {
+            coin:        self.build_coin(),
+            proof:       StreamingSource {
+                reader,
+                position: 0,
+                buffer: Vec::new(),
+            },
+            proof_index: 0,
+            read_end:    0,
+            endianness:  self.endianness,
+        }
+    }
+}
+
+impl<H: HashFunction> RandomGenerator<proof_of_work::ChallengeSeed> for PublicCoin<H> {
+    fn get_random(&mut self) -> proof_of_work::ChallengeSeed {
+        self.assert_unsealed();
+        self.counter += 1;
+        #[cfg(feature = "transcript-log")]
+        self.log_event(TranscriptEvent::Draw {
+            kind:  "ChallengeSeed",
+            value: self.digest.to_vec(),
+        });
+        // FIX: Use get_random::<[u8;32]>();
+        proof_of_work::ChallengeSeed::from_bytes_with_prefix(self.digest, &self.grinding_prefix)
+    }
+}
+
+impl<H: HashFunction, S: ProofSink> Writable<proof_of_work::Response> for ProverChannel<H, S> {
+    fn write(&mut self, data: proof_of_work::Response) {
+        self.write(data.nonce());
+    }
+}
+
+impl<H: HashFunction, S: ProofSource> Replayable<proof_of_work::Response>
+    for VerifierChannel<H, S>
+{
+    fn try_replay(&mut self) -> Result<proof_of_work::Response, Error> {
+        let nonce: u64 = self.try_replay()?;
+        Ok(proof_of_work::Response::from_nonce(nonce))
+    }
+}
+
+/// Encoded as 8 bytes in the channel's [`Endianness`], big-endian by default.
+impl<H: HashFunction, S: ProofSink> Writable<u64> for ProverChannel<H, S> {
+    fn write(&mut self, data: u64) {
+        let bytes = self.endianness.reorder(data.to_be_bytes());
+        self.write(&bytes[..]);
+    }
+}
+
+/// Encoded as 8 bytes per element, absorbed into the coin as one
+/// contiguous write. This results in the same proof bytes as writing the
+/// elements one by one, but in a different coin state. Replay with
+/// `VerifierChannel::replay_u64_many`.
+impl<H: HashFunction, S: ProofSink> Writable<&[u64]> for ProverChannel<H, S> {
+    fn write(&mut self, data: &[u64]) {
+        let bytes: Vec<u8> = data
+            .iter()
+            .flat_map(|value| self.endianness.reorder(value.to_be_bytes()))
+            .collect();
+        self.write(&bytes[..]);
+    }
+}
+
+/// Encoded as 8 bytes in two's complement, i.e. the same bytes as the `u64`
+/// with the same bit pattern.
+impl<H: HashFunction, S: ProofSink> Writable<i64> for ProverChannel<H, S> {
+    fn write(&mut self, data: i64) {
+        let bytes = self.endianness.reorder(data.to_be_bytes());
+        self.write(&bytes[..]);
+    }
+}
+
+/// Encoded as 4 bytes in the channel's [`Endianness`].
+impl<H: HashFunction, S: ProofSink> Writable<u32> for ProverChannel<H, S> {
+    fn write(&mut self, data: u32) {
+        let bytes = self.endianness.reorder(data.to_be_bytes());
+        self.write(&bytes[..]);
+    }
+}
+
+/// Encoded as a `u64`, i.e. 8 bytes, independent of the platform.
+impl<H: HashFunction, S: ProofSink> Writable<usize> for ProverChannel<H, S> {
+    fn write(&mut self, data: usize) {
+        // `usize` is at most 64 bits on all supported platforms.
+        self.write(data as u64);
+    }
+}
+
+/// Encoded as a single byte, `0x00` for `false` and `0x01` for `true`.
+impl<H: HashFunction, S: ProofSink> Writable<bool> for ProverChannel<H, S> {
+    fn write(&mut self, data: bool) {
+        self.write(&[u8::from(data)][..]);
+    }
+}
+
+impl<H: HashFunction, S: ProofSource> Replayable<u64> for VerifierChannel<H, S> {
+    fn try_replay(&mut self) -> Result<u64, Error> {
+        let bytes = self.read_bytes(8)?.try_into().unwrap();
+        Ok(u64::from_be_bytes(self.endianness.reorder(bytes)))
+    }
+}
+
+impl<H: HashFunction, S: ProofSource> Replayable<i64> for VerifierChannel<H, S> {
+    fn try_replay(&mut self) -> Result<i64, Error> {
+        let bytes = self.read_bytes(8)?.try_into().unwrap();
+        Ok(i64::from_be_bytes(self.endianness.reorder(bytes)))
+    }
+}
+
+impl<H: HashFunction, S: ProofSource> Replayable<u32> for VerifierChannel<H, S> {
+    fn try_replay(&mut self) -> Result<u32, Error> {
+        let bytes = self.read_bytes(4)?.try_into().unwrap();
+        Ok(u32::from_be_bytes(self.endianness.reorder(bytes)))
+    }
+}
+
+/// Fails with `InvalidEncoding` if the value does not fit the platform
+/// `usize`.
+impl<H: HashFunction, S: ProofSource> Replayable<usize> for VerifierChannel<H, S> {
+    fn try_replay(&mut self) -> Result<usize, Error> {
+        let value: u64 = self.try_replay()?;
+        usize::try_from(value).map_err(|_| Error::InvalidEncoding)
+    }
+}
+
+/// Fails with `InvalidEncoding` on any byte other than `0x00` or `0x01`.
+impl<H: HashFunction, S: ProofSource> Replayable<bool> for VerifierChannel<H, S> {
+    fn try_replay(&mut self) -> Result<bool, Error> {
+        match self.read_bytes(1)? {
+            [0] => Ok(false),
+            [1] => Ok(true),
+            _ => Err(Error::InvalidEncoding),
+        }
+    }
+}
+
+impl<H: HashFunction> RandomGenerator<FieldElement> for PublicCoin<H> {
+    fn get_random(&mut self) -> FieldElement {
+        loop {
+            let bytes = self.draw_bytes();
+            if let Some(element) = field_element_from_random(&bytes) {
+                #[cfg(feature = "transcript-log")]
+                self.log_event(TranscriptEvent::Draw {
+                    kind:  "FieldElement",
+                    value: element.to_montgomery_bytes_be().to_vec(),
+                });
+                break element;
+            }
+        }
+    }
+}
+
+/// Draws `N` field elements, the same as `N` calls to `get_random`.
+impl<H: HashFunction, const N: usize> RandomGenerator<[FieldElement; N]> for PublicCoin<H> {
+    fn get_random(&mut self) -> [FieldElement; N] {
+        match self.get_random_many(N).try_into() {
+            Ok(array) => array,
+            Err(_) => unreachable!(),
+        }
+    }
+}
+
+impl<H: HashFunction> RandomGenerator<U256> for PublicCoin<H> {
+    fn get_random(&mut self) -> U256 {
+        let bytes = self.draw_bytes();
+        #[cfg(feature = "transcript-log")]
+        self.log_event(TranscriptEvent::Draw {
+            kind:  "U256",
+            value: bytes.to_vec(),
+        });
+        U256::from_bytes_be(&bytes)
+    }
+}
+
+impl<H: HashFunction> RandomGenerator<[u8; 32]> for PublicCoin<H> {
+    fn get_random(&mut self) -> [u8; 32] {
+        let bytes = self.draw_bytes();
+        #[cfg(feature = "transcript-log")]
+        self.log_event(TranscriptEvent::Draw {
+            kind:  "bytes",
+            value: bytes.to_vec(),
+        });
+        bytes
+    }
+}
+
+impl<H: HashFunction> PublicCoin<H> {
+    /// The next 32 random bytes, without logging them.
+    fn draw_bytes(&mut self) -> [u8; 32] {
+        self.assert_unsealed();
+        let mut result = [0; 32];
+        let mut hasher = H::hasher();
+        hasher.update(&self.digest);
+        hasher.update(&[0_u8; 24]);
+        hasher.update(&self.counter.to_be_bytes());
+        hasher.finalize(&mut result);
+        self.counter += 1;
+        result
+    }
+
+    /// Absorb `data` prefixed with a type `label`.
+    ///
+    /// The label is not part of the proof, it only separates the transcript
+    /// of typed values from raw bytes with the same encoding.
+    fn write_labeled(&mut self, label: u8, data: &[u8]) {
+        let mut hasher = H::hasher();
+        hasher.update(&self.digest);
+        hasher.update(&[label]);
+        hasher.update(data);
+        hasher.finalize(&mut self.digest);
+        self.counter = 0;
+        self.record(data.len());
+        #[cfg(feature = "transcript-log")]
+        self.log_event(TranscriptEvent::Write {
+            kind:  "labeled",
+            bytes: [&[label][..], data].concat(),
+        });
+    }
+}
+
+impl<H: HashFunction, S: ProofSink, T> RandomGenerator<T> for ProverChannel<H, S>
+where
+    PublicCoin<H>: RandomGenerator<T>,
+{
+    fn get_random(&mut self) -> T {
+        self.coin.get_random()
+    }
+}
+
+impl<H: HashFunction, S: ProofSource, T> RandomGenerator<T> for VerifierChannel<H, S>
+where
+    PublicCoin<H>: RandomGenerator<T>,
+{
+    fn get_random(&mut self) -> T {
+        self.coin.get_random()
+    }
+}
+
+/// A [`RandomGenerator`] that returns a fixed sequence of values instead of
+/// hashing, so tests can control exactly which challenges are drawn.
+///
+/// Every draw, of any type, consumes the next value. Field element draws read
+/// the value in Montgomery form, the same mapping `PublicCoin` applies to its
+/// random bytes, so a sequence of values models the real coin's draws.
+///
+/// # Panics
+///
+/// Draws panic when the sequence is exhausted, and field element draws panic
+/// if the value is not less than the modulus.
+#[cfg(any(test, feature = "test-utils"))]
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct MockCoin {
+    values: Vec<U256>,
+    next:   usize,
+}
+
+#[cfg(any(test, feature = "test-utils"))]
+impl MockCoin {
+    /// Field element draws take these values in Montgomery form, so
+    /// `MockCoin::new(vec![U256::from(5)])` draws
+    /// `FieldElement::from_montgomery(U256::from(5))`, not
+    /// `FieldElement::from(5)`. Use [`MockCoin::from_field_elements`] to draw
+    /// given field elements.
+    pub fn new(values: Vec<U256>) -> Self {
+        Self { values, next: 0 }
+    }
+
+    /// A coin whose field element draws return `elements` in order.
+    pub fn from_field_elements(elements: &[FieldElement]) -> Self {
+        Self::new(
+            elements
+                .iter()
+                .map(|element| element.as_montgomery().clone())
+                .collect(),
+        )
+    }
+
+    /// The number of values drawn so far.
+    pub fn drawn(&self) -> usize {
+        self.next
+    }
+
+    fn next_value(&mut self) -> &U256 {
+        let value = self.values.get(self.next).expect("MockCoin exhausted");
+        self.next += 1;
+        value
+    }
+}
+
+#[cfg(any(test, feature = "test-utils"))]
+impl RandomGenerator<U256> for MockCoin {
+    fn get_random(&mut self) -> U256 {
+        self.next_value().clone()
+    }
+}
+
+#[cfg(any(test, feature = "test-utils"))]
+impl RandomGenerator<[u8; 32]> for MockCoin {
+    fn get_random(&mut self) -> [u8; 32] {
+        self.next_value().to_bytes_be()
+    }
+}
+
+#[cfg(any(test, feature = "test-utils"))]
+impl RandomGenerator<FieldElement> for MockCoin {
+    fn get_random(&mut self) -> FieldElement {
+        let value = self.next_value();
+        assert!(
+            value < &FieldElement::MODULUS,
+            "MockCoin value out of range"
+        );
+        FieldElement::from_montgomery(value.clone())
+    }
+}
+
+impl<H: HashFunction> PublicCoin<H> {
+    /// Absorb each item as a separate write, in order.
+    ///
+    /// This is the coin state of a channel after writing each item as a
+    /// `&[u8]`, which differs from writing them as a single `&[&[u8]]`.
+    pub fn absorb_sequence(&mut self, items: &[&[u8]]) {
+        for item in items {
+            self.write(*item);
+        }
+    }
+}
+
+impl<H: HashFunction> Writable<&[u8]> for PublicCoin<H> {
+    fn write(&mut self, data: &[u8]) {
+        let mut result: [u8; 32] = [0; 32];
+        let mut hasher = H::hasher();
+        hasher.update(&self.digest);
+        hasher.update(data);
+        hasher.finalize(&mut result);
+        // FIX: Hash counter into digest.
+        self.digest = result;
+        self.counter = 0;
+        self.record(data.len());
+        #[cfg(feature = "transcript-log")]
+        self.log_event(TranscriptEvent::Write {
+            kind:  "bytes",
+            bytes: data.to_vec(),
+        });
+    }
+}
+
+// Note - that this default implementation allows writing a sequence of &[u8] to
+// the proof with the same encoding for the writing and the non writing. However
+// by writing directly to the coin, other writes for the channel could separate
+// encoding from random perturbation.
+impl<H: HashFunction, S: ProofSink> Writable<&[u8]> for ProverChannel<H, S> {
+    fn write(&mut self, data: &[u8]) {
+        self.proof.write_bytes(data);
+        self.coin.write(data);
+    }
+}
+
+// Note -- This absorbs all slices in a single coin update, the same as writing
+// their concatenation. This is a different transcript from writing the slices
+// one by one, so prover and verifier need to agree on the grouping.
+impl<H: HashFunction, S: ProofSink> Writable<&[&[u8]]> for ProverChannel<H, S> {
+    fn write(&mut self, data: &[&[u8]]) {
+        let len = data.iter().map(|slice| slice.len()).sum();
+        self.proof.reserve(len);
+        let mut hasher = H::hasher();
+        hasher.update(&self.coin.digest);
+        for slice in data {
+            hasher.update(slice);
+            self.proof.write_bytes(slice);
+        }
+        hasher.finalize(&mut self.coin.digest);
+        self.coin.counter = 0;
+        self.coin.record(len);
+        #[cfg(feature = "transcript-log")]
+        self.coin.log_event(TranscriptEvent::Write {
+            kind:  "bytes",
+            bytes: data.concat(),
+        });
+    }
+}
+
+impl<H: HashFunction, S: ProofSink> Writable<MerkleRoot> for ProverChannel<H, S> {
+    fn write(&mut self, data: MerkleRoot) {
+        self.proof.write_bytes(&data.0);
+        self.coin.write_labeled(MerkleRoot::LABEL, &data.0);
+    }
+}
+
+impl<H: HashFunction, S: ProofSink> Writable<&Hash> for ProverChannel<H, S> {
+    fn write(&mut self, data: &Hash) {
+        self.write(data.as_bytes());
+    }
+}
+
+impl<H: HashFunction, S: ProofSink> Writable<&zkp_merkle_tree::Commitment> for ProverChannel<H, S> {
+    fn write(&mut self, data: &zkp_merkle_tree::Commitment) {
+        self.write(data.hash())
+    }
+}
+
+impl<H: HashFunction, S: ProofSink> Writable<&zkp_merkle_tree::Proof> for ProverChannel<H, S> {
+    fn write(&mut self, data: &zkp_merkle_tree::Proof) {
+        for hash in data.hashes() {
+            self.write(hash)
+        }
+    }
+}
+
+/// Encoded as the index as a `u64` followed by the root, for every pair. All
+/// pairs are absorbed in one coin update. Binding each root to its index makes
+/// reordering the roots change the transcript. Replay with
+/// `VerifierChannel::replay_indexed_roots`.
+impl<H: HashFunction, S: ProofSink> Writable<&[(usize, [u8; 32])]> for ProverChannel<H, S> {
+    fn write(&mut self, data: &[(usize, [u8; 32])]) {
+        let indices: Vec<[u8; 8]> = data
+            .iter()
+            .map(|(index, _)| (*index as u64).to_be_bytes())
+            .collect();
+        let slices: Vec<&[u8]> = indices
+            .iter()
+            .zip(data)
+            .flat_map(|(index, (_, root))| vec![&index[..], &root[..]])
+            .collect();
+        self.write(&slices[..]);
+    }
+}
+
+impl<H: HashFunction, S: ProofSink> Writable<&MerklePath> for ProverChannel<H, S> {
+    fn write(&mut self, data: &MerklePath) {
+        let prefix = (data.0.len() as u64).to_be_bytes();
+        let mut slices: Vec<&[u8]> = Vec::with_capacity(data.0.len() + 1);
+        slices.push(&prefix);
+        slices.extend(data.0.iter().map(|hash| &hash[..]));
+        self.write(&slices[..]);
+    }
+}
+
+// Note -- This updates the coin once with the entire slice. This results in the
+// same proof bytes as writing the elements one by one, but in a different coin
+// state. See `ProverChannel::write_elements_grouped`.
+impl<H: HashFunction, S: ProofSink> Writable<&[FieldElement]> for ProverChannel<H, S> {
+    fn write(&mut self, data: &[FieldElement]) {
+        // Equivalent to writing the concatenated bytes, but streams them into
+        // the proof and hasher directly.
+        self.proof.reserve(32 * data.len());
+        let mut hasher = H::hasher();
+        hasher.update(&self.coin.digest);
+        for element in data {
+            let bytes = self.endianness.reorder(element.to_montgomery_bytes_be());
+            hasher.update(&bytes);
+            self.proof.write_bytes(&bytes);
+        }
+        hasher.finalize(&mut self.coin.digest);
+        self.coin.counter = 0;
+        self.coin.record(32 * data.len());
+        #[cfg(feature = "transcript-log")]
+        self.coin.log_event(TranscriptEvent::Write {
+            kind:  "bytes",
+            bytes: data
+                .iter()
+                .flat_map(|element| self.endianness.reorder(element.to_montgomery_bytes_be()))
+                .collect(),
+        });
+    }
+}
+
+// Note -- Field elements are written in Montgomery form, not canonical form.
+// The Solidity verifier reads them the same way, so this is part of the proof
+// format. Use `FieldElement::to_canonical_bytes_be` for external encodings.
+impl<H: HashFunction, S: ProofSink> Writable<&FieldElement> for ProverChannel<H, S> {
+    fn write(&mut self, data: &FieldElement) {
+        let bytes = self.endianness.reorder(data.to_montgomery_bytes_be());
+        self.write(&bytes[..]);
+    }
+}
+
+// Note -- This method of writing is distinct from the field element, and is
+// used in the decommitment when groups are decommited from the rows. Each
+// element updates the coin separately, so unlike `&[FieldElement]` the coin
+// digest depends on the number of elements written.
+impl<H: HashFunction, S: ProofSink> Writable<Vec<U256>> for ProverChannel<H, S> {
+    fn write(&mut self, data: Vec<U256>) {
+        self.proof.reserve(32 * data.len());
+        for element in data {
+            self.write(element)
+        }
+    }
+}
+
+impl<H: HashFunction, S: ProofSink> Writable<U256> for ProverChannel<H, S> {
+    fn write(&mut self, data: U256) {
+        let bytes = self.endianness.reorder(data.to_bytes_be());
+        self.write(&bytes[..]);
+    }
+}
+
+impl<H: HashFunction, S: ProofSource> Replayable<[u8; 32]> for VerifierChannel<H, S> {
+    fn try_replay(&mut self) -> Result<[u8; 32], Error> {
+        // OPT: Use arrayref crate or similar to avoid copy
+        let bytes = self.read_bytes(32)?;
+        Ok(bytes.try_into().unwrap())
+    }
+}
+
+impl<H: HashFunction, S: ProofSource> Replayable<MerkleRoot> for VerifierChannel<H, S> {
+    fn try_replay(&mut self) -> Result<MerkleRoot, Error> {
+        let bytes = self.read_bytes_labeled(Some(MerkleRoot::LABEL), 32)?;
+        Ok(MerkleRoot(bytes.try_into().unwrap()))
+    }
+}
+
+/// Fails with `ProofTooShort` if the proof has fewer hashes than the length
+/// prefix claims. Nothing is absorbed by the coin on failure.
+impl<H: HashFunction, S: ProofSource> Replayable<MerklePath> for VerifierChannel<H, S> {
+    fn try_replay(&mut self) -> Result<MerklePath, Error> {
+        Ok(MerklePath(
+            self.read_length_prefixed(32)?
+                .chunks_exact(32)
+                .map(|hash| hash.try_into().unwrap())
+                .collect(),
+        ))
+    }
+}
+
+impl<H: HashFunction, S: ProofSource> Replayable<Hash> for VerifierChannel<H, S> {
+    fn try_replay(&mut self) -> Result<Hash, Error> {
+        let hash: [u8; 32] = self.try_replay()?;
+        Ok(Hash::new(hash))
+    }
+}
+
+impl<H: HashFunction, S: ProofSource> Replayable<U256> for VerifierChannel<H, S> {
+    fn try_replay(&mut self) -> Result<U256, Error> {
+        let bytes: [u8; 32] = self.try_replay()?;
+        Ok(U256::from_bytes_be(&self.endianness.reorder(bytes)))
+    }
+}
+
+// Note -- This does not check that the value is less than the modulus. Such
+// values are out of range for `FieldElement`, and will only trip a debug
+// assertion. See `VerifierChannel::try_replay_field` for a checked version.
+impl<H: HashFunction, S: ProofSource> Replayable<FieldElement> for VerifierChannel<H, S> {
+    fn try_replay(&mut self) -> Result<FieldElement, Error> {
+        let montgomery_modulus: U256 = self.try_replay()?;
+        Ok(FieldElement::from_montgomery(montgomery_modulus))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zkp_macros_decl::u256h;
+    use zkp_primefield::One;
+
+    #[test]
+    fn test_keccak256_hash() {
+        assert_eq!(
+            Keccak256::hash(&[]),
+            hex!("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470")
+        );
+    }
+
+    #[test]
+    fn test_blake2s256_hash() {
+        assert_eq!(
+            Blake2s256::hash(&[]),
+            hex!("69217a3079908094e11121d042354a7c1f55b6482ca1a51e1b250dfd1ed0eef9")
+        );
+    }
+
+    #[test]
+    fn blake2s_channel_test() {
+        let mut source: ProverChannel<Blake2s256> = ProverChannel::default();
+        source.initialize(&hex!("0123456789abcded"));
+        let rand_bytes: [u8; 32] = source.get_random();
+        source.write(&rand_bytes[..]);
+        let rand_element: FieldElement = source.get_random();
+        source.write(&rand_element);
+        let pow_seed: proof_of_work::ChallengeSeed = source.get_random();
+        let pow_challenge = pow_seed.with_difficulty(8).unwrap();
+        let pow_response = pow_challenge.solve();
+        source.write(pow_response);
+        let rand_int: U256 = source.get_random();
+
+        let mut verifier = source.clone().into_verifier(&hex!("0123456789abcded")).unwrap();
+        let bytes_test: [u8; 32] = verifier.get_random();
+        assert_eq!(bytes_test, rand_bytes);
+        let bytes_replayed: [u8; 32] = verifier.replay();
+        assert_eq!(bytes_replayed, rand_bytes);
+        let element_test: FieldElement = verifier.get_random();
+        assert_eq!(element_test, rand_element);
+        assert_eq!(
+            Replayable::<FieldElement>::replay(&mut verifier),
+            rand_element
+        );
+        let pow_seed_test: proof_of_work::ChallengeSeed = verifier.get_random();
+        let pow_challenge_test = pow_seed_test.with_difficulty(8).unwrap();
+        assert_eq!(pow_challenge_test, pow_challenge);
+        let pow_response_test: proof_of_work::Response = verifier.replay();
+        assert_eq!(pow_response_test, pow_response);
+        assert!(pow_challenge_test.verify(pow_response_test));
+        let int_test: U256 = verifier.get_random();
+        assert_eq!(int_test, rand_int);
+        assert_eq!(verifier.coin.digest, source.coin.digest);
+
+        // The transcript differs from the one produced with Keccak256.
+        let mut keccak: ProverChannel = ProverChannel::default();
+        keccak.initialize(&hex!("0123456789abcded"));
+        assert_ne!(keccak.coin.digest, source.coin.digest);
+    }
+
+    // Note - This test depends on the specific ordering of the subtests because of
+    // the nature of the channel
+    #[test]
+    fn test_channel_get_random() {
+        let mut source: ProverChannel = ProverChannel::default();
+        source.initialize(hex!("0123456789abcded").to_vec().as_slice());
+        let rand_bytes: [u8; 32] = source.get_random();
+        assert_eq!(
+            rand_bytes,
+            hex!("7d84f75ca3e9328b92123c1790834ee0084e02c09b379c6f95c5d2ae8739b9c8")
+        );
+        let rand_int: U256 = source.get_random();
+        assert_eq!(
+            rand_int,
+            u256h!("4ed5f0fd8cffa8dec69beebab09ee881e7369d6d084b90208a079eedc67d2d45")
+        );
+        let rand_element: FieldElement = source.get_random();
+        assert_eq!(
+            rand_element,
+            FieldElement::from_montgomery(u256h!(
+                "0389a47fe0e1e5f9c05d8dcb27b069b67b1c7ec61a5c0a3f54d81aea83d2c8f0"
+            ))
+        );
+    }
+
+    // Note - This test depends on the specific ordering of the subtests because of
+    // the nature of the channel
+    #[test]
+    fn test_channel_write() {
+        let mut source: ProverChannel = ProverChannel::default();
+        source.initialize(&hex!("0123456789abcded"));
+        let rand_bytes: [u8; 32] = source.get_random();
+        source.write(&rand_bytes[..]);
+        assert_eq!(
+            source.coin.digest,
+            hex!("3174a00d031bc8deff799e24a78ee347b303295a6cb61986a49873d9b6f13a0d")
+        );
+        source.write(proof_of_work::Response::from_nonce(11_028_357_238_u64));
+        assert_eq!(
+            source.coin.digest,
+            hex!("21571e2a323daa1e6f2adda87ce912608e1325492d868e8fe41626633d6acb93")
+        );
+        source.write(&FieldElement::from_montgomery(u256h!(
+            "0389a47fe0e1e5f9c05d8dcb27b069b67b1c7ec61a5c0a3f54d81aea83d2c8f0"
+        )));
+        assert_eq!(
+            source.coin.digest,
+            hex!("34a12938f047c34da72b5949434950fa2b24220270fd26e6f64b6eb5e86c6626")
+        );
+        source.write(
+            vec![
+                FieldElement::from_montgomery(u256h!(
+                    "0389a47fe0e1e5f9c05d8dcb27b069b67b1c7ec61a5c0a3f54d81aea83d2c8f0"
+                )),
+                FieldElement::from_montgomery(u256h!(
+                    "029ab47fe0e1a5f9c05d8dcb27b069b67b1c7ec61a5c0a3f54d81aea83d2c8f0"
+                )),
+            ]
+            .as_slice(),
+        );
+        assert_eq!(
+            source.coin.digest,
+            hex!("586b2c12cd444cfe29932fcb167fc0be2e575a8d68e4a41d35de8602b0aea929")
+        );
+    }
+
+    #[cfg(feature = "transcript-log")]
+    #[test]
+    fn test_channel_write_events() {
+        use TranscriptEvent::*;
+        let element = FieldElement::from_montgomery(u256h!(
+            "0389a47fe0e1e5f9c05d8dcb27b069b67b1c7ec61a5c0a3f54d81aea83d2c8f0"
+        ));
+        let other = FieldElement::from_montgomery(u256h!(
+            "029ab47fe0e1a5f9c05d8dcb27b069b67b1c7ec61a5c0a3f54d81aea83d2c8f0"
+        ));
+        let mut source: ProverChannel = ProverChannel::default();
+        source.initialize(&hex!("0123456789abcded"));
+        let rand_bytes: [u8; 32] = source.get_random();
+        source.write(&rand_bytes[..]);
+        source.write(proof_of_work::Response::from_nonce(11_028_357_238_u64));
+        source.write(&element);
+        source.write(vec![element.clone(), other.clone()].as_slice());
+        let bytes = |element: &FieldElement| element.to_montgomery_bytes_be().to_vec();
+        assert_eq!(source.events(), &[
+            Draw {
+                kind:  "bytes",
+                value: rand_bytes.to_vec(),
+            },
+            Write {
+                kind:  "bytes",
+                bytes: rand_bytes.to_vec(),
+            },
+            Write {
+                kind:  "bytes",
+                bytes: 11_028_357_238_u64.to_be_bytes().to_vec(),
+            },
+            Write {
+                kind:  "bytes",
+                bytes: bytes(&element),
+            },
+            Write {
+                kind:  "bytes",
+                bytes: [bytes(&element), bytes(&other)].concat(),
+            },
+        ]);
+
+        // Typed draws and proof of work
+        let seed: proof_of_work::ChallengeSeed = source.get_random();
+        let challenge = seed.with_difficulty(8).unwrap();
+        let response = challenge.solve();
+        source.write_pow(8, response);
+        let drawn: FieldElement = source.get_random();
+        source.write(MerkleRoot(rand_bytes));
+        assert_eq!(source.events()[5..], [
+            Draw {
+                kind:  "ChallengeSeed",
+                value: hex!("586b2c12cd444cfe29932fcb167fc0be2e575a8d68e4a41d35de8602b0aea929")
+                    .to_vec(),
+            },
+            Write {
+                kind:  "bytes",
+                bytes: response.nonce().to_be_bytes().to_vec(),
+            },
+            Pow {
+                bits:  8,
+                nonce: response.nonce(),
+            },
+            Draw {
+                kind:  "FieldElement",
+                value: bytes(&drawn),
+            },
+            Write {
+                kind:  "labeled",
+                bytes: [&[MerkleRoot::LABEL][..], &rand_bytes[..]].concat(),
+            },
+        ]);
+
+        // The verifier coin logs the same writes and draws.
+        let mut verifier = source.clone().into_verifier(&hex!("0123456789abcded")).unwrap();
+        let _: [u8; 32] = verifier.get_random();
+        let _: [u8; 32] = verifier.replay();
+        let _: proof_of_work::Response = verifier.replay();
+        let _: FieldElement = verifier.replay();
+        let _ = verifier.replay_fri_layer(2);
+        assert_eq!(verifier.coin.events[..], source.events()[..5]);
+    }
+
+    #[test]
+    fn verifier_channel_test() {
+        let mut source: ProverChannel = ProverChannel::default();
+        source.initialize(&hex!("0123456789abcded"));
+        let rand_bytes: [u8; 32] = source.get_random();
+        source.write(&rand_bytes[..]);
+        source.write(proof_of_work::Response::from_nonce(11_028_357_238_u64));
+        let written_field_element = FieldElement::from_montgomery(u256h!(
+            "0389a47fe0e1e5f9c05d8dcb27b069b67b1c7ec61a5c0a3f54d81aea83d2c8f0"
+        ));
+        source.write(&written_field_element);
+        let written_field_element_vec = vec![
+            FieldElement::from_montgomery(u256h!(
+                "0389a47fe0e1e5f9c05d8dcb27b069b67b1c7ec61a5c0a3f54d81aea83d2c8f0"
+            )),
+            FieldElement::from_montgomery(u256h!(
+                "029ab47fe0e1a5f9c05d8dcb27b069b67b1c7ec61a5c0a3f54d81aea83d2c8f0"
+            )),
+        ];
+        source.write(written_field_element_vec.as_slice());
+
+        let written_big_int_vec = vec![
+            u256h!("0389a47fe0e1e5f9c05d8dcb27b069b67b1c7ec61a5c0a3f54d81aea83d2c8f0"),
+            u256h!("129ab47fe0e1a5f9c05d8dcb27b069b67b1c7ec61a5c0a3f54d81aea83d2c8f0"),
+        ];
+        source.write(written_big_int_vec.clone());
+        let digest = source.coin.digest;
+
+        let mut verifier = source.into_verifier(&hex!("0123456789abcded")).unwrap();
+        let bytes_test: [u8; 32] = verifier.replay();
+        assert_eq!(bytes_test, rand_bytes);
+        assert_eq!(
+            verifier.coin.digest,
             hex!("3174a00d031bc8deff799e24a78ee347b303295a6cb61986a49873d9b6f13a0d")
         );
-        source.write(proof_of_work::Response::from_nonce(11_028_357_238_u64));
+        let pow_response_test: proof_of_work::Response = verifier.replay();
+        assert_eq!(pow_response_test.nonce(), 11_028_357_238_u64);
+        assert_eq!(
+            verifier.coin.digest,
+            hex!("21571e2a323daa1e6f2adda87ce912608e1325492d868e8fe41626633d6acb93")
+        );
+        let field_element_test: FieldElement = verifier.replay();
+        assert_eq!(field_element_test, written_field_element);
+        assert_eq!(
+            verifier.coin.digest,
+            hex!("34a12938f047c34da72b5949434950fa2b24220270fd26e6f64b6eb5e86c6626")
+        );
+        let field_element_vec_test: Vec<FieldElement> = verifier.replay_fri_layer(2);
+        assert_eq!(field_element_vec_test, written_field_element_vec);
+        assert_eq!(
+            verifier.coin.digest,
+            hex!("586b2c12cd444cfe29932fcb167fc0be2e575a8d68e4a41d35de8602b0aea929")
+        );
+        let bit_int_vec_test: Vec<U256> = verifier.replay_many(2);
+        assert_eq!(bit_int_vec_test, written_big_int_vec);
+        assert_eq!(verifier.coin.digest, digest);
+        assert_eq!(verifier.finalize(), Ok(()));
+    }
+
+    #[test]
+    fn test_into_verifier_seed() {
+        let mut source: ProverChannel = ProverChannel::default();
+        source.initialize(&hex!("0123456789abcded"));
+        source.write(&[0xab_u8; 32][..]);
+        assert_eq!(
+            source.clone().into_verifier(&hex!("0123456789abcdee")),
+            Err(Error::SeedMismatch)
+        );
+
+        let builder = ChannelBuilder::<Keccak256>::default().seed(&hex!("0123456789abcded"));
+        let mut built = builder.build_prover();
+        built.write(&[0xab_u8; 32][..]);
+        let mut verifier = built.into_verifier(&hex!("0123456789abcded")).unwrap();
+        assert_eq!(verifier.replay_bytes(32), Ok(vec![0xab_u8; 32]));
+        assert_eq!(verifier.coin, source.coin);
+    }
+
+    #[test]
+    fn test_truncated_proof() {
+        let mut source: ProverChannel = ProverChannel::default();
+        source.initialize(&hex!("0123456789abcded"));
+        source.write(&[0xab_u8; 32][..]);
+        source.write(proof_of_work::Response::from_nonce(11_028_357_238_u64));
+        source.write(&FieldElement::from_montgomery(u256h!(
+            "0389a47fe0e1e5f9c05d8dcb27b069b67b1c7ec61a5c0a3f54d81aea83d2c8f0"
+        )));
+        let mut truncated = source.proof.clone();
+        truncated.truncate(32 + 8 + 31);
+
+        let mut verifier: VerifierChannel = VerifierChannel::new(truncated);
+        verifier.initialize(&hex!("0123456789abcded"));
+        let hash: Result<[u8; 32], Error> = verifier.try_replay();
+        assert_eq!(hash, Ok([0xab_u8; 32]));
+        let response: Result<proof_of_work::Response, Error> = verifier.try_replay();
+        assert_eq!(
+            response,
+            Ok(proof_of_work::Response::from_nonce(11_028_357_238_u64))
+        );
+        let digest = verifier.coin.digest;
+        let element: Result<FieldElement, Error> = verifier.try_replay();
+        assert_eq!(element, Err(Error::ProofTooShort));
+        let int: Result<U256, Error> = verifier.try_replay();
+        assert_eq!(int, Err(Error::ProofTooShort));
+        let hash: Result<Hash, Error> = verifier.try_replay();
+        assert_eq!(hash, Err(Error::ProofTooShort));
+        // A failed read leaves the channel untouched
+        assert_eq!(verifier.coin.digest, digest);
+        assert_eq!(verifier.proof_index, 32 + 8);
+    }
+
+    #[test]
+    fn test_checkpoint_restore() {
+        let mut coin: PublicCoin = PublicCoin::default();
+        coin.seed(&hex!("0123456789abcded"));
+        let _: FieldElement = coin.get_random();
+        let state = coin.checkpoint();
+        let expected: FieldElement = coin.get_random();
+        let _: FieldElement = coin.get_random();
+        let _: [u8; 32] = coin.get_random();
+        coin.write(&[0xab_u8; 32][..]);
+        coin.restore(state);
+        assert_eq!(coin.checkpoint(), state);
+        let value: FieldElement = coin.get_random();
+        assert_eq!(value, expected);
+    }
+
+    /// Writes `value` to a prover channel, replays it from the resulting proof
+    /// and checks that both coins absorbed the same bytes.
+    fn assert_symmetric<T>(value: T)
+    where
+        T: Clone + PartialEq + fmt::Debug,
+        ProverChannel: Writable<T>,
+        VerifierChannel: Replayable<T>,
+    {
+        let expected = value.clone();
+        let replayed =
+            assert_symmetric_with(move |channel| channel.write(value), Replayable::replay);
+        assert_eq!(replayed, expected);
+    }
+
+    /// Like [`assert_symmetric`] for types where the written and replayed types
+    /// differ.
+    fn assert_symmetric_with<R>(
+        write: impl FnOnce(&mut ProverChannel),
+        replay: impl FnOnce(&mut VerifierChannel) -> R,
+    ) -> R {
+        let seed = hex!("0123456789abcded");
+        let mut source: ProverChannel = ProverChannel::default();
+        source.initialize(&seed);
+        write(&mut source);
+        let mut verifier = source.clone().into_verifier(&seed).unwrap();
+        let result = replay(&mut verifier);
+        assert!(verifier.at_end());
+        assert_eq!(verifier.coin, source.coin);
+        result
+    }
+
+    #[test]
+    fn test_symmetric() {
+        assert_symmetric(0x0123_4567_89ab_cdef_u64);
+        assert_symmetric(0x89ab_cdef_u32);
+        for &value in &[i64::min_value(), -1, 0, 1, i64::max_value()] {
+            assert_symmetric(value);
+        }
+        assert_symmetric(12345_usize);
+        assert_symmetric(true);
+        assert_symmetric(u256h!(
+            "0388a7f3b2a5d0c4e1d8b97e1e6f3e5dc1b7c2a35e4f8d7c6b5a493827160514"
+        ));
+        assert_symmetric(proof_of_work::Response::from_nonce(138));
+
+        let element = FieldElement::from(u256h!(
+            "0388a7f3b2a5d0c4e1d8b97e1e6f3e5dc1b7c2a35e4f8d7c6b5a493827160514"
+        ));
+        let replayed: FieldElement =
+            assert_symmetric_with(|channel| channel.write(&element), Replayable::replay);
+        assert_eq!(replayed, element);
+
+        let hash = Hash::new(hex!(
+            "0123456789abcded0123456789abcded0123456789abcded0123456789abcded"
+        ));
+        let replayed: Hash =
+            assert_symmetric_with(|channel| channel.write(&hash), Replayable::replay);
+        assert_eq!(replayed, hash);
+
+        // Slices of field elements are absorbed at once and need to be
+        // replayed as a layer, not element by element.
+        let layer = (0..5).map(FieldElement::from).collect::<Vec<_>>();
+        let replayed = assert_symmetric_with(
+            |channel| channel.write(&layer[..]),
+            |channel| channel.replay_fri_layer(5),
+        );
+        assert_eq!(replayed, layer);
+
+        // `Vec<U256>` writes the values one at a time.
+        let values = (0..5_u64).map(U256::from).collect::<Vec<_>>();
+        let replayed: Vec<U256> = assert_symmetric_with(
+            |channel| channel.write(values.clone()),
+            |channel| channel.replay_many(5),
+        );
+        assert_eq!(replayed, values);
+
+        assert_symmetric(MerkleRoot(hex!(
+            "0123456789abcded0123456789abcded0123456789abcded0123456789abcded"
+        )));
+
+        let bytes = assert_symmetric_with(
+            |channel| channel.write(&[&[1_u8, 2][..], &[3_u8][..]][..]),
+            |channel| channel.replay_bytes(3).unwrap(),
+        );
+        assert_eq!(bytes, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_merkle_path() {
+        let path = MerklePath(
+            (0..20_u8)
+                .map(|depth| {
+                    let mut hash = [depth; 32];
+                    hash[0] = 0xff;
+                    hash
+                })
+                .collect(),
+        );
+        let replayed: MerklePath =
+            assert_symmetric_with(|channel| channel.write(&path), Replayable::replay);
+        assert_eq!(replayed, path);
+        let empty: MerklePath = assert_symmetric_with(
+            |channel| channel.write(&MerklePath(vec![])),
+            Replayable::replay,
+        );
+        assert_eq!(empty, MerklePath(vec![]));
+
+        // Writing the hashes one by one is a different transcript.
+        let mut grouped: ProverChannel = ProverChannel::default();
+        grouped.initialize(&hex!("0123456789abcded"));
+        let mut individual = grouped.clone();
+        grouped.write(&path);
+        for hash in &path.0 {
+            individual.write(&hash[..]);
+        }
+        assert_eq!(grouped.proof.len(), 8 + 20 * 32);
+        assert_eq!(grouped.proof[8..], individual.proof[..]);
+        assert_ne!(grouped.coin.digest, individual.coin.digest);
+
+        // A truncated path fails without touching the coin.
+        let mut truncated = grouped.proof.clone();
+        let _ = truncated.pop();
+        let mut verifier: VerifierChannel = VerifierChannel::new(truncated);
+        verifier.initialize(&hex!("0123456789abcded"));
+        let coin = verifier.coin.clone();
+        let result: Result<MerklePath, _> = verifier.try_replay();
+        assert_eq!(result, Err(Error::ProofTooShort));
+        assert_eq!(verifier.coin, coin);
+        assert_eq!(verifier.tell(), 0);
+    }
+
+    #[test]
+    fn test_merkle_path_streaming() {
+        let seed = hex!("0123456789abcded");
+        let path = MerklePath((0..5_u8).map(|depth| [depth; 32]).collect());
+        let mut source: ProverChannel = ProverChannel::default();
+        source.initialize(&seed);
+        source.write(&path);
+        source.write(42_u64);
+        source.write(&MerklePath(vec![]));
+
+        let mut streaming: VerifierChannel<Keccak256, _> = ChannelBuilder::default()
+            .seed(&seed)
+            .build_streaming_verifier(&source.proof[..]);
+        let replayed: MerklePath = streaming.replay();
+        assert_eq!(replayed, path);
+        let value: u64 = streaming.replay();
+        assert_eq!(value, 42);
+        let empty: MerklePath = streaming.replay();
+        assert_eq!(empty, MerklePath(vec![]));
+        assert_eq!(streaming.coin.digest, source.coin.digest);
+
+        // A prefix claiming more hashes than the stream has.
+        let truncated = &source.proof[..8 + 4 * 32];
+        let mut streaming: VerifierChannel<Keccak256, _> = ChannelBuilder::default()
+            .seed(&seed)
+            .build_streaming_verifier(truncated);
+        let result: Result<MerklePath, _> = streaming.try_replay();
+        assert_eq!(result, Err(Error::ProofTooShort));
+    }
+
+    #[test]
+    fn test_indexed_roots() {
+        let roots: Vec<(usize, [u8; 32])> = (0..4_u8).map(|i| (i as usize, [i; 32])).collect();
+        let replayed = assert_symmetric_with(
+            |channel| channel.write(&roots[..]),
+            |channel| channel.replay_indexed_roots(4),
+        );
+        assert_eq!(replayed, roots);
+
+        let mut channel: ProverChannel = ProverChannel::default();
+        channel.initialize(&hex!("0123456789abcded"));
+        let mut swapped_channel = channel.clone();
+        channel.write(&roots[..]);
+        assert_eq!(channel.proof.len(), 4 * 40);
+        assert_eq!(channel.proof[..8], 0_u64.to_be_bytes());
+        assert_eq!(channel.proof[40..48], 1_u64.to_be_bytes());
+
+        // Swapping two roots changes the digest, even with the indices kept in
+        // order.
+        let mut swapped = roots.clone();
+        swapped[1].1 = roots[2].1;
+        swapped[2].1 = roots[1].1;
+        swapped_channel.write(&swapped[..]);
+        assert_ne!(channel.coin.digest, swapped_channel.coin.digest);
+    }
+
+    #[test]
+    fn test_endianness() {
+        let value = u256h!("0123456789abcded0123456789abcded0123456789abcded0123456789abcded");
+        let element = FieldElement::from(value.clone());
+        let elements = [FieldElement::from(3), FieldElement::from(5)];
+        let prove = |endianness| {
+            let builder = ChannelBuilder::default()
+                .seed(&hex!("0123456789abcded"))
+                .endianness(endianness);
+            let mut prover = builder.build_prover();
+            prover.write(0x0123_4567_89ab_cdef_u64);
+            prover.write(value.clone());
+            prover.write(&element);
+            prover.write(&elements[..]);
+            prover.write(&[1_u64, 2][..]);
+            let mut verifier = builder.build_verifier(prover.proof.clone());
+            let replayed: u64 = verifier.replay();
+            assert_eq!(replayed, 0x0123_4567_89ab_cdef);
+            let replayed: U256 = verifier.replay();
+            assert_eq!(replayed, value);
+            assert_eq!(verifier.try_replay_field(), Ok(element.clone()));
+            assert_eq!(verifier.replay_fri_layer(2), elements.to_vec());
+            assert_eq!(verifier.replay_u64_many(2), vec![1, 2]);
+            assert!(verifier.at_end());
+            assert_eq!(verifier.coin, prover.coin);
+            prover.proof.clone()
+        };
+        let big = prove(Endianness::Big);
+        let little = prove(Endianness::Little);
+        assert_eq!(big.len(), little.len());
+        assert_ne!(big, little);
+        assert_eq!(big[..8], 0x0123_4567_89ab_cdef_u64.to_be_bytes());
+        assert_eq!(little[..8], 0x0123_4567_89ab_cdef_u64.to_le_bytes());
+        // A `U256` and three field elements.
+        for (big, little) in big[8..136].chunks(32).zip(little[8..136].chunks(32)) {
+            assert!(big.iter().eq(little.iter().rev()));
+        }
+
+        // The default is big-endian.
+        let mut prover: ProverChannel = ProverChannel::default();
+        prover.initialize(&hex!("0123456789abcded"));
+        prover.write(0x0123_4567_89ab_cdef_u64);
+        assert_eq!(prover.proof[..], big[..8]);
+    }
+
+    #[test]
+    fn test_pow_verify_against() {
+        for prefix in &[&proof_of_work::DEFAULT_PREFIX[..], b"other protocol"] {
+            let mut coin: PublicCoin = PublicCoin::default();
+            coin.set_grinding_prefix(prefix);
+            coin.seed(&hex!("0123456789abcded"));
+            coin.write(&hex!("cafebabe")[..]);
+            let digest = coin.digest;
+            let seed: proof_of_work::ChallengeSeed = coin.clone().get_random();
+            let challenge = seed.with_difficulty(8).unwrap();
+            let nonce = challenge.solve().nonce();
+            assert!(PublicCoin::<Keccak256>::pow_verify_against(
+                &digest, prefix, nonce, 8
+            ));
+            for nonce in 0..1000 {
+                assert_eq!(
+                    PublicCoin::<Keccak256>::pow_verify_against(&digest, prefix, nonce, 8),
+                    challenge.verify(proof_of_work::Response::from_nonce(nonce))
+                );
+            }
+            assert_eq!(coin.digest, digest);
+        }
+    }
+
+    #[test]
+    fn test_replay_pow() {
+        let seed = hex!("0123456789abcded");
+        let prove = |valid: bool| {
+            let mut prover: ProverChannel = ProverChannel::default();
+            prover.initialize(&seed);
+            prover.write(&hex!("cafebabe")[..]);
+            let pow_seed: proof_of_work::ChallengeSeed = prover.get_random();
+            let challenge = pow_seed.with_difficulty(8).unwrap();
+            let response = (0..10_000)
+                .map(proof_of_work::Response::from_nonce)
+                .find(|&response| challenge.verify(response) == valid)
+                .unwrap();
+            prover.write_pow(8, response);
+            (
+                prover.clone().into_verifier(&seed).unwrap(),
+                prover,
+                response.nonce(),
+            )
+        };
+
+        let (mut verifier, prover, nonce) = prove(true);
+        assert_eq!(verifier.replay_bytes(4), Ok(hex!("cafebabe").to_vec()));
+        assert_eq!(verifier.replay_pow(8), Ok(nonce));
+        assert!(verifier.at_end());
+        assert_eq!(verifier.coin, prover.coin);
+
+        let (mut verifier, ..) = prove(false);
+        assert_eq!(verifier.replay_bytes(4), Ok(hex!("cafebabe").to_vec()));
+        assert_eq!(verifier.replay_pow(8), Err(Error::InvalidPow));
+
+        // Zero disables the check, but the nonce is still read.
+        let (mut verifier, prover, nonce) = prove(false);
+        assert_eq!(verifier.replay_bytes(4), Ok(hex!("cafebabe").to_vec()));
+        assert_eq!(verifier.replay_pow(0), Ok(nonce));
+        assert!(verifier.at_end());
+        assert_eq!(verifier.coin.digest, prover.coin.digest);
+
+        let (mut verifier, ..) = prove(true);
+        assert_eq!(verifier.replay_bytes(4), Ok(hex!("cafebabe").to_vec()));
+        assert_eq!(
+            verifier.replay_pow(proof_of_work::MAX_DIFFICULTY + 1),
+            Err(Error::InvalidPow)
+        );
+    }
+
+    #[test]
+    fn test_merkle_root_label() {
+        let root = hex!("0123456789abcded0123456789abcded0123456789abcded0123456789abcded");
+        let mut tagged: ProverChannel = ProverChannel::default();
+        tagged.initialize(&hex!("0123456789abcded"));
+        let mut untagged = tagged.clone();
+        tagged.write(MerkleRoot(root));
+        untagged.write(&root[..]);
+        assert_eq!(tagged.proof, untagged.proof);
+        assert_ne!(tagged.coin.digest, untagged.coin.digest);
+
+        // Replaying the root as raw bytes desynchronizes the coin.
+        let mut verifier: VerifierChannel = VerifierChannel::new(tagged.proof.clone());
+        verifier.initialize(&hex!("0123456789abcded"));
+        let _: [u8; 32] = verifier.replay();
+        assert_eq!(verifier.coin, untagged.coin);
+    }
+
+    #[test]
+    fn test_seed_with_domain() {
+        let seed = hex!("0123456789abcded");
+        let mut plain: PublicCoin = PublicCoin::default();
+        plain.seed(&seed);
+        assert_eq!(plain.digest, Keccak256::hash(&seed));
+        let mut empty: PublicCoin = PublicCoin::default();
+        empty.seed_with_domain(&[], &seed);
+        assert_eq!(empty, plain);
+
+        let mut first: PublicCoin = PublicCoin::default();
+        first.seed_with_domain(b"fri", &seed);
+        let mut second: PublicCoin = PublicCoin::default();
+        second.seed_with_domain(b"air", &seed);
+        assert_ne!(first.digest, plain.digest);
+        assert_ne!(first.digest, second.digest);
+        let a: FieldElement = first.get_random();
+        let b: FieldElement = second.get_random();
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn test_get_random_array() {
+        let mut source: ProverChannel = ProverChannel::default();
+        source.initialize(&hex!("0123456789abcded"));
+        let mut single = source.clone();
+        let array: [FieldElement; 4] = source.get_random();
+        let expected: Vec<FieldElement> = (0..4).map(|_| single.get_random()).collect();
+        assert_eq!(array.to_vec(), expected);
+        assert_eq!(source.coin, single.coin);
+        let empty: [FieldElement; 0] = source.get_random();
+        assert!(empty.is_empty());
+        assert_eq!(source.coin, single.coin);
+    }
+
+    #[test]
+    #[should_panic(expected = "Public coin used after it was sealed")]
+    fn test_sealed_draw() {
+        let mut coin: PublicCoin = PublicCoin::default();
+        coin.seed(&hex!("0123456789abcded"));
+        let _: FieldElement = coin.get_random();
+        coin.seal();
+        let _: FieldElement = coin.get_random();
+    }
+
+    #[test]
+    #[should_panic(expected = "Public coin used after it was sealed")]
+    fn test_sealed_write() {
+        let mut source: ProverChannel = ProverChannel::default();
+        source.initialize(&hex!("0123456789abcded"));
+        source.coin.seal();
+        source.write(&[FieldElement::from(5)][..]);
+    }
+
+    #[test]
+    fn test_debug() {
+        let mut source: ProverChannel = ProverChannel::default();
+        source.initialize(&hex!("0123456789abcded"));
+        source.write(&[0xab_u8; 40][..]);
+        let _: [u8; 32] = source.get_random();
+        let digest = hex::encode(source.coin.digest);
         assert_eq!(
-            source.coin.digest,
-            hex!("21571e2a323daa1e6f2adda87ce912608e1325492d868e8fe41626633d6acb93")
+            format!("{:?}", source.coin),
+            format!("PublicCoin {{ digest: \"{}\", counter: 1 }}", digest)
         );
-        source.write(&FieldElement::from_montgomery(u256h!(
-            "0389a47fe0e1e5f9c05d8dcb27b069b67b1c7ec61a5c0a3f54d81aea83d2c8f0"
-        )));
+        assert!(format!("{:?}", source).contains(&digest));
+        assert!(format!("{:?}", source).contains("proof_length: 40"));
+        let verifier: VerifierChannel = VerifierChannel::new(source.proof.clone());
+        assert!(format!("{:?}", verifier).contains("proof_index: 0"));
+    }
+
+    #[test]
+    fn test_hash() {
+        use std::collections::HashSet;
+        let mut source: ProverChannel = ProverChannel::default();
+        source.initialize(&hex!("0123456789abcded"));
+        let mut other = source.clone();
+        other.write(&[0xab_u8; 32][..]);
+        let set = [source.clone(), other, source]
+            .iter()
+            .cloned()
+            .collect::<HashSet<_>>();
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn test_i64_encoding() {
+        let mut source: ProverChannel = ProverChannel::default();
+        source.write(-2_i64);
+        source.write(i64::min_value());
+        assert_eq!(source.proof, hex!("fffffffffffffffe8000000000000000"));
+    }
+
+    #[test]
+    fn test_field_element_encoding() {
+        // One is written as its Montgomery form R mod p, not as 0x..01.
+        let one = FieldElement::one();
+        let mut source: ProverChannel = ProverChannel::default();
+        source.write(&one);
+        source.write(&[one.clone()][..]);
         assert_eq!(
-            source.coin.digest,
-            hex!("34a12938f047c34da72b5949434950fa2b24220270fd26e6f64b6eb5e86c6626")
+            source.proof,
+            [hex!("07fffffffffffdf0ffffffffffffffffffffffffffffffffffffffffffffffe1"); 2].concat()
         );
-        source.write(
-            vec![
-                FieldElement::from_montgomery(u256h!(
-                    "0389a47fe0e1e5f9c05d8dcb27b069b67b1c7ec61a5c0a3f54d81aea83d2c8f0"
-                )),
-                FieldElement::from_montgomery(u256h!(
-                    "029ab47fe0e1a5f9c05d8dcb27b069b67b1c7ec61a5c0a3f54d81aea83d2c8f0"
-                )),
-            ]
-            .as_slice(),
+        assert_eq!(&source.proof[..32], &one.to_montgomery_bytes_be()[..]);
+        assert_ne!(&source.proof[..32], &one.to_canonical_bytes_be()[..]);
+    }
+
+    #[test]
+    fn test_replay_field_non_canonical() {
+        let mut source: ProverChannel = ProverChannel::default();
+        source.write(&FieldElement::from(7));
+        source.write(&MODULUS_BYTES[..]);
+        let mut replay: VerifierChannel = VerifierChannel::new(source.proof.clone());
+        assert_eq!(replay.try_replay_field(), Ok(FieldElement::from(7)));
+        assert_eq!(
+            replay.try_replay_field(),
+            Err(Error::NonCanonicalFieldElement)
         );
+
+        let mut replay: VerifierChannel = VerifierChannel::new(source.proof.clone());
         assert_eq!(
-            source.coin.digest,
-            hex!("586b2c12cd444cfe29932fcb167fc0be2e575a8d68e4a41d35de8602b0aea929")
+            replay.try_replay_fri_layer(2),
+            Err(Error::NonCanonicalFieldElement)
         );
     }
 
     #[test]
-    fn verifier_channel_test() {
-        let mut source = ProverChannel::default();
+    fn test_u64_slice() {
+        let values = [1_u64, 0x0123_4567_89ab_cdef, u64::max_value()];
+        let replayed = assert_symmetric_with(
+            |channel| channel.write(&values[..]),
+            |channel| channel.replay_u64_many(3),
+        );
+        assert_eq!(replayed, values);
+
+        // Same proof bytes as individual writes, but a single coin update.
+        let mut grouped: ProverChannel = ProverChannel::default();
+        grouped.write(&values[..]);
+        let mut individual: ProverChannel = ProverChannel::default();
+        for &value in &values {
+            individual.write(value);
+        }
+        assert_eq!(grouped.proof, individual.proof);
+        assert_ne!(grouped.coin, individual.coin);
+    }
+
+    #[test]
+    fn test_from_coin() {
+        let seed = hex!("0123456789abcded");
+        let items: [&[u8]; 3] = [&[0x01; 32], &[0x02, 0x03], &[0x04; 7]];
+        let mut source: ProverChannel = ProverChannel::default();
+        source.initialize(&seed);
+        for item in &items {
+            source.write(*item);
+        }
+        source.write(0x0123_4567_u32);
+
+        let mut coin = PublicCoin::default();
+        coin.seed(&seed);
+        coin.absorb_sequence(&items);
+        let mut verifier: VerifierChannel =
+            VerifierChannel::from_coin(coin, source.proof.clone(), 41);
+        let value: u32 = verifier.replay();
+        assert_eq!(value, 0x0123_4567);
+        assert!(verifier.at_end());
+        assert_eq!(verifier.coin, source.coin);
+
+        // The prefix counts as read and does not update the coin again.
+        verifier.seek(32).unwrap();
+        let _: [u8; 2] = verifier.read_bytes(2).unwrap().try_into().unwrap();
+        assert_eq!(verifier.coin, source.coin);
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn test_zeroize() {
+        let mut source: ProverChannel = ProverChannel::default();
         source.initialize(&hex!("0123456789abcded"));
-        let rand_bytes: [u8; 32] = source.get_random();
-        source.write(&rand_bytes[..]);
-        source.write(proof_of_work::Response::from_nonce(11_028_357_238_u64));
-        let written_field_element = FieldElement::from_montgomery(u256h!(
-            "0389a47fe0e1e5f9c05d8dcb27b069b67b1c7ec61a5c0a3f54d81aea83d2c8f0"
-        ));
-        source.write(&written_field_element);
-        let written_field_element_vec = vec![
-            FieldElement::from_montgomery(u256h!(
-                "0389a47fe0e1e5f9c05d8dcb27b069b67b1c7ec61a5c0a3f54d81aea83d2c8f0"
-            )),
-            FieldElement::from_montgomery(u256h!(
-                "029ab47fe0e1a5f9c05d8dcb27b069b67b1c7ec61a5c0a3f54d81aea83d2c8f0"
-            )),
-        ];
-        source.write(written_field_element_vec.as_slice());
+        source.write(&[0xab_u8; 32][..]);
+        let _: [u8; 32] = source.get_random();
+        assert_ne!(source.coin.digest, [0; 32]);
+        source.zeroize();
+        assert_eq!(source.coin.digest, [0; 32]);
+        assert_eq!(source.coin.counter(), 0);
+        assert!(source.proof.is_empty());
+    }
 
-        let written_big_int_vec = vec![
-            u256h!("0389a47fe0e1e5f9c05d8dcb27b069b67b1c7ec61a5c0a3f54d81aea83d2c8f0"),
-            u256h!("129ab47fe0e1a5f9c05d8dcb27b069b67b1c7ec61a5c0a3f54d81aea83d2c8f0"),
-        ];
-        source.write(written_big_int_vec.clone());
+    #[test]
+    fn test_counter() {
+        let mut source: ProverChannel = ProverChannel::default();
+        source.initialize(&hex!("0123456789abcded"));
+        let mut verifier: VerifierChannel = VerifierChannel::new(Vec::new());
+        verifier.initialize(&hex!("0123456789abcded"));
+        assert_eq!(source.counter(), 0);
+        for i in 1..=3 {
+            let _: [u8; 32] = source.get_random();
+            let _: [u8; 32] = verifier.get_random();
+            assert_eq!(source.counter(), i);
+            assert_eq!(verifier.counter(), i);
+        }
+        source.write(&[0xab_u8; 32][..]);
+        assert_eq!(source.counter(), 0);
+        assert_eq!(source.coin.counter(), 0);
+    }
+
+    #[cfg(feature = "trace-transcript")]
+    #[test]
+    fn test_diff_transcripts() {
+        let seed = hex!("0123456789abcded");
+        let mut source: ProverChannel = ProverChannel::default();
+        source.initialize(&seed);
+        source.write(&[0x01_u8; 32][..]);
+        let _: FieldElement = source.get_random();
+        source.write(&FieldElement::from(5));
+        source.write(&FieldElement::from(7));
+        source.write(42_u64);
+
+        let replay = |proof: Vec<u8>| {
+            let mut verifier: VerifierChannel = VerifierChannel::new(proof);
+            verifier.initialize(&seed);
+            let _ = verifier.replay_bytes(32).unwrap();
+            let _: FieldElement = verifier.get_random();
+            let _: Vec<FieldElement> = verifier.replay_many(2);
+            let _: u64 = verifier.replay();
+            verifier
+        };
+        assert_eq!(
+            diff_transcripts(&source, &replay(source.proof.clone())),
+            None
+        );
+
+        let mut corrupted = source.proof.clone();
+        corrupted[40] ^= 1;
+        let verifier = replay(corrupted);
+        let diff = diff_transcripts(&source, &verifier).unwrap();
+        assert_eq!(diff.offset, 40);
+        // Both sides agree up to the start of the corrupted field element.
+        assert_eq!(diff.prover_digest, diff.verifier_digest);
+        assert_eq!(diff.prover_digest, source.coin.log[1].1);
+        assert_ne!(verifier.coin.digest, source.coin.digest);
+    }
+
+    #[test]
+    fn test_get_random_many() {
+        let mut batch: PublicCoin = PublicCoin::default();
+        batch.seed(&hex!("0123456789abcded"));
+        let mut single = batch.clone();
+        // Enough draws to hit the rejection branch a few times.
+        let batch_values = batch.get_random_many(100);
+        let single_values: Vec<FieldElement> = (0..100).map(|_| single.get_random()).collect();
+        assert_eq!(batch_values, single_values);
+        assert_eq!(batch, single);
+        assert!(batch.counter > 100);
+    }
+
+    #[test]
+    fn test_get_random_index() {
+        const BOUND: usize = 7;
+        const DRAWS: usize = 7000;
+        let mut coin: PublicCoin = PublicCoin::default();
+        coin.seed(&hex!("0123456789abcded"));
+        let mut counts = [0_usize; BOUND];
+        for _ in 0..DRAWS {
+            let index = coin.get_random_index(BOUND);
+            assert!(index < BOUND);
+            counts[index] += 1;
+        }
+        // Each bucket expects 1000 draws with a standard deviation of ~31.
+        for &count in &counts {
+            assert!(count > 850 && count < 1150);
+        }
+        assert_eq!(coin.get_random_index(1), 0);
+    }
+
+    #[test]
+    fn test_write_elements_grouped() {
+        let elements: Vec<FieldElement> = (0..6).map(FieldElement::from).collect();
+        let mut channel: ProverChannel = ProverChannel::default();
+        channel.initialize(&hex!("0123456789abcded"));
+
+        let mut slice = channel.clone();
+        slice.write(&elements[..]);
+        let mut vector = channel.clone();
+        vector.write(
+            elements
+                .iter()
+                .map(FieldElement::as_montgomery)
+                .cloned()
+                .collect::<Vec<U256>>(),
+        );
+        let mut grouped_all = channel.clone();
+        grouped_all.write_elements_grouped(&elements, elements.len());
+        let mut grouped_one = channel.clone();
+        grouped_one.write_elements_grouped(&elements, 1);
+        let mut grouped_two = channel.clone();
+        grouped_two.write_elements_grouped(&elements, 2);
+
+        // The proof bytes are independent of the grouping
+        for other in &[&vector, &grouped_all, &grouped_one, &grouped_two] {
+            assert_eq!(other.proof, slice.proof);
+        }
+        // but the coin is not.
+        assert_eq!(grouped_all.coin, slice.coin);
+        assert_eq!(grouped_one.coin, vector.coin);
+        assert_ne!(slice.coin, vector.coin);
+        assert_ne!(grouped_two.coin, slice.coin);
+        assert_ne!(grouped_two.coin, vector.coin);
+    }
+
+    #[test]
+    fn test_write_elements_streaming() {
+        let elements: Vec<FieldElement> = (0..1000)
+            .map(|i| FieldElement::from(i) * FieldElement::from(123_456_789))
+            .collect();
+        let mut streamed: ProverChannel = ProverChannel::default();
+        streamed.initialize(&hex!("0123456789abcded"));
+        let mut buffered = streamed.clone();
+        let _: FieldElement = streamed.get_random();
+        let _: FieldElement = buffered.get_random();
+
+        streamed.write(&elements[..]);
+        let bytes: Vec<u8> = elements
+            .iter()
+            .flat_map(|element| element.as_montgomery().to_bytes_be().to_vec())
+            .collect();
+        buffered.write(&bytes[..]);
+        assert_eq!(streamed.proof, buffered.proof);
+        assert_eq!(streamed.coin, buffered.coin);
+    }
+
+    #[test]
+    fn test_modulus_bytes() {
+        assert_eq!(MODULUS_BYTES, FieldElement::MODULUS.to_bytes_be());
+        let mut bytes = MODULUS_BYTES;
+        assert_eq!(field_element_from_random(&bytes), None);
+        bytes[0] |= 0xf0;
+        assert_eq!(field_element_from_random(&bytes), None);
+        bytes[31] = 0;
+        assert_eq!(
+            field_element_from_random(&bytes),
+            Some(FieldElement::from_montgomery(
+                FieldElement::MODULUS - U256::from(1_u64)
+            ))
+        );
+    }
+
+    #[test]
+    fn test_fork() {
+        let mut coin: PublicCoin = PublicCoin::default();
+        coin.seed(&hex!("0123456789abcded"));
+        let _: FieldElement = coin.get_random();
+        let parent = coin.clone();
+
+        let mut left = coin.fork(b"left");
+        let mut right = coin.fork(b"right");
+        let mut again = coin.fork(b"left");
+        assert_eq!(left, again);
+        assert_ne!(left, right);
+        let value: FieldElement = left.get_random();
+        assert_ne!(value, right.get_random());
+        assert_eq!(value, again.get_random());
+
+        // Forks do not affect the parent or each other.
+        left.write(&[0xab_u8; 32]);
+        assert_eq!(coin, parent);
+        assert_ne!(left, again);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_split_off() {
+        use rayon::prelude::*;
+        let mut coin: PublicCoin = PublicCoin::default();
+        coin.seed(&hex!("0123456789abcded"));
+        let _: FieldElement = coin.get_random();
+        let parent = coin.clone();
+
+        let sub_coins = coin.split_off(4);
+        let parallel: Vec<Vec<FieldElement>> = sub_coins
+            .into_par_iter()
+            .map(|mut sub_coin| sub_coin.get_random_many(3))
+            .collect();
+
+        // Sequential reference
+        let label = |i: u64| [&[0x02][..], &i.to_be_bytes()].concat();
+        let sequential: Vec<Vec<FieldElement>> = (0..4)
+            .map(|i| parent.fork(&label(i)).get_random_many(3))
+            .collect();
+        assert_eq!(parallel, sequential);
+        assert_eq!(coin.digest, parent.fork(&label(4)).digest);
+        assert_eq!(coin.counter(), 0);
+    }
+
+    #[test]
+    fn test_grinding_prefix() {
+        let mut source: ProverChannel = ProverChannel::default();
+        source.initialize(&hex!("0123456789abcded"));
+        let mut other = source.clone();
+        other.coin.set_grinding_prefix(b"other protocol");
+        assert_eq!(source.coin.digest, other.coin.digest);
+
+        let seed: proof_of_work::ChallengeSeed = source.get_random();
+        let other_seed: proof_of_work::ChallengeSeed = other.get_random();
+        let challenge = seed.with_difficulty(8).unwrap();
+        let other_challenge = other_seed.with_difficulty(8).unwrap();
+        let response = challenge.solve();
+        let other_response = other_challenge.solve();
+        assert_ne!(response, other_response);
+        assert!(other_challenge.verify(other_response));
+    }
+
+    #[test]
+    fn test_channel_builder() {
+        let seed = hex!("0123456789abcded");
+        let mut expected: ProverChannel = ProverChannel::default();
+        expected.initialize(&seed);
+        let builder = ChannelBuilder::default().seed(&seed);
+        assert_eq!(builder.build_prover(), expected);
+        let mut verifier: VerifierChannel = VerifierChannel::new(vec![]);
+        verifier.initialize(&seed);
+        assert_eq!(builder.build_verifier(vec![]), verifier);
+
+        let mut configured: ProverChannel = ProverChannel::default();
+        configured.coin.set_grinding_prefix(b"other protocol");
+        configured.initialize(&seed);
+        let builder = builder.grinding_prefix(b"other protocol");
+        assert_eq!(builder.build_prover(), configured);
+
+        let mut blake: ProverChannel<Blake2s256> = ProverChannel::default();
+        blake.initialize(&seed);
+        assert_eq!(
+            ChannelBuilder::default()
+                .seed(&seed)
+                .hash::<Blake2s256>()
+                .build_prover(),
+            blake
+        );
+    }
+
+    #[test]
+    fn test_mock_coin() {
+        // A protocol step that draws a field element and then a seed.
+        fn step<R>(rng: &mut R) -> (FieldElement, [u8; 32])
+        where
+            R: RandomGenerator<FieldElement> + RandomGenerator<[u8; 32]>,
+        {
+            (rng.get_random(), rng.get_random())
+        }
+
+        let mut coin = MockCoin::new(vec![U256::from(5), U256::from(0x0102)]);
+        let (element, seed) = step(&mut coin);
+        assert_eq!(element, FieldElement::from_montgomery(U256::from(5)));
+        assert_eq!(&seed[30..], &[1, 2]);
+        assert_eq!(coin.drawn(), 2);
+
+        // Field element draws map values like the real coin maps its bytes.
+        // Repeat the real coin's rejection loop to find the accepted bytes.
+        let mut public: PublicCoin = PublicCoin::default();
+        public.write(&seed[..]);
+        let mut raw = public.clone();
+        let value = loop {
+            let mut bytes: [u8; 32] = raw.get_random();
+            bytes[0] &= 0x0f;
+            let value = U256::from_bytes_be(&bytes);
+            if value < FieldElement::MODULUS {
+                break value;
+            }
+        };
+        let expected: FieldElement = public.get_random();
+        let mut coin = MockCoin::new(vec![value]);
+        let element: FieldElement = coin.get_random();
+        assert_eq!(element, expected);
+
+        let elements = [FieldElement::from(5), expected];
+        let mut coin = MockCoin::from_field_elements(&elements);
+        let first: FieldElement = coin.get_random();
+        let second: FieldElement = coin.get_random();
+        assert_eq!([first, second], elements);
+    }
+
+    #[test]
+    #[should_panic(expected = "MockCoin exhausted")]
+    fn test_mock_coin_exhausted() {
+        let mut coin = MockCoin::new(vec![U256::from(5)]);
+        let _: U256 = coin.get_random();
+        let _: U256 = coin.get_random();
+    }
+
+    #[test]
+    fn test_from_reader() {
+        let seed = hex!("0123456789abcded");
+        let mut source: ProverChannel = ProverChannel::default();
+        source.initialize(&seed);
+        source.write(&[0xab_u8; 32][..]);
+        source.write(&FieldElement::from(7));
+        source.write(42_u64);
+        source.write(&[FieldElement::from(1), FieldElement::from(2)][..]);
+
+        let mut memory: VerifierChannel = VerifierChannel::new(source.proof.clone());
+        memory.initialize(&seed);
+        let mut streaming: VerifierChannel<Keccak256, _> = ChannelBuilder::default()
+            .seed(&seed)
+            .build_streaming_verifier(&source.proof[..]);
+        let hash: [u8; 32] = memory.replay();
+        let streamed: [u8; 32] = streaming.replay();
+        assert_eq!(streamed, hash);
+        assert_eq!(memory.coin, streaming.coin);
+        let element: FieldElement = memory.replay();
+        let streamed: FieldElement = streaming.replay();
+        assert_eq!(streamed, element);
+        let value: u64 = memory.replay();
+        let streamed: u64 = streaming.replay();
+        assert_eq!(streamed, value);
+        let random: FieldElement = memory.get_random();
+        let streamed: FieldElement = streaming.get_random();
+        assert_eq!(streamed, random);
+        let layer = memory.replay_fri_layer(2);
+        assert_eq!(streaming.replay_fri_layer(2), layer);
+        assert_eq!(memory.coin, streaming.coin);
+        assert_eq!(memory.finalize(), Ok(()));
+
+        let truncated = &source.proof[..40];
+        let mut streaming: VerifierChannel<Keccak256, _> = ChannelBuilder::default()
+            .seed(&seed)
+            .build_streaming_verifier(truncated);
+        let _: [u8; 32] = streaming.replay();
+        let element: Result<FieldElement, _> = streaming.try_replay();
+        assert_eq!(element, Err(Error::ProofTooShort));
+    }
+
+    #[test]
+    fn test_replay_bytes() {
+        let data: Vec<u8> = (0..100).collect();
+        let mut source: ProverChannel = ProverChannel::default();
+        source.initialize(&hex!("0123456789abcded"));
+        source.write(&data[..]);
+        source.write(&[0xab_u8; 32][..]);
+
+        let mut verifier = source.clone().into_verifier(&hex!("0123456789abcded")).unwrap();
+        assert_eq!(verifier.replay_bytes(100), Ok(data));
+        assert_eq!(verifier.replay_bytes(33), Err(Error::ProofTooShort));
+        assert_eq!(verifier.replay_bytes(32), Ok(vec![0xab_u8; 32]));
+        assert_eq!(verifier.coin.digest, source.coin.digest);
+    }
+
+    #[test]
+    fn test_length_prefixed() {
+        let elements: Vec<FieldElement> = (1..=5).map(FieldElement::from).collect();
+        let mut source: ProverChannel = ProverChannel::default();
+        source.initialize(&hex!("0123456789abcded"));
+        source.write_with_length(&[]);
+        source.write_with_length(&elements);
+
+        let mut verifier = source.clone().into_verifier(&hex!("0123456789abcded")).unwrap();
+        assert_eq!(verifier.replay_length_prefixed(), vec![]);
+        assert_eq!(verifier.replay_length_prefixed(), elements);
+        assert_eq!(verifier.coin.digest, source.coin.digest);
 
-        let mut verifier = VerifierChannel::new(source.proof.clone());
+        // The prefix is followed by the same bytes as an unprefixed write.
+        let mut unprefixed: ProverChannel = ProverChannel::default();
+        unprefixed.write(&elements[..]);
+        assert_eq!(source.proof[8..16], 5_u64.to_be_bytes());
+        assert_eq!(source.proof[16..], unprefixed.proof[..]);
+
+        // A prefix claiming more elements than the proof has.
+        let mut truncated = source.proof.clone();
+        let _ = truncated.pop();
+        let mut verifier: VerifierChannel = VerifierChannel::new(truncated);
         verifier.initialize(&hex!("0123456789abcded"));
-        let bytes_test: [u8; 32] = verifier.read_32_bytes();
-        assert_eq!(bytes_test, rand_bytes);
+        assert_eq!(verifier.replay_length_prefixed(), vec![]);
+        let digest = verifier.coin.digest;
         assert_eq!(
-            verifier.coin.digest,
-            hex!("3174a00d031bc8deff799e24a78ee347b303295a6cb61986a49873d9b6f13a0d")
+            verifier.try_replay_length_prefixed(),
+            Err(Error::ProofTooShort)
         );
-        let pow_response_test: proof_of_work::Response = verifier.replay();
-        assert_eq!(pow_response_test.nonce(), 11_028_357_238_u64);
+        assert_eq!(verifier.coin.digest, digest);
+    }
+
+    #[test]
+    fn test_length_prefixed_streaming() {
+        let seed = hex!("0123456789abcded");
+        let elements: Vec<FieldElement> = (1..=5).map(FieldElement::from).collect();
+        let mut source: ProverChannel = ProverChannel::default();
+        source.initialize(&seed);
+        source.write_with_length(&elements);
+        source.write(42_u64);
+        source.write_with_length(&[]);
+
+        let mut streaming: VerifierChannel<Keccak256, _> = ChannelBuilder::default()
+            .seed(&seed)
+            .build_streaming_verifier(&source.proof[..]);
+        assert_eq!(streaming.replay_length_prefixed(), elements);
+        let value: u64 = streaming.replay();
+        assert_eq!(value, 42);
+        assert_eq!(streaming.replay_length_prefixed(), vec![]);
+        assert_eq!(streaming.coin.digest, source.coin.digest);
+
+        // A prefix claiming more elements than the stream has.
+        let truncated = &source.proof[..source.proof.len() - 17];
+        let mut streaming: VerifierChannel<Keccak256, _> = ChannelBuilder::default()
+            .seed(&seed)
+            .build_streaming_verifier(truncated);
         assert_eq!(
-            verifier.coin.digest,
-            hex!("21571e2a323daa1e6f2adda87ce912608e1325492d868e8fe41626633d6acb93")
+            streaming.try_replay_length_prefixed(),
+            Err(Error::ProofTooShort)
         );
-        let field_element_test: FieldElement = verifier.replay();
-        assert_eq!(field_element_test, written_field_element);
+
+        // An absurd length fails when the stream ends, without allocating.
+        let huge = [(1_u64 << 50).to_be_bytes(), [0; 8]].concat();
+        let mut streaming: VerifierChannel<Keccak256, _> = ChannelBuilder::default()
+            .seed(&seed)
+            .build_streaming_verifier(&huge[..]);
         assert_eq!(
-            verifier.coin.digest,
-            hex!("34a12938f047c34da72b5949434950fa2b24220270fd26e6f64b6eb5e86c6626")
+            streaming.try_replay_length_prefixed(),
+            Err(Error::ProofTooShort)
         );
-        let field_element_vec_test: Vec<FieldElement> = verifier.replay_fri_layer(2);
-        assert_eq!(field_element_vec_test, written_field_element_vec);
+    }
+
+    #[test]
+    fn test_finalize() {
+        let mut source: ProverChannel = ProverChannel::default();
+        source.initialize(&hex!("0123456789abcded"));
+        source.write(&[0xab_u8; 32][..]);
+        source.write(42_u64);
+
+        let mut verifier = source.clone().into_verifier(&hex!("0123456789abcded")).unwrap();
+        assert_eq!(verifier.bytes_remaining(), 40);
+        let _: [u8; 32] = verifier.replay();
+        assert_eq!(verifier.bytes_remaining(), 8);
+        assert!(!verifier.at_end());
+        let _: u64 = verifier.replay();
+        assert!(verifier.at_end());
+        assert_eq!(verifier.finalize(), Ok(()));
+
+        let mut proof = source.proof.clone();
+        proof.extend_from_slice(&[0_u8; 3]);
+        let mut verifier: VerifierChannel = VerifierChannel::new(proof);
+        verifier.initialize(&hex!("0123456789abcded"));
+        let _: [u8; 32] = verifier.replay();
+        let _: u64 = verifier.replay();
+        assert_eq!(verifier.bytes_remaining(), 3);
+        assert_eq!(verifier.finalize(), Err(Error::TrailingBytes(3)));
+    }
+
+    #[test]
+    fn test_replay_iter() {
+        let hashes: Vec<Hash> = (0..5_u8).map(|i| Hash::new([i; 32])).collect();
+        let mut source: ProverChannel = ProverChannel::default();
+        source.initialize(&hex!("0123456789abcded"));
+        source.write(hashes.len());
+        for hash in &hashes {
+            source.write(hash);
+        }
+        source.write(&FieldElement::from(42));
+
+        let mut explicit: VerifierChannel = VerifierChannel::new(source.proof.clone());
+        explicit.initialize(&hex!("0123456789abcded"));
+        let mut lazy = explicit.clone();
+
+        let count: usize = explicit.replay();
+        let explicit_hashes: Vec<Hash> = explicit.replay_many(count);
+        let element: FieldElement = explicit.replay();
+        assert_eq!(explicit_hashes, hashes);
+        assert_eq!(element, FieldElement::from(42));
+
+        let count: usize = lazy.replay();
+        for (replayed, expected) in lazy.replay_iter::<Hash>(count).zip(&hashes) {
+            assert_eq!(&replayed, expected);
+        }
+        let element: FieldElement = lazy.replay();
+        assert_eq!(element, FieldElement::from(42));
+        assert_eq!(lazy.coin.digest, explicit.coin.digest);
+        assert_eq!(lazy.coin.digest, source.coin.digest);
+
+        // Items that are not pulled are not read.
+        let mut partial: VerifierChannel = VerifierChannel::new(source.proof.clone());
+        let _: usize = partial.replay();
+        assert_eq!(partial.replay_iter::<Hash>(5).take(2).count(), 2);
+        assert_eq!(partial.bytes_remaining(), 3 * 32 + 32);
+    }
+
+    #[test]
+    fn test_seek() {
+        let mut source: ProverChannel = ProverChannel::default();
+        source.initialize(&hex!("0123456789abcded"));
+        source.write(&[0xab_u8; 32][..]);
+        source.write(42_u64);
+        source.write(&[0xcd_u8; 32][..]);
+
+        let mut verifier: VerifierChannel = VerifierChannel::new(source.proof.clone());
+        verifier.initialize(&hex!("0123456789abcded"));
+        let first: [u8; 32] = verifier.replay();
+        let int: u64 = verifier.replay();
+        assert_eq!(verifier.tell(), 40);
+        let digest = verifier.coin.digest;
+
+        // Seeking past the read part is not allowed
+        assert_eq!(verifier.seek(41), Err(Error::CoinDesync));
+        assert_eq!(verifier.tell(), 40);
+
+        // Re-reading does not change the coin
+        assert_eq!(verifier.seek(0), Ok(()));
+        let again: [u8; 32] = verifier.replay();
+        assert_eq!(again, first);
+        assert_eq!(verifier.coin.digest, digest);
+        // Reads can not cross into the unread part
+        assert_eq!(verifier.replay_bytes(9), Err(Error::CoinDesync));
+        let again: u64 = verifier.replay();
+        assert_eq!(again, int);
+        assert_eq!(verifier.coin.digest, digest);
+
+        // Seek backward then forward and continue as normal
+        assert_eq!(verifier.seek(32), Ok(()));
+        assert_eq!(verifier.seek(40), Ok(()));
+        let last: [u8; 32] = verifier.replay();
+        assert_eq!(last, [0xcd_u8; 32]);
+        assert_eq!(verifier.coin.digest, source.coin.digest);
+        assert_eq!(verifier.finalize(), Ok(()));
+    }
+
+    #[test]
+    fn test_streaming() {
+        let mut buffered: ProverChannel = ProverChannel::default();
+        buffered.initialize(&hex!("0123456789abcded"));
+        let mut streaming = ChannelBuilder::default()
+            .seed(&hex!("0123456789abcded"))
+            .build_streaming_prover(Vec::new());
+        assert_eq!(streaming.coin, buffered.coin);
+
+        let elements: Vec<FieldElement> = (0..10).map(FieldElement::from).collect();
+        buffered.write(&[0xab_u8; 32][..]);
+        streaming.write(&[0xab_u8; 32][..]);
+        buffered.write(&elements[..]);
+        streaming.write(&elements[..]);
+        let _: FieldElement = buffered.get_random();
+        let _: FieldElement = streaming.get_random();
+        buffered.write(42_u64);
+        streaming.write(42_u64);
+
+        assert_eq!(streaming.coin, buffered.coin);
+        assert_eq!(streaming.finish().unwrap(), buffered.proof);
+    }
+
+    #[test]
+    fn test_write_slices() {
+        let roots = [[0x01_u8; 32], [0x02_u8; 32], [0x03_u8; 32]];
+        let slices: Vec<&[u8]> = roots.iter().map(|root| &root[..]).collect();
+        let mut channel: ProverChannel = ProverChannel::default();
+        channel.initialize(&hex!("0123456789abcded"));
+        let _: FieldElement = channel.get_random();
+
+        let mut batched = channel.clone();
+        batched.write(&slices[..]);
+        let mut concatenated = channel.clone();
+        concatenated.write(&roots.concat()[..]);
+        let mut separate = channel.clone();
+        for slice in &slices {
+            separate.write(*slice);
+        }
+
+        assert_eq!(batched.proof, concatenated.proof);
+        assert_eq!(batched.coin, concatenated.coin);
+        assert_eq!(batched.proof, separate.proof);
+        assert_ne!(batched.coin, separate.coin);
+    }
+
+    #[test]
+    fn test_integer_round_trip() {
+        let mut source: ProverChannel = ProverChannel::default();
+        source.initialize(&hex!("0123456789abcded"));
+        source.write(0x0102_0304_u32);
+        source.write(0x0102_0304_0506_0708_u64);
+        source.write(42_usize);
+        source.write(true);
+        source.write(false);
         assert_eq!(
-            verifier.coin.digest,
-            hex!("586b2c12cd444cfe29932fcb167fc0be2e575a8d68e4a41d35de8602b0aea929")
+            source.proof,
+            hex!("010203040102030405060708000000000000002a0100").to_vec()
         );
-        let bit_int_vec_test: Vec<U256> = verifier.replay_many(2);
-        assert_eq!(bit_int_vec_test, written_big_int_vec);
+
+        let mut verifier = source.clone().into_verifier(&hex!("0123456789abcded")).unwrap();
+        let int32: u32 = verifier.replay();
+        let int64: u64 = verifier.replay();
+        let size: usize = verifier.replay();
+        let flag_true: bool = verifier.replay();
+        let flag_false: bool = verifier.replay();
+        assert_eq!(int32, 0x0102_0304_u32);
+        assert_eq!(int64, 0x0102_0304_0506_0708_u64);
+        assert_eq!(size, 42);
+        assert!(flag_true);
+        assert!(!flag_false);
         assert_eq!(verifier.coin.digest, source.coin.digest);
+
+        let mut verifier: VerifierChannel = VerifierChannel::new(vec![2]);
+        let flag: Result<bool, Error> = verifier.try_replay();
+        assert_eq!(flag, Err(Error::InvalidEncoding));
     }
 
     #[test]
     fn test_challenge_seed_from_channel() {
-        let mut rand_source = ProverChannel::default();
+        let mut rand_source: ProverChannel = ProverChannel::default();
         rand_source.initialize(&hex!("0123456789abcded"));
         // Verify that reading challenges does not depend on public coin counter.
         // FIX: Make it depend on public coin counter.
@@ -0,0 +1,202 @@
+use crate::{
+    polynomial::DensePolynomial, rational_expression::RationalExpression, trace_table::TraceTable,
+};
+use std::prelude::v1::*;
+use zkp_primefield::{invert_batch, FieldElement, Pow};
+
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+enum Instruction {
+    X,
+    Constant(FieldElement),
+    Trace(usize, isize),
+    Polynomial(DensePolynomial),
+    Add,
+    Neg,
+    Mul,
+    Inv,
+    Exp(usize),
+}
+
+/// A [`RationalExpression`] flattened to instructions for a stack machine.
+///
+/// Created using [`RationalExpression::compile`]. The expression tree is only
+/// traversed once, when compiling. Evaluation runs each instruction on all
+/// rows at once, so the cost of the traversal is amortized over the domain.
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct CompiledExpression {
+    instructions: Vec<Instruction>,
+}
+
+impl RationalExpression {
+    /// Flatten the expression for evaluation on an entire domain, see
+    /// [`CompiledExpression::evaluate_domain`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the expression contains a `ClaimPolynomial`.
+    pub fn compile(&self) -> CompiledExpression {
+        let mut instructions = Vec::new();
+        self.compile_into(&mut instructions);
+        CompiledExpression { instructions }
+    }
+
+    /// Appends the instructions in post-order, operands before operators.
+    fn compile_into(&self, instructions: &mut Vec<Instruction>) {
+        use RationalExpression::*;
+        let instruction = match self {
+            X => Instruction::X,
+            Constant(c) => Instruction::Constant(c.clone()),
+            &Trace(i, j) => Instruction::Trace(i, j),
+            Polynomial(p, a) => {
+                a.compile_into(instructions);
+                Instruction::Polynomial(p.clone())
+            }
+            ClaimPolynomial(..) => panic!("ClaimPolynomial should be substituted by Polynomial"),
+            Add(a, b) => {
+                a.compile_into(instructions);
+                b.compile_into(instructions);
+                Instruction::Add
+            }
+            Neg(a) => {
+                a.compile_into(instructions);
+                Instruction::Neg
+            }
+            Mul(a, b) => {
+                a.compile_into(instructions);
+                b.compile_into(instructions);
+                Instruction::Mul
+            }
+            Inv(a) => {
+                a.compile_into(instructions);
+                Instruction::Inv
+            }
+            Exp(a, e) => {
+                a.compile_into(instructions);
+                Instruction::Exp(*e)
+            }
+        };
+        instructions.push(instruction);
+    }
+}
+
+impl CompiledExpression {
+    /// Evaluate the expression on every row of `trace`, where `X` takes the
+    /// value `domain[i]` on row `i`.
+    ///
+    /// `Trace(column, offset)` on row `i` refers to row `i + offset` of the
+    /// trace, wrapping around. The result is the same as calling
+    /// [`RationalExpression::evaluate`] for each row.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `domain` and `trace` differ in length, if the trace has too
+    /// few columns, or on division by zero. Inverses are computed in a batch,
+    /// so the panic does not identify the row.
+    pub fn evaluate_domain(&self, trace: &TraceTable, domain: &[FieldElement]) -> Vec<FieldElement> {
+        use Instruction::*;
+        let size = trace.num_rows();
+        assert_eq!(domain.len(), size, "Domain and trace differ in length");
+        let mut stack: Vec<Vec<FieldElement>> = Vec::new();
+        for instruction in &self.instructions {
+            let result = match instruction {
+                X => domain.to_vec(),
+                Constant(c) => vec![c.clone(); size],
+                &Trace(i, j) => {
+                    let mut column: Vec<_> = trace.column(i).cloned().collect();
+                    #[allow(clippy::cast_possible_wrap, clippy::cast_sign_loss)]
+                    column.rotate_left(j.rem_euclid(size as isize) as usize);
+                    column
+                }
+                Polynomial(p) => {
+                    let mut a = stack.pop().unwrap();
+                    for value in &mut a {
+                        *value = p.evaluate(value);
+                    }
+                    a
+                }
+                Add => {
+                    let b = stack.pop().unwrap();
+                    let mut a = stack.pop().unwrap();
+                    for (a, b) in a.iter_mut().zip(&b) {
+                        *a += b;
+                    }
+                    a
+                }
+                Neg => {
+                    let mut a = stack.pop().unwrap();
+                    for value in &mut a {
+                        *value = -&*value;
+                    }
+                    a
+                }
+                Mul => {
+                    let b = stack.pop().unwrap();
+                    let mut a = stack.pop().unwrap();
+                    for (a, b) in a.iter_mut().zip(&b) {
+                        *a *= b;
+                    }
+                    a
+                }
+                Inv => invert_batch(&stack.pop().unwrap()),
+                &Exp(e) => {
+                    let mut a = stack.pop().unwrap();
+                    for value in &mut a {
+                        *value = value.pow(e);
+                    }
+                    a
+                }
+            };
+            stack.push(result);
+        }
+        stack.pop().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zkp_primefield::{One, Root};
+    use RationalExpression::*;
+
+    #[test]
+    fn evaluate_domain() {
+        let size = 16;
+        let mut trace = TraceTable::new(size, 2);
+        for i in 0..size {
+            trace[(i, 0)] = FieldElement::from(i + 3);
+            trace[(i, 1)] = FieldElement::from(7 * i * i + 1);
+        }
+        // A coset, so denominators in X do not vanish.
+        let generator = FieldElement::root(size).unwrap();
+        let mut domain = vec![FieldElement::from(5)];
+        for i in 1..size {
+            let next = &domain[i - 1] * &generator;
+            domain.push(next);
+        }
+        let p = DensePolynomial::new(&[1.into(), 2.into(), 3.into(), 0.into()]);
+        let expression = (Trace(0, 1) - Trace(1, -1).pow(3)) / (X.pow(size) - 1)
+            + Polynomial(p, Box::new(Trace(1, 0) * X))
+            - Trace(0, -17).neg() / Trace(0, 2);
+
+        let compiled = expression.compile();
+        let result = compiled.evaluate_domain(&trace, &domain);
+        for (row, (x, value)) in domain.iter().zip(&result).enumerate() {
+            let values = |i: usize, j: isize| {
+                #[allow(clippy::cast_possible_wrap, clippy::cast_sign_loss)]
+                let row = (row as isize + j).rem_euclid(size as isize) as usize;
+                trace[(row, i)].clone()
+            };
+            assert_eq!(value, &expression.evaluate(x, &values));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Division by zero")]
+    fn evaluate_domain_division_by_zero() {
+        let trace = TraceTable::new(4, 1);
+        let domain = vec![FieldElement::one(); 4];
+        let _ = Trace(0, 0).inv().compile().evaluate_domain(&trace, &domain);
+    }
+}
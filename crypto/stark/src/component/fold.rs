@@ -1,5 +1,5 @@
 use super::{Component, Mapped, PolynomialWriter};
-use crate::RationalExpression;
+use crate::{RationalExpression, TraceError};
 use zkp_primefield::fft::permute_index;
 
 /// Note: `Fold::new(Fold::new(A, m), n) == Fold::new(A, m + n)`
@@ -83,7 +83,19 @@ where
             .collect::<Vec<_>>()
     }
 
+    fn channel_seed(&self, claim: &Self::Claim) -> Vec<u8> {
+        self.element.channel_seed(claim)
+    }
+
     fn trace<P: PolynomialWriter>(&self, trace: &mut P, witness: &Self::Witness) {
+        self.try_trace(trace, witness).unwrap()
+    }
+
+    fn try_trace<P: PolynomialWriter>(
+        &self,
+        trace: &mut P,
+        witness: &Self::Witness,
+    ) -> Result<(), TraceError> {
         let reduction = 1 << self.folds;
         let mut trace = Mapped::new(
             trace,
@@ -96,7 +108,7 @@ where
                 (polynomial, location)
             },
         );
-        self.element.trace(&mut trace, witness)
+        self.element.try_trace(&mut trace, witness)
     }
 }
 
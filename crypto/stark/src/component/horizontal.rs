@@ -1,5 +1,5 @@
-use super::{Component, Mapped, PolynomialWriter};
-use crate::RationalExpression;
+use super::{concat_seeds, Component, Mapped, PolynomialWriter};
+use crate::{RationalExpression, TraceError};
 
 #[derive(Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "std", derive(Debug))]
@@ -45,7 +45,10 @@ where
     fn polynomial_size(&self) -> usize {
         let left = self.left().polynomial_size();
         let right = self.right().polynomial_size();
-        assert_eq!(left, right);
+        assert_eq!(
+            left, right,
+            "Horizontal components must have the same polynomial size"
+        );
         left
     }
 
@@ -75,14 +78,29 @@ where
         result
     }
 
+    fn channel_seed(&self, claim: &Self::Claim) -> Vec<u8> {
+        concat_seeds(vec![
+            self.left.channel_seed(&claim.0),
+            self.right.channel_seed(&claim.1),
+        ])
+    }
+
     fn trace<P: PolynomialWriter>(&self, trace: &mut P, witness: &Self::Witness) {
+        self.try_trace(trace, witness).unwrap()
+    }
+
+    fn try_trace<P: PolynomialWriter>(
+        &self,
+        trace: &mut P,
+        witness: &Self::Witness,
+    ) -> Result<(), TraceError> {
         let mut left_trace = Mapped::new(
             trace,
             self.left.num_polynomials(),
             self.left.polynomial_size(),
             |polynomial, location| (polynomial, location),
         );
-        self.left.trace(&mut left_trace, &witness.0);
+        self.left.try_trace(&mut left_trace, &witness.0)?;
         let left_polys = self.left.num_polynomials();
         let mut right_trace = Mapped::new(
             trace,
@@ -90,7 +108,7 @@ where
             self.right.polynomial_size(),
             |polynomial, location| (polynomial + left_polys, location),
         );
-        self.right.trace(&mut right_trace, &witness.1)
+        self.right.try_trace(&mut right_trace, &witness.1)
     }
 }
 
@@ -140,6 +158,29 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_width() {
+        let component = Horizontal::new(Test::new(8, 3, &1.into()), Test::new(8, 2, &2.into()));
+        assert_eq!(component.num_polynomials(), 5);
+        assert_eq!(component.polynomial_size(), 8);
+        let witness = ((3.into(), 4.into()), (5.into(), 6.into()));
+        let trace = component.trace_table(&witness);
+        let left = component.left().trace_table(&witness.0);
+        let right = component.right().trace_table(&witness.1);
+        for i in 0..8 {
+            assert_eq!(&trace[i][..3], &left[i]);
+            assert_eq!(&trace[i][3..], &right[i]);
+        }
+        assert_eq!(component.check(&witness), Ok(()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Horizontal components must have the same polynomial size")]
+    fn test_size_mismatch() {
+        let component = Horizontal::new(Test::new(8, 1, &1.into()), Test::new(16, 1, &1.into()));
+        let _ = component.polynomial_size();
+    }
+
     // Test `Horizontal::new(Horizontal::new(A, B), C) == Horizontal::new(A,
     // Horizontal::new(B, C))`
     #[test]
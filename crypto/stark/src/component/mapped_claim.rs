@@ -0,0 +1,121 @@
+use super::{Component, PolynomialWriter};
+use crate::{RationalExpression, TraceError};
+use std::marker::PhantomData;
+
+/// Component with the claim type adapted through a projection.
+///
+/// Constructed using [`Component::map_claim`]. Since the projection can not
+/// be inverted, the witness carries the outer claim next to the inner witness,
+/// like the witness of [`super::Test`] carries its claim.
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct MappedClaim<Inner, Claim, F>
+where
+    Inner: Component,
+    F: Fn(&Claim) -> Inner::Claim,
+{
+    inner:  Inner,
+    map:    F,
+    _claim: PhantomData<Claim>,
+}
+
+impl<Inner, Claim, F> MappedClaim<Inner, Claim, F>
+where
+    Inner: Component,
+    F: Fn(&Claim) -> Inner::Claim,
+{
+    pub fn new(inner: Inner, map: F) -> Self {
+        Self {
+            inner,
+            map,
+            _claim: PhantomData,
+        }
+    }
+
+    pub fn inner(&self) -> &Inner {
+        &self.inner
+    }
+}
+
+impl<Inner, Claim, F> Component for MappedClaim<Inner, Claim, F>
+where
+    Inner: Component,
+    Claim: Clone,
+    F: Fn(&Claim) -> Inner::Claim,
+{
+    type Claim = Claim;
+    type Witness = (Claim, Inner::Witness);
+
+    fn num_polynomials(&self) -> usize {
+        self.inner.num_polynomials()
+    }
+
+    fn polynomial_size(&self) -> usize {
+        self.inner.polynomial_size()
+    }
+
+    fn claim(&self, witness: &Self::Witness) -> Self::Claim {
+        witness.0.clone()
+    }
+
+    fn constraints(&self, claim: &Self::Claim) -> Vec<RationalExpression> {
+        self.inner.constraints(&(self.map)(claim))
+    }
+
+    fn channel_seed(&self, claim: &Self::Claim) -> Vec<u8> {
+        self.inner.channel_seed(&(self.map)(claim))
+    }
+
+    fn trace<P: PolynomialWriter>(&self, trace: &mut P, witness: &Self::Witness) {
+        self.try_trace(trace, witness).unwrap()
+    }
+
+    fn try_trace<P: PolynomialWriter>(
+        &self,
+        trace: &mut P,
+        witness: &Self::Witness,
+    ) -> Result<(), TraceError> {
+        self.inner.try_trace(trace, &witness.1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{test::Test, Empty},
+        *,
+    };
+    use zkp_primefield::FieldElement;
+
+    #[test]
+    fn test_unit_claim() {
+        let component = Empty::new(2, 8).map_claim(|_: &u64| ());
+        let witness = (42_u64, ());
+        assert_eq!(component.claim(&witness), 42);
+        assert!(component.constraints(&42).is_empty());
+        assert_eq!(component.check(&witness), Ok(()));
+    }
+
+    #[test]
+    fn test_projected_claim() {
+        let inner = Test::new(8, 2, &3.into());
+        let component = inner
+            .clone()
+            .map_claim(|claim: &u64| FieldElement::from(*claim));
+        let inner_witness = (FieldElement::from(5), FieldElement::from(7));
+        let witness = (5_u64, inner_witness.clone());
+        let claim = component.claim(&witness);
+        for (result, expected) in component
+            .constraints(&claim)
+            .iter()
+            .zip(inner.constraints(&inner.claim(&inner_witness)).iter())
+        {
+            assert_eq!(result, expected);
+        }
+        assert_eq!(
+            component.trace_table(&witness),
+            inner.trace_table(&inner_witness)
+        );
+        assert_eq!(component.check(&witness), Ok(()));
+    }
+}
@@ -2,25 +2,221 @@ mod empty;
 mod fold;
 mod horizontal;
 mod mapped;
+mod mapped_claim;
+mod sequence;
 mod test;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
 mod vertical;
+mod with_claim;
 
 use crate::{
-    constraint_check::check_constraints,
+    constraint_check::{check_constraints, check_trace_arguments, constraint_failures, cyclic_row},
     proof::Proof,
     prover::prove,
     verifier::{verify, Error as VerifierError},
-    Constraints, ProverError, RationalExpression, TraceTable,
+    Constraints, ProverError, RationalExpression, TraceError, TraceTable,
 };
 use log::trace;
-use zkp_primefield::{FieldElement, Root};
+use std::fmt;
+use zkp_primefield::{FieldElement, Pow, Root};
 
 pub use empty::Empty;
 pub use fold::Fold;
 pub use horizontal::Horizontal;
 pub use mapped::Mapped;
+pub use mapped_claim::MappedClaim;
+pub use sequence::Sequence;
 pub use test::Test;
 pub use vertical::Vertical;
+pub use with_claim::WithClaim;
+
+/// A public claim that can be bound into the proof transcript.
+///
+/// Components can use it to implement [`Component::channel_seed`], so
+/// different claims result in different verifier challenges.
+pub trait WritableClaim {
+    /// Append an unambiguous encoding of the claim to `seed`.
+    fn write_claim(&self, seed: &mut Vec<u8>);
+}
+
+impl WritableClaim for () {
+    fn write_claim(&self, _seed: &mut Vec<u8>) {}
+}
+
+/// Encoded as 8 big-endian bytes.
+impl WritableClaim for u64 {
+    fn write_claim(&self, seed: &mut Vec<u8>) {
+        seed.extend_from_slice(&self.to_be_bytes());
+    }
+}
+
+/// Encoded as a `u64`.
+impl WritableClaim for usize {
+    fn write_claim(&self, seed: &mut Vec<u8>) {
+        (*self as u64).write_claim(seed);
+    }
+}
+
+/// Encoded as 32 big-endian bytes of the canonical (not Montgomery) value.
+impl WritableClaim for FieldElement {
+    fn write_claim(&self, seed: &mut Vec<u8>) {
+        seed.extend_from_slice(&self.to_uint().to_bytes_be());
+    }
+}
+
+impl<A: WritableClaim, B: WritableClaim> WritableClaim for (A, B) {
+    fn write_claim(&self, seed: &mut Vec<u8>) {
+        self.0.write_claim(seed);
+        self.1.write_claim(seed);
+    }
+}
+
+/// Encoded as the length followed by the elements.
+impl<T: WritableClaim> WritableClaim for Vec<T> {
+    fn write_claim(&self, seed: &mut Vec<u8>) {
+        self.len().write_claim(seed);
+        for element in self {
+            element.write_claim(seed);
+        }
+    }
+}
+
+/// Combines the channel seeds of the parts of a composite component.
+///
+/// Each seed is prefixed with its length as a `u64`, so the parts can not run
+/// into each other.
+fn concat_seeds<I: IntoIterator<Item = Vec<u8>>>(seeds: I) -> Vec<u8> {
+    let mut result = Vec::new();
+    for seed in seeds {
+        seed.len().write_claim(&mut result);
+        result.extend_from_slice(&seed);
+    }
+    result
+}
+
+/// A constraint that does not hold on a row of the trace.
+///
+/// Constraints are not named, so the failing expression itself is included to
+/// locate it.
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct ConstraintFailure {
+    pub row:        usize,
+    pub constraint: usize,
+    pub expression: RationalExpression,
+}
+
+/// A constraint that does not hold on a row of the trace, with the values
+/// involved. Returned by [`Component::check_verbose`].
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct ConstraintViolation {
+    pub row:        usize,
+    pub constraint: usize,
+    pub expression: RationalExpression,
+    /// The numerator of the expression on the row. It should be zero, so the
+    /// constraint is divisible by its denominator.
+    pub value:      FieldElement,
+    /// The trace values the expression references, as `(column, row, value)`.
+    pub trace:      Vec<(usize, usize, FieldElement)>,
+}
+
+/// Why [`Component::check`] failed.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum CheckError {
+    /// The trace could not be constructed, see [`Component::try_trace`].
+    TraceFailed(TraceError),
+    /// A constraint does not hold. Contains the row and the constraint index.
+    ConstraintFailed(usize, usize),
+    /// A constraint references a column outside the trace table. Contains the
+    /// constraint index and the column and offset of the reference.
+    InvalidTraceArgument(usize, usize, isize),
+}
+
+impl fmt::Display for CheckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use CheckError::*;
+        match *self {
+            TraceFailed(ref e) => fmt::Display::fmt(e, f),
+            ConstraintFailed(row, constraint) => {
+                write!(f, "Constraint {} fails on row {}", constraint, row)
+            }
+            InvalidTraceArgument(constraint, column, offset) => {
+                write!(
+                    f,
+                    "Constraint {} references Trace({}, {}) outside the trace table",
+                    constraint, column, offset
+                )
+            }
+        }
+    }
+}
+
+impl From<TraceError> for CheckError {
+    fn from(err: TraceError) -> Self {
+        Self::TraceFailed(err)
+    }
+}
+
+impl From<DetailedCheckError<(usize, usize)>> for CheckError {
+    fn from(err: DetailedCheckError<(usize, usize)>) -> Self {
+        use DetailedCheckError::*;
+        match err {
+            TraceFailed(e) => Self::TraceFailed(e),
+            ConstraintFailed((row, constraint)) => Self::ConstraintFailed(row, constraint),
+            InvalidTraceArgument(constraint, column, offset) => {
+                Self::InvalidTraceArgument(constraint, column, offset)
+            }
+        }
+    }
+}
+
+/// Why [`Component::check_named`] or [`Component::check_verbose`] failed.
+/// Like [`CheckError`], but a failing constraint is described by `F`.
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum DetailedCheckError<F> {
+    /// The trace could not be constructed, see [`Component::try_trace`].
+    TraceFailed(TraceError),
+    /// A constraint does not hold.
+    ConstraintFailed(F),
+    /// A constraint references a column outside the trace table. Contains the
+    /// constraint index and the column and offset of the reference.
+    InvalidTraceArgument(usize, usize, isize),
+}
+
+impl<F: fmt::Display> fmt::Display for DetailedCheckError<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use DetailedCheckError::*;
+        match self {
+            TraceFailed(e) => fmt::Display::fmt(e, f),
+            ConstraintFailed(failure) => fmt::Display::fmt(failure, f),
+            InvalidTraceArgument(constraint, column, offset) => {
+                fmt::Display::fmt(
+                    &CheckError::InvalidTraceArgument(*constraint, *column, *offset),
+                    f,
+                )
+            }
+        }
+    }
+}
+
+impl<F> From<TraceError> for DetailedCheckError<F> {
+    fn from(err: TraceError) -> Self {
+        Self::TraceFailed(err)
+    }
+}
+
+impl fmt::Display for ConstraintFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Constraint {} fails on row {}: {}",
+            self.constraint, self.row, self.expression
+        )
+    }
+}
 
 /// A set of Polynomials represented by their values at roots of unity.
 pub trait PolynomialWriter {
@@ -64,10 +260,36 @@ pub trait Component {
 
     fn constraints(&self, claim: &Self::Claim) -> Vec<RationalExpression>;
 
+    /// Seed for the proof channel.
+    ///
+    /// The default is empty for backwards compatibility. Components should
+    /// bind their public claim here, otherwise the Fiat-Shamir transcript,
+    /// and with it every verifier challenge, is the same for different claims
+    /// and a proof transcript can be reused across claims. The combinators in
+    /// this module include the seeds of their parts.
+    fn channel_seed(&self, _claim: &Self::Claim) -> Vec<u8> {
+        Vec::new()
+    }
+
     // TODO: add claim_polynomials function here.
 
     fn trace<P: PolynomialWriter>(&self, trace: &mut P, witness: &Self::Witness);
 
+    /// Like [`Component::trace`], but fails on a witness that is inconsistent
+    /// with the claim, for example with [`TraceError::InvalidWitness`].
+    ///
+    /// The default calls [`Component::trace`]. Components with witnesses that
+    /// can be invalid should override this and implement `trace` by
+    /// unwrapping it.
+    fn try_trace<P: PolynomialWriter>(
+        &self,
+        trace: &mut P,
+        witness: &Self::Witness,
+    ) -> Result<(), TraceError> {
+        self.trace(trace, witness);
+        Ok(())
+    }
+
     fn trace_generator(&self) -> RationalExpression {
         FieldElement::root(self.polynomial_size())
             .expect("num_polynomials not power of 2.")
@@ -85,13 +307,31 @@ pub trait Component {
         trace_table
     }
 
+    /// Construct a trace table using [`Component::try_trace`].
+    fn try_trace_table(&self, witness: &Self::Witness) -> Result<TraceTable, TraceError> {
+        let polynomials = self.num_polynomials();
+        let size = self.polynomial_size();
+        let mut trace_table = TraceTable::new(size, polynomials);
+        self.try_trace(&mut trace_table, witness)?;
+        Ok(trace_table)
+    }
+
+    /// Adapt the claim type by projecting it to this component's claim.
+    fn map_claim<C, F>(self, f: F) -> MappedClaim<Self, C, F>
+    where
+        Self: Sized,
+        F: Fn(&C) -> Self::Claim,
+    {
+        MappedClaim::new(self, f)
+    }
+
     fn prove(&self, witness: &Self::Witness) -> Result<Proof, ProverError> {
         let polynomials = self.num_polynomials();
         let size = self.polynomial_size();
         let claim = self.claim(witness);
-        let channel_seed = Vec::new();
+        let channel_seed = self.channel_seed(&claim);
         let expressions = self.constraints(&claim);
-        let trace = self.trace_table(witness);
+        let trace = self.try_trace_table(witness)?;
         let constraints =
             Constraints::from_expressions((size, polynomials), channel_seed, expressions).unwrap();
         prove(&constraints, &trace)
@@ -100,23 +340,482 @@ pub trait Component {
     fn verify(&self, claim: &Self::Claim, proof: &Proof) -> Result<(), VerifierError> {
         let polynomials = self.num_polynomials();
         let size = self.polynomial_size();
-        let channel_seed = Vec::new();
+        let channel_seed = self.channel_seed(claim);
         let expressions = self.constraints(claim);
         let constraints =
             Constraints::from_expressions((size, polynomials), channel_seed, expressions).unwrap();
         verify(&constraints, proof)
     }
 
-    fn check(&self, witness: &Self::Witness) -> Result<(), (usize, usize)> {
-        let polynomials = self.num_polynomials();
-        let size = self.polynomial_size();
-        let claim = self.claim(witness);
-        let channel_seed = Vec::new();
-        let expressions = self.constraints(&claim);
-        // TODO: Error handling
-        let constraints =
-            Constraints::from_expressions((size, polynomials), channel_seed, expressions).unwrap();
-        let trace = self.trace_table(witness);
+    /// Checks the constraints on the trace for `witness`, without proving.
+    ///
+    /// Constraints referencing a column outside the trace are rejected before
+    /// the trace is built, see [`Constraints::check_trace_arguments`]. Trace
+    /// offsets wrap around, see [`check_constraints`].
+    fn check(&self, witness: &Self::Witness) -> Result<(), CheckError> {
+        let (constraints, trace) = constraints_and_trace(self, witness)?;
         check_constraints(&constraints, &trace)
+            .map_err(|(row, constraint)| CheckError::ConstraintFailed(row, constraint))
+    }
+
+    /// Runs [`Component::check`] followed by a full proof and verification.
+    ///
+    /// Returns a description of the first step that failed.
+    #[cfg(any(test, feature = "test-utils"))]
+    fn test_prove_verify(
+        &self,
+        claim: &Self::Claim,
+        witness: &Self::Witness,
+    ) -> Result<(), String> {
+        self.check(witness).map_err(|e| e.to_string())?;
+        let proof = self
+            .prove(witness)
+            .map_err(|e| format!("Proving failed: {}", e))?;
+        self.verify(claim, &proof)
+            .map_err(|e| format!("Verification failed: {}", e))
+    }
+
+    /// Like [`Component::check`], but reports the failing constraint
+    /// expression with the row.
+    fn check_named(
+        &self,
+        witness: &Self::Witness,
+    ) -> Result<(), DetailedCheckError<ConstraintFailure>> {
+        let (constraints, trace) = constraints_and_trace(self, witness)?;
+        check_constraints(&constraints, &trace).map_err(|(row, constraint)| {
+            DetailedCheckError::ConstraintFailed(ConstraintFailure {
+                row,
+                constraint,
+                expression: constraints.expressions()[constraint].clone(),
+            })
+        })
+    }
+
+    /// Like [`Component::check`], but reports all constraint violations with
+    /// the value of the constraint and the trace values it uses.
+    ///
+    /// Violations are ordered by row, then by constraint.
+    fn check_verbose(
+        &self,
+        witness: &Self::Witness,
+    ) -> Result<(), DetailedCheckError<Vec<ConstraintViolation>>> {
+        let size = self.polynomial_size();
+        let (constraints, trace) = constraints_and_trace(self, witness)?;
+        let trace_generator = FieldElement::root(size).unwrap();
+        let violations: Vec<_> = constraint_failures(&constraints, &trace)
+            .map(|(row, constraint)| {
+                let expression = constraints.expressions()[constraint].clone();
+                let cells = |column: usize, offset: isize| {
+                    let row = cyclic_row(row, offset, size);
+                    (column, row, trace[(row, column)].clone())
+                };
+                let (numerator, _) = expression.fraction();
+                let value = numerator.evaluate(&trace_generator.pow(row), &|column, offset| {
+                    cells(column, offset).2
+                });
+                let trace = expression
+                    .trace_arguments()
+                    .into_iter()
+                    .map(|(column, offset)| cells(column, offset))
+                    .collect();
+                ConstraintViolation {
+                    row,
+                    constraint,
+                    expression,
+                    value,
+                    trace,
+                }
+            })
+            .collect();
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(DetailedCheckError::ConstraintFailed(violations))
+        }
+    }
+}
+
+/// Builds the constraints and trace table of `component` for `witness`.
+///
+/// Constraints referencing a column outside the trace are rejected before the
+/// trace is built.
+fn constraints_and_trace<C: Component + ?Sized, F>(
+    component: &C,
+    witness: &C::Witness,
+) -> Result<(Constraints, TraceTable), DetailedCheckError<F>> {
+    let polynomials = component.num_polynomials();
+    let size = component.polynomial_size();
+    let claim = component.claim(witness);
+    let channel_seed = component.channel_seed(&claim);
+    let expressions = component.constraints(&claim);
+    check_trace_arguments(&expressions, polynomials).map_err(|(constraint, column, offset)| {
+        DetailedCheckError::InvalidTraceArgument(constraint, column, offset)
+    })?;
+    // TODO: Error handling
+    let constraints =
+        Constraints::from_expressions((size, polynomials), channel_seed, expressions).unwrap();
+    let trace = component.try_trace_table(witness)?;
+    Ok((constraints, trace))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        channel::{ProverChannel, RandomGenerator},
+        Provable, Verifiable,
+    };
+    use zkp_primefield::{Inv, Zero};
+
+    /// Two columns, the first is the claim on every row and the second starts
+    /// with two.
+    struct Pair;
+
+    impl Component for Pair {
+        type Claim = FieldElement;
+        type Witness = (FieldElement, FieldElement);
+
+        fn num_polynomials(&self) -> usize {
+            2
+        }
+
+        fn polynomial_size(&self) -> usize {
+            8
+        }
+
+        fn claim(&self, witness: &Self::Witness) -> Self::Claim {
+            witness.0.clone()
+        }
+
+        fn constraints(&self, claim: &Self::Claim) -> Vec<RationalExpression> {
+            use RationalExpression::*;
+            vec![
+                (Trace(0, 0) - Constant(claim.clone())) / (X.pow(8) - 1),
+                (Trace(1, 0) - 2) / (X - 1),
+            ]
+        }
+
+        fn channel_seed(&self, claim: &Self::Claim) -> Vec<u8> {
+            let mut seed = Vec::new();
+            claim.write_claim(&mut seed);
+            seed
+        }
+
+        fn trace<P: PolynomialWriter>(&self, trace: &mut P, witness: &Self::Witness) {
+            for i in 0..8 {
+                trace.write(0, i, witness.0.clone());
+            }
+            trace.write(1, 0, witness.1.clone());
+        }
+    }
+
+    /// One column with the witness and its inverse. Zero has no inverse.
+    struct Inverse;
+
+    impl Component for Inverse {
+        type Claim = ();
+        type Witness = FieldElement;
+
+        fn num_polynomials(&self) -> usize {
+            1
+        }
+
+        fn polynomial_size(&self) -> usize {
+            2
+        }
+
+        fn claim(&self, _witness: &Self::Witness) -> Self::Claim {}
+
+        fn constraints(&self, _claim: &Self::Claim) -> Vec<RationalExpression> {
+            use RationalExpression::*;
+            vec![(Trace(0, 0) * Trace(0, 1) - 1) / (X - 1)]
+        }
+
+        fn trace<P: PolynomialWriter>(&self, trace: &mut P, witness: &Self::Witness) {
+            self.try_trace(trace, witness).unwrap()
+        }
+
+        fn try_trace<P: PolynomialWriter>(
+            &self,
+            trace: &mut P,
+            witness: &Self::Witness,
+        ) -> Result<(), TraceError> {
+            let inverse = witness.inv().ok_or(TraceError::InvalidWitness)?;
+            trace.write(0, 0, witness.clone());
+            trace.write(0, 1, inverse);
+            Ok(())
+        }
+    }
+
+    /// One constant column, compared to the given column and offset.
+    struct Shift(usize, isize);
+
+    impl Component for Shift {
+        type Claim = ();
+        type Witness = ();
+
+        fn num_polynomials(&self) -> usize {
+            1
+        }
+
+        fn polynomial_size(&self) -> usize {
+            4
+        }
+
+        fn claim(&self, _witness: &Self::Witness) -> Self::Claim {}
+
+        fn constraints(&self, _claim: &Self::Claim) -> Vec<RationalExpression> {
+            use RationalExpression::*;
+            vec![Trace(self.0, self.1) - Trace(0, 0)]
+        }
+
+        fn trace<P: PolynomialWriter>(&self, trace: &mut P, _witness: &Self::Witness) {
+            for i in 0..4 {
+                trace.write(0, i, 3.into());
+            }
+        }
+    }
+
+    /// Like [`Pair`], but claims one more than the first column.
+    struct Mismatch;
+
+    impl Component for Mismatch {
+        type Claim = FieldElement;
+        type Witness = (FieldElement, FieldElement);
+
+        fn num_polynomials(&self) -> usize {
+            Pair.num_polynomials()
+        }
+
+        fn polynomial_size(&self) -> usize {
+            Pair.polynomial_size()
+        }
+
+        fn claim(&self, witness: &Self::Witness) -> Self::Claim {
+            &witness.0 + FieldElement::from(1)
+        }
+
+        fn constraints(&self, claim: &Self::Claim) -> Vec<RationalExpression> {
+            Pair.constraints(claim)
+        }
+
+        fn trace<P: PolynomialWriter>(&self, trace: &mut P, witness: &Self::Witness) {
+            Pair.trace(trace, witness)
+        }
+    }
+
+    fn first_challenge(seed: &[u8]) -> FieldElement {
+        let mut channel: ProverChannel = ProverChannel::default();
+        channel.initialize(seed);
+        channel.get_random()
+    }
+
+    #[test]
+    fn test_channel_seed() {
+        let seed_a = Pair.channel_seed(&5.into());
+        let seed_b = Pair.channel_seed(&6.into());
+        assert_ne!(seed_a, seed_b);
+        assert_ne!(first_challenge(&seed_a), first_challenge(&seed_b));
+
+        let witness = (5.into(), 2.into());
+        let proof = Pair.prove(&witness).unwrap();
+        assert_eq!(Pair.verify(&5.into(), &proof), Ok(()));
+    }
+
+    #[test]
+    fn test_composed_channel_seed() {
+        let seed = |claim: u64| Pair.channel_seed(&claim.into());
+        let prefixed = |claim: u64| [&32_u64.to_be_bytes()[..], &seed(claim)].concat();
+        let pair = || [prefixed(5), prefixed(6)].concat();
+
+        assert_eq!(Fold::new(Pair, 1).channel_seed(&5.into()), seed(5));
+        assert_eq!(
+            Pair.map_claim(|claim: &u64| FieldElement::from(*claim + 1))
+                .channel_seed(&4),
+            seed(5)
+        );
+        assert_eq!(
+            Horizontal::new(Pair, Pair).channel_seed(&(5.into(), 6.into())),
+            pair()
+        );
+        assert_eq!(
+            Sequence::new(Pair, Pair).channel_seed(&(5.into(), 6.into())),
+            pair()
+        );
+        assert_eq!(
+            Vertical::new(Pair, 2).channel_seed(&vec![5.into(), 6.into()]),
+            pair()
+        );
+        assert_ne!(
+            Horizontal::new(Pair, Pair).channel_seed(&(6.into(), 5.into())),
+            pair()
+        );
+    }
+
+    #[test]
+    fn test_check_named() {
+        assert_eq!(Pair.check_named(&(5.into(), 2.into())), Ok(()));
+        let failure = match Pair.check_named(&(5.into(), 3.into())) {
+            Err(DetailedCheckError::ConstraintFailed(failure)) => failure,
+            other => panic!("Unexpected result {:?}", other),
+        };
+        assert_eq!(failure.row, 0);
+        assert_eq!(failure.constraint, 1);
+        assert_eq!(failure.expression, Pair.constraints(&5.into())[1]);
+        assert_eq!(
+            failure.to_string(),
+            format!("Constraint 1 fails on row 0: {}", failure.expression)
+        );
+    }
+
+    #[test]
+    fn test_check_verbose() {
+        use RationalExpression::*;
+        assert_eq!(Pair.check_verbose(&(5.into(), 2.into())), Ok(()));
+
+        let mut witness = (FieldElement::from(5), FieldElement::from(3));
+        let violations = match Pair.check_verbose(&witness) {
+            Err(DetailedCheckError::ConstraintFailed(violations)) => violations,
+            other => panic!("Unexpected result {:?}", other),
+        };
+        assert_eq!(violations, vec![ConstraintViolation {
+            row:        0,
+            constraint: 1,
+            expression: Pair.constraints(&5.into())[1].clone(),
+            value:      1.into(),
+            trace:      vec![(1, 0, 3.into())],
+        }]);
+
+        // The first column does not match the claim on any row.
+        witness.1 = 2.into();
+        let violations = match Mismatch.check_verbose(&witness) {
+            Err(DetailedCheckError::ConstraintFailed(violations)) => violations,
+            other => panic!("Unexpected result {:?}", other),
+        };
+        assert_eq!(violations.len(), 8);
+        for (row, violation) in violations.iter().enumerate() {
+            assert_eq!(violation.row, row);
+            assert_eq!(violation.constraint, 0);
+            assert_eq!(violation.value, -&FieldElement::from(1));
+            assert_eq!(violation.trace, vec![(0, row, 5.into())]);
+            assert!(violation.expression.denominator().is_some());
+            assert_eq!(
+                violation.expression,
+                (Trace(0, 0) - Constant(6.into())) / (X.pow(8) - 1)
+            );
+        }
+    }
+
+    #[test]
+    fn test_try_trace() {
+        assert_eq!(Inverse.check(&3.into()), Ok(()));
+        let invalid = FieldElement::zero();
+        assert_eq!(
+            Inverse.try_trace_table(&invalid),
+            Err(TraceError::InvalidWitness)
+        );
+        assert_eq!(
+            Inverse.check(&invalid),
+            Err(CheckError::TraceFailed(TraceError::InvalidWitness))
+        );
+        assert_eq!(
+            Inverse.check_named(&invalid),
+            Err(DetailedCheckError::TraceFailed(TraceError::InvalidWitness))
+        );
+        assert_eq!(
+            Inverse.check_verbose(&invalid),
+            Err(DetailedCheckError::TraceFailed(TraceError::InvalidWitness))
+        );
+        assert_eq!(
+            Inverse.prove(&invalid),
+            Err(ProverError::TraceFailed(TraceError::InvalidWitness))
+        );
+        let bundle = WithClaim::new(Inverse, ());
+        assert_eq!(
+            Provable::prove(&bundle, &invalid),
+            Err(ProverError::TraceFailed(TraceError::InvalidWitness))
+        );
+    }
+
+    #[test]
+    fn test_try_trace_composed() {
+        let valid = FieldElement::from(3);
+        let invalid = FieldElement::zero();
+        let failed = Err(TraceError::InvalidWitness);
+        let horizontal = Horizontal::new(Inverse, Inverse);
+        assert!(horizontal
+            .try_trace_table(&(valid.clone(), valid.clone()))
+            .is_ok());
+        assert_eq!(
+            horizontal.try_trace_table(&(valid.clone(), invalid.clone())),
+            failed
+        );
+        let sequence = Sequence::new(Inverse, Inverse);
+        assert_eq!(
+            sequence.try_trace_table(&(invalid.clone(), valid.clone())),
+            failed
+        );
+        let vertical = Vertical::new(Inverse, 2);
+        assert_eq!(
+            vertical.try_trace_table(&vec![valid.clone(), invalid.clone()]),
+            failed
+        );
+        assert_eq!(Fold::new(Inverse, 1).try_trace_table(&invalid), failed);
+        let mapped = Inverse.map_claim(|_: &u64| ());
+        assert_eq!(mapped.try_trace_table(&(0, invalid.clone())), failed);
+        assert_eq!(
+            mapped.check(&(0, invalid)),
+            Err(CheckError::TraceFailed(TraceError::InvalidWitness))
+        );
+    }
+
+    #[test]
+    fn test_check_trace_arguments() {
+        // Offsets wrap around the trace
+        assert_eq!(Shift(0, 3).check(&()), Ok(()));
+        assert_eq!(Shift(0, 4).check(&()), Ok(()));
+        assert_eq!(Shift(0, -5).check(&()), Ok(()));
+        let error = Shift(1, -1).check(&()).unwrap_err();
+        assert_eq!(error, CheckError::InvalidTraceArgument(0, 1, -1));
+        assert_eq!(
+            error.to_string(),
+            "Constraint 0 references Trace(1, -1) outside the trace table"
+        );
+        let error = Shift(1, -1).check_named(&()).unwrap_err();
+        assert_eq!(error, DetailedCheckError::InvalidTraceArgument(0, 1, -1));
+        assert_eq!(
+            Shift(1, -1).check_verbose(&()),
+            Err(DetailedCheckError::InvalidTraceArgument(0, 1, -1))
+        );
+        assert_eq!(
+            error.to_string(),
+            "Constraint 0 references Trace(1, -1) outside the trace table"
+        );
+    }
+
+    #[test]
+    fn test_writable_claim() {
+        let seed = |claim: &(u64, Vec<FieldElement>)| {
+            let mut seed = Vec::new();
+            claim.write_claim(&mut seed);
+            seed
+        };
+        let claim_a = (3_u64, vec![FieldElement::from(5), FieldElement::from(7)]);
+        let claim_b = (3_u64, vec![FieldElement::from(5), FieldElement::from(8)]);
+        assert_eq!(seed(&claim_a).len(), 8 + 8 + 2 * 32);
+        assert_ne!(
+            first_challenge(&seed(&claim_a)),
+            first_challenge(&seed(&claim_b))
+        );
+
+        // The bridge uses the component's seed, which binds the claim.
+        let seed = |claim: u64| {
+            WithClaim::new(Pair, FieldElement::from(claim))
+                .constraints()
+                .channel_seed()
+                .to_vec()
+        };
+        assert_eq!(seed(5), Pair.channel_seed(&5.into()));
+        assert_ne!(first_challenge(&seed(5)), first_challenge(&seed(6)));
     }
 }
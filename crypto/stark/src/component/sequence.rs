@@ -0,0 +1,207 @@
+use super::{concat_seeds, Component, Mapped, PolynomialWriter};
+use crate::{polynomial::DensePolynomial, RationalExpression, TraceError, TraceTable};
+use zkp_primefield::{FieldElement, One};
+
+/// Runs `First` and then `Second` in the same columns.
+///
+/// The trace of `Second` is placed in the rows following the trace of
+/// `First`. Both components must have the same number of polynomials and the
+/// same polynomial size `n`, so the combined trace has `2 n` rows with
+/// generator `ω`.
+///
+/// The constraints of each component need to hold only on its own half of the
+/// rows. In the combined trace row `i` and row `i + n` both have `X^2 = ω^(2
+/// i)`, which is the `X` value of row `i` in the original components. So
+/// substituting `X` by `X^2` turns each constraint into one that holds on row
+/// `i` of both halves. It is then gated by multiplying with a selector
+/// polynomial `s(X)` that is one on the rows of the component and zero on the
+/// other half, interpolated over the `2 n` rows. Since `s` is zero on the other
+/// half, the gated constraint is divisible by the same denominators.
+///
+/// The selector has degree `2 n - 1`, which increases the constraint degree
+/// accordingly. Trace references that cross the boundary between the halves,
+/// or wrap around the end, see the other component's rows and need to be
+/// exempted by the component's own constraints, as they would for wrap around.
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Sequence<First, Second>
+where
+    First: Component,
+    Second: Component,
+{
+    first:  First,
+    second: Second,
+}
+
+impl<First, Second> Sequence<First, Second>
+where
+    First: Component,
+    Second: Component,
+{
+    pub fn new(first: First, second: Second) -> Self {
+        assert_eq!(
+            first.num_polynomials(),
+            second.num_polynomials(),
+            "Sequence components must have the same number of polynomials"
+        );
+        assert_eq!(
+            first.polynomial_size(),
+            second.polynomial_size(),
+            "Sequence components must have the same polynomial size"
+        );
+        Sequence { first, second }
+    }
+
+    pub fn first(&self) -> &First {
+        &self.first
+    }
+
+    pub fn second(&self) -> &Second {
+        &self.second
+    }
+
+    /// Polynomial that is one on the first (or second) half of the rows and
+    /// zero on the other half.
+    fn selector(&self, first: bool) -> DensePolynomial {
+        let size = self.first.polynomial_size();
+        let offset = if first { 0 } else { size };
+        let mut table = TraceTable::new(2 * size, 1);
+        for i in 0..size {
+            table[(offset + i, 0)] = FieldElement::one();
+        }
+        table.interpolate().pop().unwrap()
+    }
+}
+
+impl<First, Second> Component for Sequence<First, Second>
+where
+    First: Component,
+    Second: Component,
+{
+    type Claim = (<First as Component>::Claim, <Second as Component>::Claim);
+    type Witness = (
+        <First as Component>::Witness,
+        <Second as Component>::Witness,
+    );
+
+    fn num_polynomials(&self) -> usize {
+        self.first.num_polynomials()
+    }
+
+    fn polynomial_size(&self) -> usize {
+        2 * self.first.polynomial_size()
+    }
+
+    fn claim(&self, witness: &Self::Witness) -> Self::Claim {
+        (self.first.claim(&witness.0), self.second.claim(&witness.1))
+    }
+
+    fn constraints(&self, claim: &Self::Claim) -> Vec<RationalExpression> {
+        use RationalExpression::*;
+        let gate = |expressions: Vec<RationalExpression>, selector: DensePolynomial| {
+            expressions.into_iter().map(move |expression| {
+                let expression = expression.map(&|node| {
+                    match node {
+                        X => X.pow(2),
+                        other => other,
+                    }
+                });
+                Polynomial(selector.clone(), Box::new(X)) * expression
+            })
+        };
+        let mut result = Vec::new();
+        result.extend(gate(self.first.constraints(&claim.0), self.selector(true)));
+        result.extend(gate(
+            self.second.constraints(&claim.1),
+            self.selector(false),
+        ));
+        result
+    }
+
+    fn channel_seed(&self, claim: &Self::Claim) -> Vec<u8> {
+        concat_seeds(vec![
+            self.first.channel_seed(&claim.0),
+            self.second.channel_seed(&claim.1),
+        ])
+    }
+
+    fn trace<P: PolynomialWriter>(&self, trace: &mut P, witness: &Self::Witness) {
+        self.try_trace(trace, witness).unwrap()
+    }
+
+    fn try_trace<P: PolynomialWriter>(
+        &self,
+        trace: &mut P,
+        witness: &Self::Witness,
+    ) -> Result<(), TraceError> {
+        let num_polynomials = self.first.num_polynomials();
+        let size = self.first.polynomial_size();
+        let mut first_trace = Mapped::new(trace, num_polynomials, size, |polynomial, location| {
+            (polynomial, location)
+        });
+        self.first.try_trace(&mut first_trace, &witness.0)?;
+        let mut second_trace = Mapped::new(trace, num_polynomials, size, |polynomial, location| {
+            (polynomial, location + size)
+        });
+        self.second.try_trace(&mut second_trace, &witness.1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::test::Test, *};
+    use crate::{check_constraints, Constraints};
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_check() {
+        proptest!(|(
+            log_rows in 0_usize..6,
+            cols in 1_usize..5,
+            seeds: (FieldElement, FieldElement),
+            witness: ((FieldElement, FieldElement), (FieldElement, FieldElement)),
+        )| {
+            let rows = 1 << log_rows;
+            let component = Sequence::new(
+                Test::new(rows, cols, &seeds.0),
+                Test::new(rows, cols, &seeds.1),
+            );
+            prop_assert_eq!(component.check(&witness), Ok(()));
+        });
+    }
+
+    #[test]
+    fn test_layout() {
+        let component = Sequence::new(Test::new(8, 2, &1.into()), Test::new(8, 2, &2.into()));
+        let witness = ((3.into(), 4.into()), (5.into(), 6.into()));
+        let trace = component.trace_table(&witness);
+        let first = component.first().trace_table(&witness.0);
+        let second = component.second().trace_table(&witness.1);
+        assert_eq!(trace.dimensions(), (16, 2));
+        for i in 0..8 {
+            assert_eq!(trace.row(i), first.row(i));
+            assert_eq!(trace.row(i + 8), second.row(i));
+        }
+    }
+
+    #[test]
+    fn test_failing_row() {
+        let component = Sequence::new(Test::new(8, 1, &1.into()), Test::new(8, 1, &2.into()));
+        let witness = ((3.into(), 4.into()), (5.into(), 6.into()));
+        let claim = component.claim(&witness);
+        let constraints = Constraints::from_expressions(
+            (component.polynomial_size(), component.num_polynomials()),
+            Vec::new(),
+            component.constraints(&claim),
+        )
+        .unwrap();
+        let mut trace = component.trace_table(&witness);
+        assert_eq!(check_constraints(&constraints, &trace), Ok(()));
+
+        // Break the recurrence in the second half. The first component's
+        // constraints are gated off there, so the failure is reported for the
+        // second component's recurrence constraint.
+        trace[(11, 0)] += FieldElement::one();
+        assert_eq!(check_constraints(&constraints, &trace), Err((11, 3)));
+    }
+}
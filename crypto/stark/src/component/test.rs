@@ -144,4 +144,17 @@ mod tests {
             prop_assert_eq!(result, Ok(()));
         });
     }
+
+    #[test]
+    fn test_prove_verify_harness() {
+        let component = Test::new(16, 3, &2.into());
+        let witness = (3.into(), 5.into());
+        let claim = component.claim(&witness);
+        assert_eq!(component.test_prove_verify(&claim, &witness), Ok(()));
+        let wrong_claim = claim + FieldElement::from(1);
+        assert!(component
+            .test_prove_verify(&wrong_claim, &witness)
+            .unwrap_err()
+            .starts_with("Verification failed"));
+    }
 }
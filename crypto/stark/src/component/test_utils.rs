@@ -0,0 +1,109 @@
+//! Helpers for testing [`Component`] implementations.
+use super::Component;
+use crate::{check_constraints, Constraints};
+use rand::random;
+use zkp_primefield::{FieldElement, One};
+
+/// Checks that the constraints reject modified traces.
+///
+/// Generates the trace for a valid `claim` and `witness` and then, for
+/// `samples` randomly chosen cells, adds one to the cell and checks that the
+/// constraints no longer hold. This surfaces cells that are not constrained.
+///
+/// # Panics
+///
+/// Panics if the unmodified trace does not satisfy the constraints, or lists
+/// the `(row, column)` of every cell whose modification was not detected.
+pub fn assert_constraints_catch_mutations<C: Component>(
+    component: &C,
+    claim: &C::Claim,
+    witness: &C::Witness,
+    samples: usize,
+) {
+    let constraints = Constraints::from_expressions(
+        (component.polynomial_size(), component.num_polynomials()),
+        component.channel_seed(claim),
+        component.constraints(claim),
+    )
+    .unwrap();
+    let mut trace = component.trace_table(witness);
+    assert_eq!(
+        check_constraints(&constraints, &trace),
+        Ok(()),
+        "Unmodified trace does not satisfy the constraints"
+    );
+    let (rows, columns) = trace.dimensions();
+    let mut undetected = Vec::new();
+    for _ in 0..samples {
+        let cell = (random::<usize>() % rows, random::<usize>() % columns);
+        trace[cell] += FieldElement::one();
+        if check_constraints(&constraints, &trace).is_ok() {
+            undetected.push(cell);
+        }
+        trace[cell] -= FieldElement::one();
+    }
+    undetected.sort_unstable();
+    undetected.dedup();
+    assert!(
+        undetected.is_empty(),
+        "Modifying these (row, column) cells was not detected: {:?}",
+        undetected
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{test::Test, PolynomialWriter},
+        *,
+    };
+    use crate::RationalExpression;
+
+    /// The first column is constant, but the second is only constrained on
+    /// the first row.
+    struct UnderConstrained;
+
+    impl Component for UnderConstrained {
+        type Claim = ();
+        type Witness = ();
+
+        fn num_polynomials(&self) -> usize {
+            2
+        }
+
+        fn polynomial_size(&self) -> usize {
+            8
+        }
+
+        fn claim(&self, _witness: &Self::Witness) -> Self::Claim {}
+
+        fn constraints(&self, _claim: &Self::Claim) -> Vec<RationalExpression> {
+            use RationalExpression::*;
+            vec![
+                (Trace(0, 0) - Trace(0, 1)) / (X.pow(8) - 1),
+                (Trace(1, 0) - 2) / (X - 1),
+            ]
+        }
+
+        fn trace<P: PolynomialWriter>(&self, trace: &mut P, _witness: &Self::Witness) {
+            for i in 0..8 {
+                trace.write(0, i, 1.into());
+                trace.write(1, i, 2.into());
+            }
+        }
+    }
+
+    #[test]
+    fn test_constrained() {
+        let component = Test::new(8, 3, &2.into());
+        let witness = (3.into(), 5.into());
+        let claim = component.claim(&witness);
+        assert_constraints_catch_mutations(&component, &claim, &witness, 50);
+    }
+
+    #[test]
+    #[should_panic(expected = "was not detected")]
+    fn test_under_constrained() {
+        assert_constraints_catch_mutations(&UnderConstrained, &(), &(), 100);
+    }
+}
@@ -1,5 +1,5 @@
-use super::{Component, Mapped, PolynomialWriter};
-use crate::RationalExpression;
+use super::{concat_seeds, Component, Mapped, PolynomialWriter};
+use crate::{RationalExpression, TraceError};
 
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[cfg_attr(feature = "std", derive(Debug))]
@@ -72,17 +72,29 @@ where
             .collect::<Vec<_>>()
     }
 
+    fn channel_seed(&self, claim: &Self::Claim) -> Vec<u8> {
+        concat_seeds(claim.iter().map(|claim| self.element.channel_seed(claim)))
+    }
+
     fn trace<P: PolynomialWriter>(&self, trace: &mut P, witness: &Self::Witness) {
+        self.try_trace(trace, witness).unwrap()
+    }
+
+    fn try_trace<P: PolynomialWriter>(
+        &self,
+        trace: &mut P,
+        witness: &Self::Witness,
+    ) -> Result<(), TraceError> {
         let num_polynomials = self.element.num_polynomials();
         let polynomial_size = self.element.polynomial_size();
-        witness.iter().enumerate().for_each(|(i, witness)| {
+        witness.iter().enumerate().try_for_each(|(i, witness)| {
             let mut transformed = Mapped::new(
                 trace,
                 num_polynomials,
                 polynomial_size,
                 |polynomial, location| (polynomial, location + i * polynomial_size),
             );
-            self.element.trace(&mut transformed, witness);
+            self.element.try_trace(&mut transformed, witness)
         })
     }
 }
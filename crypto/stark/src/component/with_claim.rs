@@ -0,0 +1,89 @@
+use super::Component;
+use crate::{Constraints, Provable, TraceError, TraceTable, Verifiable};
+
+/// A component bundled with its claim.
+///
+/// This implements [`Verifiable`] and [`Provable`] for components with any
+/// claim type, by capturing the claim. The transcript is seeded with the
+/// component's [`Component::channel_seed`], so proofs are interchangeable with
+/// [`Component::prove`] and [`Component::verify`].
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct WithClaim<C>
+where
+    C: Component,
+{
+    component: C,
+    claim:     C::Claim,
+}
+
+impl<C> WithClaim<C>
+where
+    C: Component,
+{
+    pub fn new(component: C, claim: C::Claim) -> Self {
+        Self { component, claim }
+    }
+
+    pub fn component(&self) -> &C {
+        &self.component
+    }
+
+    pub fn claim(&self) -> &C::Claim {
+        &self.claim
+    }
+}
+
+impl<C> Verifiable for WithClaim<C>
+where
+    C: Component,
+{
+    fn constraints(&self) -> Constraints {
+        Constraints::from_expressions(
+            (
+                self.component.polynomial_size(),
+                self.component.num_polynomials(),
+            ),
+            self.component.channel_seed(&self.claim),
+            self.component.constraints(&self.claim),
+        )
+        .unwrap()
+    }
+}
+
+impl<C> Provable<&C::Witness> for WithClaim<C>
+where
+    C: Component,
+{
+    fn trace(&self, witness: &C::Witness) -> TraceTable {
+        self.component.trace_table(witness)
+    }
+
+    fn try_trace(&self, witness: &C::Witness) -> Result<TraceTable, TraceError> {
+        self.component.try_trace_table(witness)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::test::Test, *};
+    use zkp_primefield::FieldElement;
+
+    #[test]
+    fn test_prove_verify() {
+        let component = Test::new(16, 2, &3.into());
+        let witness = (FieldElement::from(5), FieldElement::from(7));
+        let claim = component.claim(&witness);
+        let bundle = WithClaim::new(component.clone(), claim.clone());
+        assert_eq!(bundle.check(&witness), Ok(()));
+        let proof = bundle.prove(&witness).unwrap();
+        assert_eq!(bundle.verify(&proof), Ok(()));
+        // Proofs are interchangeable with the component's own entry points.
+        assert_eq!(component.verify(&claim, &proof), Ok(()));
+        let proof = component.prove(&witness).unwrap();
+        assert_eq!(bundle.verify(&proof), Ok(()));
+
+        let wrong = WithClaim::new(component, claim + FieldElement::from(1));
+        assert!(wrong.verify(&proof).is_err());
+    }
+}
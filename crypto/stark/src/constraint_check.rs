@@ -1,5 +1,8 @@
-use crate::{constraints::Constraints, trace_table::TraceTable};
-use std::convert::TryInto;
+use crate::{
+    constraints::Constraints, rational_expression::RationalExpression, trace_table::TraceTable,
+};
+use rayon::prelude::*;
+use std::{convert::TryInto, iter::successors};
 use zkp_primefield::{FieldElement, One, Pow, Root};
 
 #[allow(clippy::doc_markdown)]
@@ -30,40 +33,172 @@ use zkp_primefield::{FieldElement, One, Pow, Root};
 /// of this function also depends heavily on the size of the system so for the
 /// best experience using it to check constraints while developing it is best to
 /// limit the trace table to the smallest meeting your needs.
-
+///
+/// ## Trace offsets
+///
+/// The trace is cyclic, like the evaluation domain it is interpolated on.
+/// `Trace(column, offset)` on row `row` reads row `(row + offset) % rows`, so
+/// constraints on the last rows see the first rows again. This matches the
+/// prover, where an offset is a multiplication of `X` by a power of the trace
+/// generator. Offsets of a trace length or more wrap around as well, small
+/// traces legitimately use them.
+///
+/// ## Panics
+///
+/// Panics if a constraint references a column outside the table, see
+/// [`Constraints::check_trace_arguments`] for a check that does not.
 pub fn check_constraints(
     constraints: &Constraints,
     table: &TraceTable,
 ) -> Result<(), (usize, usize)> {
+    match constraint_failures(constraints, table).next() {
+        Some(failure) => Err(failure),
+        None => Ok(()),
+    }
+}
+
+/// All `(row, constraint)` pairs that fail [`check_constraints`], by row.
+pub(crate) fn constraint_failures<'a>(
+    constraints: &'a Constraints,
+    table: &'a TraceTable,
+) -> impl Iterator<Item = (usize, usize)> + 'a {
     let trace_generator = FieldElement::root(table.num_rows()).unwrap();
-    let mut current_root = FieldElement::one();
-    let len = table.num_rows();
+    let roots = successors(Some(FieldElement::one()), move |root| {
+        Some(root * &trace_generator)
+    });
+    roots
+        .take(table.num_rows())
+        .enumerate()
+        .flat_map(move |(row, current_root)| {
+            row_failures(constraints, table, row, current_root).map(move |which| (row, which))
+        })
+}
 
-    for row in 0..len {
-        // Note - Still in col row form
-        let trace = |i: usize, j: isize| {
-            if j.is_positive() {
-                let j: usize = j.try_into().unwrap();
-                table[((j + row) % len, i)].clone()
-            } else {
-                let j: usize = j.abs().try_into().unwrap();
-                if row < j {
-                    table[(len + row - j, i)].clone()
-                } else {
-                    table[(row - j, i)].clone()
-                }
+/// Like [`check_constraints`], but checks rows in parallel.
+///
+/// The rows are split in contiguous chunks, each checked serially. The result
+/// is the same as for [`check_constraints`]: the lowest failing row, with the
+/// first constraint that fails on it.
+pub fn par_check_constraints(
+    constraints: &Constraints,
+    table: &TraceTable,
+) -> Result<(), (usize, usize)> {
+    let len = table.num_rows();
+    let trace_generator = FieldElement::root(len).unwrap();
+    // Several chunks per thread to balance the load. Both the trace length and
+    // the chunk size are powers of two, so the chunks cover the trace exactly.
+    let chunk_size = (len / (4 * rayon::current_num_threads())).next_power_of_two();
+    let num_chunks = len / chunk_size;
+    let failure = (0..num_chunks).into_par_iter().find_map_first(|chunk| {
+        let start = chunk * chunk_size;
+        let mut current_root = trace_generator.pow(start);
+        for row in start..start + chunk_size {
+            if let Some(which) = row_failures(constraints, table, row, current_root.clone()).next()
+            {
+                return Some((row, which));
             }
-        };
-        for (which, expression) in constraints.expressions().iter().enumerate() {
-            if !expression.check(&current_root, &trace).1 {
-                return Err((row, which));
+            current_root *= &trace_generator;
+        }
+        None
+    });
+    match failure {
+        Some(failure) => Err(failure),
+        None => Ok(()),
+    }
+}
+
+/// Indices of the constraints that fail on `row`, where `X` is `current_root`.
+fn row_failures<'a>(
+    constraints: &'a Constraints,
+    table: &'a TraceTable,
+    row: usize,
+    current_root: FieldElement,
+) -> impl Iterator<Item = usize> + 'a {
+    let len = table.num_rows();
+    // Note - Still in col row form
+    let trace = move |i: usize, j: isize| table[(cyclic_row(row, j, len), i)].clone();
+    constraints
+        .expressions()
+        .iter()
+        .enumerate()
+        .filter(move |(_, expression)| !expression.check(&current_root, &trace).1)
+        .map(|(which, _)| which)
+}
+
+impl Constraints {
+    /// Check the constraints on a trace table, see [`check_constraints`].
+    ///
+    /// On failure, returns the row and the index of the first constraint that
+    /// does not hold.
+    ///
+    /// ```
+    /// use zkp_stark::{
+    ///     primefield::{FieldElement, Pow, Root},
+    ///     Constraints,
+    ///     RationalExpression::*,
+    ///     TraceTable,
+    /// };
+    ///
+    /// // Each value is the square of the previous one, starting at 2.
+    /// let last_row = Constant(FieldElement::root(4).unwrap().pow(3_usize));
+    /// let constraints = Constraints::from_expressions((4, 1), vec![], vec![
+    ///     (Trace(0, 1) - Trace(0, 0).pow(2)) * (X - last_row) / (X.pow(4) - 1),
+    ///     (Trace(0, 0) - 2) / (X - 1),
+    /// ])
+    /// .unwrap();
+    /// let mut trace = TraceTable::new(4, 1);
+    /// for (row, value) in [2_usize, 4, 16, 256].iter().enumerate() {
+    ///     trace[(row, 0)] = (*value).into();
+    /// }
+    /// assert_eq!(constraints.check(&trace), Ok(()));
+    ///
+    /// trace[(2, 0)] = 15.into();
+    /// assert_eq!(constraints.check(&trace), Err((1, 0)));
+    /// ```
+    pub fn check(&self, trace: &TraceTable) -> Result<(), (usize, usize)> {
+        check_constraints(self, trace)
+    }
+}
+
+impl Constraints {
+    /// Check that every `Trace(column, offset)` in the constraints references
+    /// a column of the trace table. Offsets are always valid, they wrap
+    /// around the trace, see [`check_constraints`].
+    ///
+    /// On failure, returns the index of the first offending constraint with
+    /// its column and offset.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a constraint contains a `ClaimPolynomial`.
+    pub fn check_trace_arguments(&self) -> Result<(), (usize, usize, isize)> {
+        check_trace_arguments(self.expressions(), self.trace_ncolumns())
+    }
+}
+
+/// See [`Constraints::check_trace_arguments`]. Takes the expressions, so it
+/// can run before [`Constraints::from_expressions`] rejects them.
+pub(crate) fn check_trace_arguments(
+    expressions: &[RationalExpression],
+    num_columns: usize,
+) -> Result<(), (usize, usize, isize)> {
+    for (which, expression) in expressions.iter().enumerate() {
+        for (column, offset) in expression.trace_arguments() {
+            if column >= num_columns {
+                return Err((which, column, offset));
             }
         }
-        current_root *= &trace_generator;
     }
     Ok(())
 }
 
+/// The row `offset` rows from `row` in a cyclic trace of `len` rows.
+pub(crate) fn cyclic_row(row: usize, offset: isize, len: usize) -> usize {
+    let len: isize = len.try_into().unwrap();
+    let row: isize = row.try_into().unwrap();
+    (row + offset).rem_euclid(len).try_into().unwrap()
+}
+
 pub(crate) fn check_specific_constraint(
     constraints: &Constraints,
     table: &TraceTable,
@@ -79,19 +214,7 @@ pub(crate) fn check_specific_constraint(
     }
     let len = table.num_rows();
 
-    let trace = |i: usize, j: isize| {
-        if j.is_positive() {
-            let j: usize = j.try_into().unwrap();
-            table[((j + row) % len, i)].clone()
-        } else {
-            let j: usize = j.abs().try_into().unwrap();
-            if row < j {
-                table[(len + row - j, i)].clone()
-            } else {
-                table[(row - j, i)].clone()
-            }
-        }
-    };
+    let trace = |i: usize, j: isize| table[(cyclic_row(row, j, len), i)].clone();
 
     constraints.expressions()[which_constraint]
         .check(&x, &trace)
@@ -102,6 +225,7 @@ pub(crate) fn check_specific_constraint(
 mod tests {
     use super::*;
     use crate::{traits::tests::Recurrance, Provable, Verifiable};
+    use proptest::prelude::*;
     use zkp_macros_decl::field_element;
     use zkp_primefield::Zero;
     use zkp_u256::U256;
@@ -150,4 +274,75 @@ mod tests {
         trace[(0, 0)] = FieldElement::zero();
         assert_eq!(check_specific_constraint(&constraints, &trace, 0, 2), false);
     }
+
+    #[test]
+    fn parallel_matches_serial() {
+        proptest!(|(
+            index in 1_usize..300,
+            initial_value: FieldElement,
+            corruptions: Vec<(usize, usize, FieldElement)>,
+        )| {
+            let recurrance = Recurrance {
+                index,
+                initial_value,
+                exponent: 1,
+            };
+            let constraints = recurrance.claim().constraints();
+            let mut trace = recurrance.claim().trace(&recurrance.witness());
+            prop_assert_eq!(par_check_constraints(&constraints, &trace), Ok(()));
+            let rows = trace.num_rows();
+            for (row, column, value) in corruptions.into_iter().take(3) {
+                trace[(row % rows, column % 2)] = value;
+            }
+            prop_assert_eq!(
+                par_check_constraints(&constraints, &trace),
+                check_constraints(&constraints, &trace)
+            );
+        });
+    }
+
+    #[test]
+    fn parallel_lowest_row() {
+        let recurrance = Recurrance {
+            index:         1000,
+            initial_value: field_element!("cafebabe"),
+            exponent:      1,
+        };
+        let constraints = recurrance.claim().constraints();
+        let mut trace = recurrance.claim().trace(&recurrance.witness());
+        trace[(900, 1)] = FieldElement::zero();
+        trace[(20, 0)] = FieldElement::zero();
+        trace[(500, 0)] = FieldElement::zero();
+        assert_eq!(check_constraints(&constraints, &trace), Err((19, 0)));
+        assert_eq!(par_check_constraints(&constraints, &trace), Err((19, 0)));
+    }
+
+    #[test]
+    fn trace_offsets_wrap() {
+        use crate::RationalExpression::*;
+        let mut trace = TraceTable::new(4, 1);
+        for row in 0..4 {
+            trace[(row, 0)] = FieldElement::from(row);
+        }
+        let last_row = Constant(FieldElement::root(4).unwrap().pow(3_usize));
+        let every_row = || (X.pow(4) - 1).inv();
+        let constraints = Constraints::from_expressions((4, 1), vec![], vec![
+            (Trace(0, 1) - Trace(0, 0) - 1) * (X - last_row) * every_row(),
+            // The last row is followed by the first
+            (Trace(0, 1) - Trace(0, -3)) * every_row(),
+            // Offsets of a trace length or more wrap around the trace
+            (Trace(0, 5) - Trace(0, 1)) * every_row(),
+            (Trace(0, -9) - Trace(0, -1)) * every_row(),
+        ])
+        .unwrap();
+        assert_eq!(constraints.check_trace_arguments(), Ok(()));
+        assert_eq!(check_constraints(&constraints, &trace), Ok(()));
+        trace[(0, 0)] = FieldElement::from(7);
+        assert_eq!(check_constraints(&constraints, &trace), Err((0, 0)));
+
+        assert_eq!(
+            check_trace_arguments(&[Trace(0, -4), Trace(1, 2)], 1),
+            Err((1, 1, 2))
+        );
+    }
 }
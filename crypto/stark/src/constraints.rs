@@ -1,11 +1,30 @@
-use crate::{polynomial::DensePolynomial, rational_expression::RationalExpression};
+use crate::{
+    channel::{ChannelBuilder, Endianness, HashFunction, TranscriptHash},
+    polynomial::DensePolynomial,
+    proof_of_work,
+    rational_expression::RationalExpression,
+};
 use itertools::Itertools;
+use log::warn;
 use std::{collections::BTreeSet, fmt, prelude::v1::*};
 use zkp_primefield::{FieldElement, Root};
 
 #[derive(Clone, Debug)]
 pub enum Error {
     InvalidTraceLength,
+    IndeterminateDegree,
+    /// An expression references a trace column that is not less than the
+    /// number of columns.
+    ColumnOutOfRange {
+        column: usize,
+        width:  usize,
+    },
+    /// An expression references a row offset whose absolute value is not less
+    /// than the trace length.
+    OffsetOutOfRange {
+        offset: isize,
+        length: usize,
+    },
 }
 
 impl fmt::Display for Error {
@@ -13,6 +32,26 @@ impl fmt::Display for Error {
         use Error::*;
         match *self {
             InvalidTraceLength => write!(f, "Invalid trace length (must be power of two)"),
+            IndeterminateDegree => {
+                write!(
+                    f,
+                    "Constraint degree can not be determined (trace in denominator)"
+                )
+            }
+            ColumnOutOfRange { column, width } => {
+                write!(
+                    f,
+                    "Trace column {} is out of range for a trace with {} columns",
+                    column, width
+                )
+            }
+            OffsetOutOfRange { offset, length } => {
+                write!(
+                    f,
+                    "Trace offset {} is out of range for a trace with {} rows",
+                    offset, length
+                )
+            }
         }
     }
 }
@@ -45,9 +84,23 @@ pub struct Constraints {
     /// Proof of work difficulty
     ///
     /// The difficulty of the proof of work step in number of leading zero bits
-    /// required.
+    /// required. At most 255. Zero disables grinding: the proof still contains
+    /// a nonce, but it is not checked.
     pub pow_bits: usize,
 
+    /// Proof of work domain separation prefix
+    ///
+    /// Hashed into every proof of work challenge. Prover and verifier need to
+    /// use the same prefix. Defaults to the prefix of the Solidity verifier.
+    pub grinding_prefix: Vec<u8>,
+
+    /// Proof of work search limit
+    ///
+    /// If set, the prover only tries nonces below this limit and fails with
+    /// `ProverError::PowNotFound` if none of them is valid. Defaults to `None`,
+    /// which searches until a valid nonce is found.
+    pub pow_max_iterations: Option<u64>,
+
     /// Number of queries made to the oracles
     pub num_queries: usize,
 
@@ -61,6 +114,17 @@ pub struct Constraints {
     /// is written explicitly in coefficient form.
     pub fri_layout: Vec<usize>,
 
+    /// Hash function of the public coin
+    ///
+    /// Prover and verifier need to use the same hash. Defaults to Keccak256.
+    pub transcript_hash: TranscriptHash,
+
+    /// Byte order of the words in the proof
+    ///
+    /// Prover and verifier need to use the same byte order. Defaults to
+    /// big-endian, the only byte order supported by the Solidity verifier.
+    pub endianness: Endianness,
+
     /// To make autogeneration easier we have included a 'ClaimPolynomial'
     /// these claim polynomials need to be taken out of the expressions before
     /// they can be evaluated
@@ -95,6 +159,10 @@ impl Constraints {
     /// Requires all instances of `RationalExpression::ClaimPolynomial` in the
     /// expressions to have been replaced by
     /// `RationalExpression::DensePolynomial`.
+    ///
+    /// Returns [`Error::ColumnOutOfRange`] if an expression references a trace
+    /// column outside the table and [`Error::OffsetOutOfRange`] if it
+    /// references a row offset of at least the trace length.
     // False positive
     // TODO: Remove once [1] clears
     // [1]: <https://github.com/rust-lang/rust-clippy/issues/5351>
@@ -103,23 +171,72 @@ impl Constraints {
         (trace_nrows, trace_ncolumns): (usize, usize),
         channel_seed: Vec<u8>,
         expressions: Vec<RationalExpression>,
+    ) -> Result<Self, Error> {
+        Self::from_expressions_with_blowup(
+            (trace_nrows, trace_ncolumns),
+            channel_seed,
+            expressions,
+            16,
+        )
+    }
+
+    /// Like [`from_expressions`](Self::from_expressions), but picks the
+    /// smallest power of two blowup that covers the highest constraint degree.
+    ///
+    /// The blowup is at least two, so the evaluation domain is always strictly
+    /// larger than the trace. Returns [`Error::IndeterminateDegree`] if a
+    /// constraint has the trace in a denominator.
+    pub fn with_auto_blowup(
+        (trace_nrows, trace_ncolumns): (usize, usize),
+        channel_seed: Vec<u8>,
+        expressions: Vec<RationalExpression>,
+    ) -> Result<Self, Error> {
+        let mut degree = 0;
+        for expression in &expressions {
+            let (numerator_degree, denominator_degree) = expression.trace_degree();
+            if denominator_degree > 0 {
+                return Err(Error::IndeterminateDegree);
+            }
+            degree = std::cmp::max(degree, numerator_degree);
+        }
+        let blowup = std::cmp::max(degree.next_power_of_two(), 2);
+        Self::from_expressions_with_blowup(
+            (trace_nrows, trace_ncolumns),
+            channel_seed,
+            expressions,
+            blowup,
+        )
+    }
+
+    fn from_expressions_with_blowup(
+        (trace_nrows, trace_ncolumns): (usize, usize),
+        channel_seed: Vec<u8>,
+        expressions: Vec<RationalExpression>,
+        blowup: usize,
     ) -> Result<Self, Error> {
         let _ = FieldElement::root(trace_nrows).ok_or(Error::InvalidTraceLength)?;
+        check_trace(&expressions, (trace_nrows, trace_ncolumns))?;
         // TODO: Hash expressions into channel seed
         // TODO - Examine if we want to up these security params further.
         // 22.5*4  + 0 queries = 90
         // TODO: Sensible default for pow_bits. For small proofs it should be small.
-        Ok(Self {
+        let constraints = Self {
             channel_seed,
             trace_nrows,
             trace_ncolumns,
             expressions,
-            blowup: 16,
+            blowup,
             pow_bits: 0,
+            grinding_prefix: proof_of_work::DEFAULT_PREFIX.to_vec(),
+            pow_max_iterations: None,
             num_queries: 45,
             fri_layout: Self::default_fri_layout(trace_nrows),
+            transcript_hash: TranscriptHash::default(),
+            endianness: Endianness::default(),
             claim_polynomials: vec![],
-        })
+        };
+        constraints.check_blowup();
+        Ok(constraints)
     }
 
     /// Requires all instances of `RationalExpression::ClaimPolynomial` in the
@@ -139,9 +256,10 @@ impl Constraints {
         op_fri_layout: Option<Vec<usize>>,
     ) -> Result<Self, Error> {
         let _ = FieldElement::root(trace_nrows).ok_or(Error::InvalidTraceLength)?;
+        check_trace(&expressions, (trace_nrows, trace_ncolumns))?;
         // TODO: Hash expressions into channel seed
         // 15*4 + 30 queries = 90
-        Ok(Self {
+        let constraints = Self {
             channel_seed,
             trace_nrows,
             trace_ncolumns,
@@ -160,6 +278,8 @@ impl Constraints {
                     }
                 }
             },
+            grinding_prefix: proof_of_work::DEFAULT_PREFIX.to_vec(),
+            pow_max_iterations: None,
             num_queries: match op_num_queries {
                 Some(x) => x,
                 None => 13,
@@ -168,14 +288,48 @@ impl Constraints {
                 Some(x) => x,
                 None => Self::default_fri_layout(trace_nrows),
             },
+            transcript_hash: TranscriptHash::default(),
+            endianness: Endianness::default(),
             claim_polynomials: vec![],
-        })
+        };
+        constraints.check_blowup();
+        Ok(constraints)
+    }
+
+    /// Warns if the constraint degree, in multiples of the trace degree,
+    /// exceeds the blowup factor. The constraint polynomial would not fit
+    /// the low-degree-extension domain.
+    fn check_blowup(&self) {
+        let degree = self
+            .expressions
+            .iter()
+            .map(|c| {
+                let (numerator_degree, denominator_degree) = c.trace_degree();
+                numerator_degree.saturating_sub(denominator_degree)
+            })
+            .max()
+            .unwrap_or(0);
+        if degree > self.blowup {
+            warn!(
+                "Constraint degree {} exceeds the blowup factor {}",
+                degree, self.blowup
+            );
+        }
     }
 
     pub fn channel_seed(&self) -> &[u8] {
         &self.channel_seed
     }
 
+    /// The channel configuration shared by the prover and verifier.
+    pub(crate) fn channel<H: HashFunction>(&self) -> ChannelBuilder<H> {
+        ChannelBuilder::default()
+            .hash::<H>()
+            .grinding_prefix(&self.grinding_prefix)
+            .endianness(self.endianness)
+            .seed(&self.channel_seed)
+    }
+
     pub fn trace_nrows(&self) -> usize {
         self.trace_nrows
     }
@@ -184,6 +338,12 @@ impl Constraints {
         self.trace_ncolumns
     }
 
+    /// The trace dimensions as `(rows, columns)`.
+    pub fn trace_arity(&self) -> (usize, usize) {
+        (self.trace_nrows, self.trace_ncolumns)
+    }
+
+    /// The number of constraint expressions.
     pub fn len(&self) -> usize {
         self.expressions.len()
     }
@@ -196,7 +356,9 @@ impl Constraints {
         &self.expressions
     }
 
-    pub fn degree(&self) -> usize {
+    /// The largest degree of a constraint, in multiples of the trace degree,
+    /// or `None` if there are no constraints.
+    pub fn max_degree(&self) -> Option<usize> {
         self.expressions
             .iter()
             .map(|c| {
@@ -204,7 +366,16 @@ impl Constraints {
                 numerator_degree - denominator_degree
             })
             .max()
-            .expect("no constraints")
+    }
+
+    /// Like [`Constraints::max_degree`], for constraints known to be
+    /// non-empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no constraints.
+    pub fn degree(&self) -> usize {
+        self.max_degree().expect("no constraints")
     }
 
     // TODO: Better explanation with literature references.
@@ -245,6 +416,76 @@ impl Constraints {
         32 * total_decommitment
     }
 
+    /// Estimate of the proof size in bytes.
+    ///
+    /// Unlike [`Constraints::max_proof_size`] this is not a bound. It uses the
+    /// expected overlap between uniformly random queries, both in the Merkle
+    /// proofs and in the FRI cosets. In practice the estimate is within 10% of
+    /// the actual size. The channel seed is not part of the proof.
+    ///
+    /// The proof parameters (`blowup`, `num_queries` and `fri_layout`) are
+    /// public fields of `Constraints` rather than a separate parameter type,
+    /// so the estimate uses the ones set on `self`, the same as
+    /// [`Constraints::max_proof_size`] and `prove`. To compare parameter
+    /// choices, adjust the fields on a clone. Returns zero if there
+    /// are no constraints, as there is nothing to prove.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn estimate_proof_size(&self) -> usize {
+        // Expectations are computed in fixed point with 32 fractional bits.
+        const ONE: u128 = 1 << 32;
+        const HASH: u128 = 32;
+        const ELEMENT: u128 = 32;
+        const NONCE: u128 = 8;
+        let constraint_polynomials = match self.max_degree() {
+            Some(degree) => degree.next_power_of_two() as u128,
+            None => return 0,
+        };
+        let queries = self.num_queries as u128;
+        // Expected number of distinct values among the queries, reduced to
+        // `bits` bits.
+        let distinct = |bits: usize| {
+            let size = 1_u128 << bits;
+            let mut missed = ONE;
+            for _ in 0..self.num_queries {
+                missed = missed * (size - 1) / size;
+            }
+            size * (ONE - missed)
+        };
+        // Expected number of hashes in a Merkle proof for all queries in a tree
+        // with `depth` levels: all siblings that are not themselves known.
+        let proof_hashes = |depth: usize| -> u128 {
+            (1..=depth)
+                .map(|level| (2 * distinct(level - 1)).saturating_sub(distinct(level)))
+                .sum()
+        };
+        let trace_arguments = self.trace_arguments().len() as u128;
+
+        // Trace and constraint commitments, out of domain values and the
+        // proof of work nonce.
+        let mut size =
+            ONE * (2 * HASH + (trace_arguments + constraint_polynomials) * ELEMENT + NONCE);
+
+        // Trace and constraint decommitments.
+        let mut depth = (self.trace_nrows * self.blowup).trailing_zeros() as usize;
+        let columns = self.trace_ncolumns as u128 + constraint_polynomials;
+        size += ONE * queries * columns * ELEMENT + 2 * proof_hashes(depth) * HASH;
+
+        // FRI layer commitments and the rest of each queried coset.
+        for &reductions in &self.fri_layout {
+            let known = distinct(depth);
+            depth -= reductions;
+            let cosets = distinct(depth);
+            let coset_size = 1_u128 << reductions;
+            size += ONE * HASH
+                + (cosets * coset_size).saturating_sub(known) * ELEMENT
+                + proof_hashes(depth) * HASH;
+        }
+
+        // Coefficients of the last FRI layer.
+        size += ONE * ((1 << depth) / self.blowup) as u128 * ELEMENT;
+        (size / ONE) as usize
+    }
+
     pub fn combine(&self, constraint_coefficients: &[FieldElement]) -> RationalExpression {
         use RationalExpression::*;
         assert_eq!(2 * self.len(), constraint_coefficients.len());
@@ -265,6 +506,8 @@ impl Constraints {
             .sum()
     }
 
+    /// The sorted and deduplicated `(column, offset)` pairs referenced by any
+    /// of the constraint expressions.
     pub fn trace_arguments(&self) -> Vec<(usize, isize)> {
         self.expressions
             .iter()
@@ -297,8 +540,40 @@ impl Constraints {
     }
 }
 
+/// Fails on the first `Trace` column that is not less than `width` or offset
+/// whose absolute value is not less than `length`.
+fn check_trace(
+    expressions: &[RationalExpression],
+    (length, width): (usize, usize),
+) -> Result<(), Error> {
+    // Unlike `RationalExpression::trace_arguments`, this also looks inside
+    // `ClaimPolynomial`s, which need not be substituted yet.
+    fn find(expression: &RationalExpression, (length, width): (usize, usize)) -> Option<Error> {
+        use RationalExpression::*;
+        match expression {
+            &Trace(column, _) if column >= width => Some(Error::ColumnOutOfRange { column, width }),
+            &Trace(_, offset) if offset.unsigned_abs() >= length => {
+                Some(Error::OffsetOutOfRange { offset, length })
+            }
+            X | Constant(_) | Trace(..) => None,
+            Polynomial(_, a) | ClaimPolynomial(_, _, a, _) | Exp(a, _) | Neg(a) | Inv(a) => {
+                find(a, (length, width))
+            }
+            Add(a, b) | Mul(a, b) => find(a, (length, width)).or_else(|| find(b, (length, width))),
+        }
+    }
+    match expressions
+        .iter()
+        .find_map(|expression| find(expression, (length, width)))
+    {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
     use crate::{prove, traits::tests::Recurrance, Provable, Verifiable};
     use zkp_macros_decl::field_element;
     use zkp_primefield::FieldElement;
@@ -322,5 +597,136 @@ mod tests {
 
         let actual = prove(&constraints, &public.trace(&private)).unwrap();
         assert!(actual.as_bytes().len() <= constraints.max_proof_size());
+
+        // The estimate is documented to be within 10%.
+        let actual = actual.as_bytes().len();
+        let estimate = constraints.estimate_proof_size();
+        assert!(10 * estimate >= 9 * actual, "{} < {}", estimate, actual);
+        assert!(10 * estimate <= 11 * actual, "{} > {}", estimate, actual);
+    }
+
+    #[test]
+    fn estimate_proof_size_empty() {
+        let constraints = Constraints::from_expressions((1024, 2), vec![], vec![]).unwrap();
+        assert_eq!(constraints.estimate_proof_size(), 0);
+    }
+
+    #[test]
+    fn auto_blowup() {
+        use RationalExpression::*;
+        let blowup = |expressions: Vec<RationalExpression>| {
+            Constraints::with_auto_blowup((1024, 2), vec![], expressions)
+                .unwrap()
+                .blowup
+        };
+        assert_eq!(blowup(vec![]), 2);
+        assert_eq!(blowup(vec![(Trace(0, 1) - Trace(1, 0)) / (X - 1)]), 2);
+        assert_eq!(blowup(vec![Trace(0, 0) * Trace(1, 0)]), 2);
+        assert_eq!(
+            blowup(vec![Trace(0, 0), Trace(0, 1) - Trace(0, 0).pow(3)]),
+            4
+        );
+        assert_eq!(blowup(vec![Trace(0, 0).pow(5) * Trace(1, 1)]), 8);
+        assert_eq!(blowup(vec![Trace(0, 0).pow(16)]), 16);
+        assert_eq!(blowup(vec![Trace(0, 0).pow(17)]), 32);
+    }
+
+    #[test]
+    fn column_out_of_range() {
+        use RationalExpression::*;
+        let result = Constraints::from_expressions((8, 3), vec![], vec![
+            Trace(0, 0) - Trace(2, 1),
+            (Trace(1, 0) - Trace(5, 0)) / (X - 1),
+        ]);
+        assert!(matches!(
+            result,
+            Err(Error::ColumnOutOfRange {
+                column: 5,
+                width:  3,
+            })
+        ));
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Trace column 5 is out of range for a trace with 3 columns"
+        );
+        let claim = ClaimPolynomial(0, 1, Box::new(Trace(3, 0)), None);
+        assert!(matches!(
+            Constraints::with_auto_blowup((8, 3), vec![], vec![claim]),
+            Err(Error::ColumnOutOfRange {
+                column: 3,
+                width:  3,
+            })
+        ));
+    }
+
+    #[test]
+    fn offset_out_of_range() {
+        use RationalExpression::*;
+        assert!(
+            Constraints::from_expressions((8, 3), vec![], vec![Trace(0, 7) - Trace(2, -7)]).is_ok()
+        );
+        let result = Constraints::from_expressions((8, 3), vec![], vec![
+            Trace(0, 0) - Trace(2, 1),
+            (Trace(1, 0) - Trace(1, -8)) / (X - 1),
+        ]);
+        assert!(matches!(
+            result,
+            Err(Error::OffsetOutOfRange {
+                offset: -8,
+                length: 8,
+            })
+        ));
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Trace offset -8 is out of range for a trace with 8 rows"
+        );
+        assert!(matches!(
+            Constraints::from_expressions((8, 3), vec![], vec![Trace(0, 8)]),
+            Err(Error::OffsetOutOfRange {
+                offset: 8,
+                length: 8,
+            })
+        ));
+    }
+
+    #[test]
+    fn auto_blowup_indeterminate() {
+        use RationalExpression::*;
+        assert!(matches!(
+            Constraints::with_auto_blowup((1024, 2), vec![], vec![Trace(0, 0) / Trace(1, 0)]),
+            Err(Error::IndeterminateDegree)
+        ));
+    }
+
+    #[test]
+    fn trace_arguments() {
+        use RationalExpression::*;
+        let constraints = Constraints::from_expressions((1024, 3), vec![], vec![
+            Trace(0, 1) - Trace(2, 0),
+            Trace(2, 1) * Trace(0, 1),
+        ])
+        .unwrap();
+        assert_eq!(constraints.trace_arguments(), vec![(0, 1), (2, 0), (2, 1)]);
+    }
+
+    #[test]
+    fn introspection() {
+        use RationalExpression::*;
+        let constraints = Constraints::from_expressions((1024, 3), vec![], vec![
+            Trace(0, 1) - Trace(2, 0),
+            Trace(2, 1) * Trace(0, 1).pow(3) / (X.pow(1024) - 1),
+            (Trace(1, 0) - 5) / (X - 1),
+        ])
+        .unwrap();
+        assert_eq!(constraints.trace_arity(), (1024, 3));
+        assert_eq!(constraints.len(), 3);
+        assert!(!constraints.is_empty());
+        assert_eq!(constraints.max_degree(), Some(4));
+        assert_eq!(constraints.degree(), 4);
+
+        let empty = Constraints::from_expressions((1024, 3), vec![], vec![]).unwrap();
+        assert_eq!(empty.len(), 0);
+        assert!(empty.is_empty());
+        assert_eq!(empty.max_degree(), None);
     }
 }
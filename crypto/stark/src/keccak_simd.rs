@@ -0,0 +1,166 @@
+//! Keccak-f[1600] on several independent states at once.
+//!
+//! Every state word is stored as an array with one entry per state, so each
+//! step of the permutation is the same operation on all entries. The compiler
+//! can map these onto vector registers where available.
+use crate::channel::COUNTER_BATCH;
+use std::convert::TryInto;
+
+type Lanes = [u64; COUNTER_BATCH];
+
+const ROUND_CONSTANTS: [u64; 24] = [
+    0x0000_0000_0000_0001,
+    0x0000_0000_0000_8082,
+    0x8000_0000_0000_808a,
+    0x8000_0000_8000_8000,
+    0x0000_0000_0000_808b,
+    0x0000_0000_8000_0001,
+    0x8000_0000_8000_8081,
+    0x8000_0000_0000_8009,
+    0x0000_0000_0000_008a,
+    0x0000_0000_0000_0088,
+    0x0000_0000_8000_8009,
+    0x0000_0000_8000_000a,
+    0x0000_0000_8000_808b,
+    0x8000_0000_0000_008b,
+    0x8000_0000_0000_8089,
+    0x8000_0000_0000_8003,
+    0x8000_0000_0000_8002,
+    0x8000_0000_0000_0080,
+    0x0000_0000_0000_800a,
+    0x8000_0000_8000_000a,
+    0x8000_0000_8000_8081,
+    0x8000_0000_0000_8080,
+    0x0000_0000_8000_0001,
+    0x8000_0000_8000_8008,
+];
+
+const RHO: [u32; 24] = [
+    1, 3, 6, 10, 15, 21, 28, 36, 45, 55, 2, 14, 27, 41, 56, 8, 25, 43, 62, 18, 39, 61, 20, 44,
+];
+
+const PI: [usize; 24] = [
+    10, 7, 11, 17, 18, 3, 5, 16, 8, 21, 24, 4, 15, 23, 19, 13, 12, 2, 20, 14, 22, 9, 6, 1,
+];
+
+fn map(a: &Lanes, f: impl Fn(u64) -> u64) -> Lanes {
+    let mut result = [0; COUNTER_BATCH];
+    for (r, a) in result.iter_mut().zip(a.iter()) {
+        *r = f(*a);
+    }
+    result
+}
+
+fn zip(a: &Lanes, b: &Lanes, f: impl Fn(u64, u64) -> u64) -> Lanes {
+    let mut result = [0; COUNTER_BATCH];
+    for ((r, a), b) in result.iter_mut().zip(a.iter()).zip(b.iter()) {
+        *r = f(*a, *b);
+    }
+    result
+}
+
+/// Apply Keccak-f[1600] to every state in the batch.
+pub(crate) fn keccakf(state: &mut [Lanes; 25]) {
+    for round_constant in &ROUND_CONSTANTS {
+        // Theta
+        let mut parity = [[0; COUNTER_BATCH]; 5];
+        for (x, p) in parity.iter_mut().enumerate() {
+            for y in 0..5 {
+                *p = zip(p, &state[x + 5 * y], |a, b| a ^ b);
+            }
+        }
+        for x in 0..5 {
+            let rotated = map(&parity[(x + 1) % 5], |a| a.rotate_left(1));
+            let d = zip(&parity[(x + 4) % 5], &rotated, |a, b| a ^ b);
+            for y in 0..5 {
+                state[x + 5 * y] = zip(&state[x + 5 * y], &d, |a, b| a ^ b);
+            }
+        }
+
+        // Rho and pi
+        let mut last = state[1];
+        for (&rho, &pi) in RHO.iter().zip(PI.iter()) {
+            let next = state[pi];
+            state[pi] = map(&last, |a| a.rotate_left(rho));
+            last = next;
+        }
+
+        // Chi
+        for y in 0..5 {
+            let row: [Lanes; 5] = state[5 * y..5 * y + 5].try_into().unwrap();
+            for x in 0..5 {
+                let mixed = zip(&row[(x + 1) % 5], &row[(x + 2) % 5], |a, b| !a & b);
+                state[x + 5 * y] = zip(&row[x], &mixed, |a, b| a ^ b);
+            }
+        }
+
+        // Iota
+        state[0] = map(&state[0], |a| a ^ round_constant);
+    }
+}
+
+/// Keccak256 of `digest || [0; 24] || counter` for consecutive counters.
+pub(crate) fn hash_counters(digest: &[u8; 32], first: u64) -> [[u8; 32]; COUNTER_BATCH] {
+    // The 64 byte input fits in a single block, so we fill in the padded block
+    // and apply the permutation directly.
+    let mut state = [[0_u64; COUNTER_BATCH]; 25];
+    for (lane, bytes) in state.iter_mut().zip(digest.chunks_exact(8)) {
+        *lane = [u64::from_le_bytes(bytes.try_into().unwrap()); COUNTER_BATCH];
+    }
+    for (i, lane) in state[7].iter_mut().enumerate() {
+        *lane = u64::from_le_bytes((first + i as u64).to_be_bytes());
+    }
+    // Keccak padding, the last byte of the block is at the end of lane 16.
+    state[8] = [0x01; COUNTER_BATCH];
+    state[16] = [0x80 << 56; COUNTER_BATCH];
+    keccakf(&mut state);
+    let mut result = [[0_u8; 32]; COUNTER_BATCH];
+    for (i, digest) in result.iter_mut().enumerate() {
+        for (bytes, lane) in digest.chunks_exact_mut(8).zip(state.iter()) {
+            bytes.copy_from_slice(&lane[i].to_le_bytes());
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channel::{HashFunction, Keccak256};
+    use proptest::prelude::*;
+    use tiny_keccak::Hasher;
+
+    proptest!(
+        #[test]
+        fn keccakf_matches_scalar(states: [[u64; 25]; COUNTER_BATCH]) {
+            let mut batch = [[0; COUNTER_BATCH]; 25];
+            for (i, state) in states.iter().enumerate() {
+                for (word, lane) in batch.iter_mut().zip(state.iter()) {
+                    word[i] = *lane;
+                }
+            }
+            keccakf(&mut batch);
+            for (i, state) in states.iter().enumerate() {
+                let mut expected = *state;
+                tiny_keccak::keccakf(&mut expected);
+                for (word, lane) in batch.iter().zip(expected.iter()) {
+                    prop_assert_eq!(word[i], *lane);
+                }
+            }
+        }
+
+        #[test]
+        fn hash_counters_matches_scalar(digest: [u8; 32], first in 0_u64..u64::max_value() - 8) {
+            let batch = hash_counters(&digest, first);
+            for (i, result) in batch.iter().enumerate() {
+                let mut expected = [0; 32];
+                let mut hasher = Keccak256::hasher();
+                hasher.update(&digest);
+                hasher.update(&[0_u8; 24]);
+                hasher.update(&(first + i as u64).to_be_bytes());
+                hasher.finalize(&mut expected);
+                prop_assert_eq!(result, &expected);
+            }
+        }
+    );
+}
@@ -47,12 +47,15 @@
 // TODO: False positives <https://github.com/rust-lang/rust-clippy/issues/5917>
 #![allow(clippy::wildcard_imports)]
 
-mod channel;
+pub mod channel;
 mod constraints;
+#[cfg(feature = "keccak-simd")]
+mod keccak_simd;
 mod polynomial;
 mod proof;
-mod proof_of_work;
+pub mod proof_of_work;
 mod rational_expression;
+mod sexpr;
 #[cfg(feature = "std")]
 mod solidity_seralizer;
 #[cfg(feature = "std")]
@@ -65,6 +68,8 @@ mod verifier;
 #[cfg(feature = "prover")]
 mod algebraic_dag;
 #[cfg(feature = "prover")]
+mod compiled_expression;
+#[cfg(feature = "prover")]
 pub mod component;
 #[cfg(feature = "prover")]
 mod constraint_check;
@@ -85,29 +90,47 @@ extern crate no_std_compat as std;
 pub use zkp_primefield as primefield;
 
 // Exports for verifier
+pub use channel::{Endianness, Error as ChannelError, TranscriptHash};
 pub use constraints::{Constraints, Error as ConstraintError};
 pub use polynomial::DensePolynomial;
-pub use proof::Proof;
-pub use rational_expression::RationalExpression;
+pub use proof::{Proof, ProofItem};
+pub use rational_expression::{RationalExpression, SimplifyContext};
+#[cfg(feature = "std")]
+pub use rational_expression::CanonicalForm;
+pub use sexpr::Error as ParseError;
 pub use traits::Verifiable;
 pub use verifier::{verify, Error as VerifierError};
+#[cfg(feature = "std")]
+pub use verifier::verify_from;
 
 // We want std for this so that we can use hex encode
 #[cfg(feature = "std")]
-pub use solidity_seralizer::proof_serialize;
+pub use proof::annotate_proof;
+#[cfg(feature = "std")]
+pub use solidity_seralizer::{proof_serialize, Error as SoliditySerializeError};
 #[cfg(feature = "std")]
 pub use solidity_verifier::generate;
 
 // Exports for prover
 #[cfg(feature = "prover")]
-pub use constraint_check::check_constraints;
+pub use compiled_expression::CompiledExpression;
+#[cfg(feature = "prover")]
+pub use constraint_check::{check_constraints, par_check_constraints};
 #[cfg(feature = "prover")]
-pub use prover::{prove, Error as ProverError};
+pub use prover::{prove, prove_to, Error as ProverError};
+#[cfg(all(feature = "prover", feature = "transcript-log"))]
+pub use channel::TranscriptEvent;
+#[cfg(all(feature = "prover", feature = "transcript-log"))]
+pub use prover::prove_with_transcript;
 #[cfg(feature = "prover")]
-pub use trace_table::TraceTable;
+pub use trace_table::{ColumnView, Error as TraceError, TraceTable};
 #[cfg(feature = "prover")]
 pub use traits::Provable;
 
+// Exports for tests
+#[cfg(feature = "test-utils")]
+pub use channel::MockCoin;
+
 #[cfg(test)]
 mod tests {
     pub(crate) fn init() {
@@ -1,4 +1,6 @@
 use std::prelude::v1::*;
+#[cfg(feature = "std")]
+use std::fmt::Write;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Proof(Vec<u8>);
@@ -12,3 +14,93 @@ impl Proof {
         &self.0
     }
 }
+
+/// A segment of a proof, as read by the verifier. See [`annotate_proof`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ProofItem {
+    /// 32 byte hash, such as a commitment.
+    Hash,
+    /// 32 byte field element in Montgomery form.
+    FieldElement,
+    /// 8 byte big-endian integer.
+    U64,
+    /// 8 byte big-endian proof of work nonce.
+    Pow,
+}
+
+impl ProofItem {
+    pub fn size(self) -> usize {
+        match self {
+            Self::Hash | Self::FieldElement => 32,
+            Self::U64 | Self::Pow => 8,
+        }
+    }
+}
+
+/// Renders `proof` as a hex dump with one line per item of `schedule`.
+///
+/// Each line has the byte offset, the item and its bytes. Items extending
+/// past the end of the proof are marked `(truncated)`, and bytes left over
+/// after the schedule are shown as `Trailing`.
+#[cfg(feature = "std")]
+pub fn annotate_proof(proof: &[u8], schedule: &[ProofItem]) -> String {
+    let mut result = String::new();
+    let mut offset = 0;
+    for item in schedule {
+        let end = offset + item.size();
+        let bytes = &proof[offset.min(proof.len())..end.min(proof.len())];
+        let _ = write!(
+            result,
+            "{:06x}  {:<13} {}",
+            offset,
+            format!("{:?}", item),
+            hex::encode(bytes)
+        );
+        if end > proof.len() {
+            result.push_str(" (truncated)");
+        }
+        result.push('\n');
+        offset = end;
+    }
+    if offset < proof.len() {
+        let _ = writeln!(
+            result,
+            "{:06x}  {:<13} {}",
+            offset,
+            "Trailing",
+            hex::encode(&proof[offset..])
+        );
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_annotate_proof() {
+        let mut proof = vec![0xaa; 32];
+        proof.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 138]);
+        proof.extend_from_slice(&[0xbb; 34]);
+        let annotated = annotate_proof(&proof, &[
+            ProofItem::Hash,
+            ProofItem::Pow,
+            ProofItem::FieldElement,
+        ]);
+        assert_eq!(
+            annotated,
+            format!(
+                "000000  Hash          {}\n000020  Pow           000000000000008a\n000028  \
+                 FieldElement  {}\n000048  Trailing      bbbb\n",
+                "aa".repeat(32),
+                "bb".repeat(32)
+            )
+        );
+        let truncated = annotate_proof(&proof[..36], &[ProofItem::Hash, ProofItem::U64]);
+        assert_eq!(
+            truncated.lines().last(),
+            Some("000020  U64           00000000 (truncated)")
+        );
+    }
+}
@@ -1,90 +1,387 @@
+//! Proof of work grinding for the Fiat-Shamir transcript.
+//!
+//! A [`ChallengeSeed`] is drawn from the public coin and combined with a
+//! difficulty into a [`Challenge`]. The prover searches for a [`Response`]
+//! whose Keccak256 digest has enough leading zero bits, the verifier checks
+//! it.
 #[cfg(all(feature = "std", feature = "prover"))]
-use log::{info, trace};
+use log::{info, log_enabled, trace, Level};
 #[cfg(all(feature = "std", feature = "prover"))]
 use rayon::prelude::*;
-use std::convert::TryFrom;
+#[cfg(feature = "std")]
+use std::error;
 #[cfg(all(feature = "std", feature = "prover"))]
 use std::sync::atomic::{AtomicU64, Ordering::Relaxed};
-use tiny_keccak::{Hasher, Keccak};
+use std::{
+    convert::{TryFrom, TryInto},
+    fmt,
+    prelude::v1::*,
+};
+use tiny_keccak::{keccakf, Hasher, Keccak};
 use zkp_macros_decl::hex;
+use zkp_primefield::FieldElement;
 use zkp_u256::{Binary, U256};
 
+/// Domain separation prefix hashed into every challenge, unless configured
+/// otherwise.
+pub const DEFAULT_PREFIX: [u8; 8] = hex!("0123456789abcded");
+
+/// The largest supported difficulty.
+///
+/// The difficulty is hashed into the challenge as a single byte. The smallest
+/// is one, a difficulty of zero would accept every response.
+pub const MAX_DIFFICULTY: usize = 255;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Error {
+    InvalidDifficulty(usize),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Error::*;
+        match *self {
+            InvalidDifficulty(difficulty) => {
+                write!(f, "Proof of work difficulty {} is out of range", difficulty)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for Error {}
+
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
 #[cfg_attr(feature = "std", derive(Debug))]
-pub(crate) struct ChallengeSeed([u8; 32]);
+pub struct ChallengeSeed {
+    seed:   [u8; 32],
+    prefix: Vec<u8>,
+}
 
+/// A seed combined with the prefix and difficulty.
+///
+/// The combining hash is computed once in [`ChallengeSeed::with_difficulty`],
+/// so a challenge can be reused to check many responses.
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
 #[cfg_attr(feature = "std", derive(Debug))]
-pub(crate) struct Challenge {
+pub struct Challenge {
     seed:       [u8; 32],
     difficulty: usize,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 #[cfg_attr(feature = "std", derive(Debug))]
-pub(crate) struct Response {
+pub struct Response {
     nonce: u64,
 }
 
+/// A hash on field elements, such as Poseidon.
+///
+/// Transcripts that are verified inside another proof use such a hash instead
+/// of Keccak, so grinding has to be expressed in the field too, see
+/// [`FieldChallenge`].
+pub trait AlgebraicHash {
+    fn hash(elements: &[FieldElement]) -> FieldElement;
+}
+
+/// A proof of work challenge for transcripts using an [`AlgebraicHash`].
+///
+/// The nonce is a field element. It is valid if `H(seed, difficulty, nonce)`
+/// has at least `difficulty` low-order zero bits in its canonical
+/// representation.
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct FieldChallenge {
+    seed:       FieldElement,
+    difficulty: usize,
+}
+
+impl FieldChallenge {
+    /// Fails if the difficulty exceeds [`MAX_DIFFICULTY`].
+    pub fn new(seed: FieldElement, difficulty: usize) -> Result<Self, Error> {
+        if difficulty > MAX_DIFFICULTY {
+            return Err(Error::InvalidDifficulty(difficulty));
+        }
+        Ok(Self { seed, difficulty })
+    }
+
+    /// The number of low-order zero bits of the digest for `nonce`.
+    fn work<H: AlgebraicHash>(&self, nonce: &FieldElement) -> usize {
+        H::hash(&[
+            self.seed.clone(),
+            FieldElement::from(self.difficulty),
+            nonce.clone(),
+        ])
+        .to_uint()
+        .trailing_zeros()
+    }
+
+    pub fn verify<H: AlgebraicHash>(&self, nonce: &FieldElement) -> bool {
+        self.work::<H>(nonce) >= self.difficulty
+    }
+
+    /// Find the lowest valid nonce, counting up from zero.
+    ///
+    /// A difficulty of zero is rejected, as it is by
+    /// [`ChallengeSeed::with_difficulty`].
+    #[cfg(feature = "prover")]
+    pub fn solve<H: AlgebraicHash>(&self) -> Result<FieldElement, Error> {
+        if self.difficulty == 0 {
+            return Err(Error::InvalidDifficulty(self.difficulty));
+        }
+        info!("Solving {} bit field proof of work.", self.difficulty);
+        Ok((0..u64::max_value())
+            .map(FieldElement::from)
+            .find(|nonce| self.verify::<H>(nonce))
+            .expect("No valid nonce found"))
+    }
+}
+
 impl ChallengeSeed {
-    pub(crate) fn from_bytes(seed: [u8; 32]) -> Self {
-        Self(seed)
+    #[cfg(test)]
+    pub fn from_bytes(seed: [u8; 32]) -> Self {
+        Self::from_bytes_with_prefix(seed, &DEFAULT_PREFIX)
+    }
+
+    /// Use a custom domain separation prefix, for interoperability with
+    /// verifiers that use a different one.
+    pub fn from_bytes_with_prefix(seed: [u8; 32], prefix: &[u8]) -> Self {
+        Self {
+            seed,
+            prefix: prefix.to_vec(),
+        }
     }
 
-    pub(crate) fn with_difficulty(self, difficulty: usize) -> Challenge {
+    /// Fails if the difficulty is zero or exceeds [`MAX_DIFFICULTY`].
+    pub fn with_difficulty(self, difficulty: usize) -> Result<Challenge, Error> {
+        if difficulty == 0 || difficulty > MAX_DIFFICULTY {
+            return Err(Error::InvalidDifficulty(difficulty));
+        }
         let mut seed = [0_u8; 32];
         let mut keccak = Keccak::v256();
-        keccak.update(&hex!("0123456789abcded"));
-        keccak.update(&self.0);
+        keccak.update(&self.prefix);
+        keccak.update(&self.seed);
         keccak.update(&[u8::try_from(difficulty).unwrap()]);
         keccak.finalize(&mut seed);
-        Challenge { difficulty, seed }
+        Ok(Challenge { seed, difficulty })
     }
 }
 
 impl Challenge {
-    pub(crate) fn verify(&self, response: Response) -> bool {
+    /// Keccak256 of `seed || nonce`, as the four little-endian lanes of the
+    /// digest.
+    fn digest_lanes(&self, response: Response) -> [u64; 4] {
+        // The Keccak256 input `seed || nonce` fits in a single block, so we
+        // fill in the padded block and apply the permutation directly.
+        let mut state = [0_u64; 25];
+        for (lane, bytes) in state.iter_mut().zip(self.seed.chunks_exact(8)) {
+            *lane = u64::from_le_bytes(bytes.try_into().unwrap());
+        }
+        state[4] = u64::from_le_bytes(response.nonce.to_be_bytes());
+        // Keccak padding, the last byte of the block is at the end of lane 16.
+        state[5] = 0x01;
+        state[16] = 0x80 << 56;
+        keccakf(&mut state);
+        [state[0], state[1], state[2], state[3]]
+    }
+
+    /// Keccak256 of `seed || nonce` as a big-endian number.
+    fn digest(&self, response: Response) -> U256 {
+        let mut bytes = [0_u8; 32];
+        for (chunk, lane) in bytes.chunks_exact_mut(8).zip(&self.digest_lanes(response)) {
+            chunk.copy_from_slice(&lane.to_le_bytes());
+        }
+        U256::from_bytes_be(&bytes)
+    }
+
+    /// Verify against a target threshold instead of the difficulty: the
+    /// digest, as a big-endian number, must be less than `target`.
+    ///
+    /// This allows difficulties between powers of two. A difficulty of `bits`
+    /// corresponds to a target of `2^(256 - bits)`, see
+    /// [`Challenge::difficulty_target`]. The difficulty hashed into the
+    /// challenge is not used otherwise.
+    pub fn verify_target(&self, response: Response, target: &U256) -> bool {
+        &self.digest(response) < target
+    }
+
+    /// The target that accepts the same responses as [`Challenge::verify`].
+    pub fn difficulty_target(&self) -> U256 {
+        U256::ONE << (256 - self.difficulty)
+    }
+
+    /// The number of leading zero bits of the digest.
+    fn leading_zeros(&self, response: Response) -> usize {
+        // The digest is the little-endian encoding of the first four lanes. We
+        // count leading zeros of the digest as a big-endian number.
+        let mut work = 0;
+        for lane in &self.digest_lanes(response) {
+            let zeros = lane.swap_bytes().leading_zeros() as usize;
+            work += zeros;
+            if zeros < 64 {
+                break;
+            }
+        }
+        work
+    }
+
+    pub fn verify(&self, response: Response) -> bool {
         // TODO: return Result<()>
-        // OPT: Inline Keccak256 and work directly on buffer using 'keccakf'
-        let mut keccak = Keccak::v256();
-        let mut digest = [0_u8; 32];
-        keccak.update(&self.seed);
-        keccak.update(&(response.nonce.to_be_bytes()));
-        keccak.finalize(&mut digest);
-        // OPT: Check performance impact of conversion
-        let work = U256::from_bytes_be(&digest).leading_zeros();
-        work >= self.difficulty
+        self.leading_zeros(response) >= self.difficulty
+    }
+
+    /// The number of leading zero bits of the digest if the response is
+    /// valid, `None` otherwise.
+    ///
+    /// The work can exceed the difficulty. The excess shows how much margin
+    /// the prover had, which is useful for monitoring grinding.
+    pub fn work(&self, response: Response) -> Option<usize> {
+        let work = self.leading_zeros(response);
+        if work >= self.difficulty {
+            Some(work)
+        } else {
+            None
+        }
+    }
+
+    /// Same result as [`Challenge::verify`], but without branches that depend
+    /// on the digest.
+    ///
+    /// All lanes are always inspected and the work is accumulated with
+    /// masks, so the running time only depends on the (public) difficulty.
+    /// This relies on the compiler emitting branchless code for comparisons
+    /// and on `leading_zeros` being constant time on the target, which holds
+    /// for common platforms but is not guaranteed by the language.
+    pub fn verify_ct(&self, response: Response) -> bool {
+        let mut work = 0_usize;
+        // One while all previous lanes were zero, zero after that.
+        let mut counting = 1_usize;
+        for lane in &self.digest_lanes(response) {
+            let zeros = lane.swap_bytes().leading_zeros() as usize;
+            work += counting * zeros;
+            counting &= usize::from(zeros == 64);
+        }
+        // Both values are at most 256, so `work - difficulty` wraps around to
+        // a value with high bits set exactly when the work is insufficient.
+        let deficit = work.wrapping_sub(self.difficulty);
+        deficit >> 15 == 0
     }
 }
 
+/// Finding a response takes an expected `2^difficulty` hash evaluations.
 #[cfg(feature = "prover")]
 impl Challenge {
-    #[cfg(not(feature = "std"))]
-    pub(crate) fn solve(&self) -> Response {
-        // We assume a nonce exists and will be found in reasonable time.
+    /// Find the lowest valid response.
+    ///
+    /// The whole nonce range is searched in one parallel pass. Only when trace
+    /// logging is enabled does the search go through [`Self::solve_from`] to
+    /// report progress.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no nonce is valid. For the supported difficulties this is
+    /// astronomically unlikely, but the search takes impractically long well
+    /// before that, around 60 bits.
+    pub fn solve(&self) -> Response {
+        if log_enabled!(Level::Trace) {
+            self.solve_from(0, |progress| {
+                trace!("Proof of work progress: {} nonces", progress.next_nonce);
+            })
+        } else {
+            info!("Solving {} bit proof of work.", self.difficulty);
+            self.solve_range(0, u64::max_value())
+        }
+        .expect("No valid nonce found")
+    }
+
+    /// Find the lowest valid response with a nonce below `max_iterations`.
+    ///
+    /// Returns `None` if there is no such nonce.
+    pub fn solve_bounded(&self, max_iterations: u64) -> Option<Response> {
+        info!("Solving {} bit proof of work.", self.difficulty);
+        self.solve_range(0, max_iterations)
+    }
+
+    /// Find the lowest valid response with a nonce of at least `start`.
+    ///
+    /// The search proceeds in blocks of [`PROGRESS_INTERVAL`] nonces. After
+    /// every block without a valid nonce, `report` is called with the next
+    /// nonce to try. Passing that nonce as `start` later resumes the search and
+    /// finds the same response as an uninterrupted search.
+    pub fn solve_from<F>(&self, start: u64, report: F) -> Option<Response>
+    where
+        F: Fn(Progress),
+    {
         info!(
-            "Solving {} bit proof of work single-threaded.",
-            self.difficulty
+            "Solving {} bit proof of work from nonce {}.",
+            self.difficulty, start
         );
-        #[allow(clippy::maybe_infinite_iter)]
-        (0_u64..)
+        let mut block_start = start;
+        loop {
+            let block_end = block_start.saturating_add(PROGRESS_INTERVAL);
+            if let Some(response) = self.solve_range(block_start, block_end) {
+                return Some(response);
+            }
+            if block_end == u64::max_value() {
+                return None;
+            }
+            report(Progress {
+                next_nonce: block_end,
+            });
+            block_start = block_end;
+        }
+    }
+
+    /// Find the lowest response with a digest below `target`, see
+    /// [`Challenge::verify_target`].
+    ///
+    /// Returns `None` if there is no such nonce, which is always the case for
+    /// a zero target.
+    pub fn solve_target(&self, target: &U256) -> Option<Response> {
+        info!("Solving proof of work with target {}.", target);
+        if target == &U256::ZERO {
+            return None;
+        }
+        Self::search(0, u64::max_value(), |response| {
+            self.verify_target(response, target)
+        })
+    }
+
+    /// Find the lowest valid response with a nonce in `start..end`.
+    fn solve_range(&self, start: u64, end: u64) -> Option<Response> {
+        Self::search(start, end, |response| self.verify(response))
+    }
+
+    /// Find the lowest accepted response with a nonce in `start..end`.
+    #[cfg(not(feature = "std"))]
+    fn search<F>(start: u64, end: u64, accept: F) -> Option<Response>
+    where
+        F: Fn(Response) -> bool + Sync,
+    {
+        (start..end)
             .map(|nonce| Response { nonce })
-            .find(|&response| self.verify(response))
-            .expect("No valid nonce found")
+            .find(|&response| accept(response))
     }
 
+    /// Find the lowest accepted response with a nonce in `start..end`.
+    ///
+    /// The range is shared between all threads.
     #[cfg(feature = "std")]
-    pub(crate) fn solve(&self) -> Response {
+    fn search<F>(start: u64, end: u64, accept: F) -> Option<Response>
+    where
+        F: Fn(Response) -> bool + Sync,
+    {
         let num_threads = rayon::current_num_threads();
-        info!(
-            "Solving {} bit proof of work with {} threads.",
-            self.difficulty, num_threads
+        trace!(
+            "BEGIN Proof of work {}..{} with {} threads",
+            start,
+            end,
+            num_threads
         );
-        trace!("BEGIN Proof of work");
         let first_nonce = AtomicU64::new(u64::max_value());
         (0..num_threads as u64).into_par_iter().for_each(|offset| {
-            for nonce in (offset..).step_by(num_threads) {
-                if self.verify(Response { nonce }) {
+            for nonce in (start.saturating_add(offset)..end).step_by(num_threads) {
+                if accept(Response { nonce }) {
                     let _ = fetch_min(&first_nonce, nonce);
                 }
                 if nonce >= first_nonce.load(Relaxed) {
@@ -93,18 +390,34 @@ impl Challenge {
             }
         });
         trace!("END Proof of work");
-        Response {
-            nonce: first_nonce.into_inner(),
+        let nonce = first_nonce.into_inner();
+        if nonce < end {
+            Some(Response { nonce })
+        } else {
+            None
         }
     }
 }
 
+/// Number of nonces [`Challenge::solve_from`] tries between progress reports.
+#[cfg(feature = "prover")]
+pub const PROGRESS_INTERVAL: u64 = 1 << 14;
+
+/// Progress of [`Challenge::solve_from`]: all nonces from the start up to
+/// `next_nonce` are invalid.
+#[cfg(feature = "prover")]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Progress {
+    pub next_nonce: u64,
+}
+
 impl Response {
-    pub(crate) fn from_nonce(nonce: u64) -> Self {
+    pub fn from_nonce(nonce: u64) -> Self {
         Self { nonce }
     }
 
-    pub(crate) fn nonce(self) -> u64 {
+    pub fn nonce(self) -> u64 {
         self.nonce
     }
 }
@@ -127,15 +440,321 @@ fn fetch_min(atom: &AtomicU64, value: u64) -> u64 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use zkp_primefield::Pow;
+
+    fn verify_naive(challenge: &Challenge, response: Response) -> bool {
+        let mut keccak = Keccak::v256();
+        let mut digest = [0_u8; 32];
+        keccak.update(&challenge.seed);
+        keccak.update(&(response.nonce.to_be_bytes()));
+        keccak.finalize(&mut digest);
+        let work = U256::from_bytes_be(&digest).leading_zeros();
+        work >= challenge.difficulty
+    }
+
+    #[test]
+    fn proof_of_work_verify_naive() {
+        let seed = ChallengeSeed::from_bytes(hex!(
+            "0123456789abcded0123456789abcded0123456789abcded0123456789abcded"
+        ));
+        for &difficulty in &[1, 4, 8, 12] {
+            let challenge = seed.clone().with_difficulty(difficulty).unwrap();
+            for nonce in 0..10000 {
+                let response = Response::from_nonce(nonce);
+                assert_eq!(
+                    challenge.verify(response),
+                    verify_naive(&challenge, response)
+                );
+            }
+        }
+        // Nonces with high bytes set
+        let challenge = seed.with_difficulty(4).unwrap();
+        for nonce in (0..10000).map(|i| u64::max_value() - 7919 * i) {
+            let response = Response::from_nonce(nonce);
+            assert_eq!(
+                challenge.verify(response),
+                verify_naive(&challenge, response)
+            );
+        }
+    }
+
+    #[test]
+    fn proof_of_work_work() {
+        let challenge = ChallengeSeed::from_bytes(hex!(
+            "0123456789abcded0123456789abcded0123456789abcded0123456789abcded"
+        ))
+        .with_difficulty(8)
+        .unwrap();
+        let mut keccak = Keccak::v256();
+        let mut digest = [0_u8; 32];
+        keccak.update(&challenge.seed);
+        keccak.update(&138_u64.to_be_bytes());
+        keccak.finalize(&mut digest);
+        let expected = U256::from_bytes_be(&digest).leading_zeros();
+        assert!(expected >= 8);
+        assert_eq!(challenge.work(Response::from_nonce(138)), Some(expected));
+        assert_eq!(challenge.work(Response::from_nonce(137)), None);
+        for nonce in 0..1000 {
+            let response = Response::from_nonce(nonce);
+            assert_eq!(
+                challenge.work(response).is_some(),
+                challenge.verify(response)
+            );
+        }
+    }
+
+    #[test]
+    fn proof_of_work_verify_ct() {
+        let seed = ChallengeSeed::from_bytes(hex!(
+            "0123456789abcded0123456789abcded0123456789abcded0123456789abcded"
+        ));
+        for &difficulty in &[1, 4, 8, 12, MAX_DIFFICULTY] {
+            let challenge = seed.clone().with_difficulty(difficulty).unwrap();
+            for nonce in 0..10000 {
+                let response = Response::from_nonce(nonce);
+                assert_eq!(challenge.verify_ct(response), challenge.verify(response));
+            }
+        }
+    }
 
     #[test]
     fn proof_of_work_test() {
         let challenge = ChallengeSeed::from_bytes(hex!(
             "0123456789abcded0123456789abcded0123456789abcded0123456789abcded"
         ))
-        .with_difficulty(8);
+        .with_difficulty(8)
+        .unwrap();
         let response = challenge.solve();
         assert_eq!(response.nonce, 138);
         assert!(challenge.verify(response));
     }
+
+    #[test]
+    fn proof_of_work_lowest_nonce() {
+        // The parallel search must find the same nonce as a sequential one.
+        for i in 0..16_u8 {
+            let challenge = ChallengeSeed::from_bytes([i; 32])
+                .with_difficulty(10)
+                .unwrap();
+            let sequential = (0..u64::max_value())
+                .map(Response::from_nonce)
+                .find(|&response| challenge.verify(response))
+                .unwrap();
+            assert_eq!(challenge.solve(), sequential);
+        }
+    }
+
+    #[test]
+    fn proof_of_work_target() {
+        let seed = ChallengeSeed::from_bytes(hex!(
+            "0123456789abcded0123456789abcded0123456789abcded0123456789abcded"
+        ));
+        let challenge = seed.clone().with_difficulty(8).unwrap();
+        let target = challenge.difficulty_target();
+        assert_eq!(target, U256::ONE << 248);
+        for nonce in 0..2000 {
+            let response = Response::from_nonce(nonce);
+            assert_eq!(
+                challenge.verify_target(response, &target),
+                challenge.verify(response)
+            );
+        }
+        assert_eq!(challenge.solve_target(&target).unwrap().nonce(), 138);
+
+        // A target between 2^247 and 2^248 is harder than 8 bits.
+        let harder = (U256::ONE << 247) + (U256::ONE << 246);
+        let response = challenge.solve_target(&harder).unwrap();
+        assert!(response.nonce() >= 138);
+        assert!(challenge.verify_target(response, &harder));
+        assert!(challenge.verify(response));
+        assert_eq!(challenge.solve_target(&U256::ZERO), None);
+        let easiest = seed.with_difficulty(1).unwrap();
+        assert_eq!(easiest.difficulty_target(), U256::ONE << 255);
+    }
+
+    #[test]
+    fn proof_of_work_bounded() {
+        let challenge = ChallengeSeed::from_bytes(hex!(
+            "0123456789abcded0123456789abcded0123456789abcded0123456789abcded"
+        ))
+        .with_difficulty(8)
+        .unwrap();
+        assert_eq!(challenge.solve_bounded(0), None);
+        assert_eq!(challenge.solve_bounded(10), None);
+        assert_eq!(challenge.solve_bounded(138), None);
+        assert_eq!(
+            challenge.solve_bounded(139),
+            Some(Response::from_nonce(138))
+        );
+        assert_eq!(
+            challenge.solve_bounded(1_000_000),
+            Some(Response::from_nonce(138))
+        );
+    }
+
+    #[test]
+    fn proof_of_work_resume() {
+        let challenge = ChallengeSeed::from_bytes(hex!(
+            "0123456789abcded0123456789abcded0123456789abcded0123456789abcded"
+        ))
+        .with_difficulty(8)
+        .unwrap();
+        let no_progress = |progress: Progress| panic!("Unexpected {:?}", progress);
+        for &start in &[0, 100, 137, 138] {
+            assert_eq!(
+                challenge.solve_from(start, no_progress),
+                Some(Response::from_nonce(138))
+            );
+        }
+        let next = challenge.solve_from(139, no_progress).unwrap();
+        assert!(next.nonce() > 138);
+        assert!(challenge.verify(next));
+        assert_eq!(
+            challenge.solve_bounded(next.nonce()),
+            Some(Response::from_nonce(138))
+        );
+    }
+
+    #[test]
+    fn proof_of_work_progress() {
+        let challenge = ChallengeSeed::from_bytes(hex!(
+            "0123456789abcded0123456789abcded0123456789abcded0123456789abcded"
+        ))
+        .with_difficulty(16)
+        .unwrap();
+        let expected = challenge.solve();
+        let reports = std::sync::Mutex::new(Vec::new());
+        let response = challenge.solve_from(0, |progress| reports.lock().unwrap().push(progress));
+        assert_eq!(response, Some(expected));
+        let reports = reports.into_inner().unwrap();
+        assert!(!reports.is_empty());
+        for (i, progress) in reports.iter().enumerate() {
+            assert_eq!(progress.next_nonce, (i as u64 + 1) * PROGRESS_INTERVAL);
+            assert!(progress.next_nonce <= expected.nonce());
+        }
+        // Resume from the last checkpoint
+        let resumed = challenge.solve_from(reports.last().unwrap().next_nonce, |_| {});
+        assert_eq!(resumed, Some(expected));
+    }
+
+    #[test]
+    fn proof_of_work_difficulties() {
+        let seed = ChallengeSeed::from_bytes(hex!(
+            "0123456789abcded0123456789abcded0123456789abcded0123456789abcded"
+        ));
+        for &difficulty in &[1, 20] {
+            let challenge = seed.clone().with_difficulty(difficulty).unwrap();
+            let response = challenge.solve();
+            assert!(challenge.verify(response));
+        }
+    }
+
+    #[test]
+    #[ignore = "Expensive test to run, takes an expected 2^40 hashes"]
+    fn proof_of_work_difficulty_40() {
+        let challenge = ChallengeSeed::from_bytes(hex!(
+            "0123456789abcded0123456789abcded0123456789abcded0123456789abcded"
+        ))
+        .with_difficulty(40)
+        .unwrap();
+        let response = challenge.solve();
+        assert!(challenge.verify(response));
+        assert!(challenge.work(response).unwrap() >= 40);
+    }
+
+    #[test]
+    fn proof_of_work_prefix() {
+        let digest = hex!("0123456789abcded0123456789abcded0123456789abcded0123456789abcded");
+        let default = ChallengeSeed::from_bytes(digest)
+            .with_difficulty(8)
+            .unwrap();
+        let explicit = ChallengeSeed::from_bytes_with_prefix(digest, &DEFAULT_PREFIX)
+            .with_difficulty(8)
+            .unwrap();
+        let custom = ChallengeSeed::from_bytes_with_prefix(digest, b"other protocol")
+            .with_difficulty(8)
+            .unwrap();
+        assert_eq!(default, explicit);
+        assert_eq!(default.solve().nonce(), 138);
+        let response = custom.solve();
+        assert_ne!(response.nonce(), 138);
+        assert!(custom.verify(response));
+    }
+
+    #[test]
+    fn proof_of_work_reuse_challenge() {
+        let seed = ChallengeSeed::from_bytes(hex!(
+            "0123456789abcded0123456789abcded0123456789abcded0123456789abcded"
+        ));
+        let challenge = seed.clone().with_difficulty(8).unwrap();
+        for nonce in 0..300 {
+            let response = Response::from_nonce(nonce);
+            let one_shot = seed.clone().with_difficulty(8).unwrap();
+            assert_eq!(challenge.verify(response), one_shot.verify(response));
+        }
+        assert!(challenge.verify(Response::from_nonce(138)));
+    }
+
+    #[test]
+    fn proof_of_work_invalid_difficulty() {
+        let seed = ChallengeSeed::from_bytes(hex!(
+            "0123456789abcded0123456789abcded0123456789abcded0123456789abcded"
+        ));
+        assert!(seed.clone().with_difficulty(1).is_ok());
+        assert!(seed.clone().with_difficulty(MAX_DIFFICULTY).is_ok());
+        assert_eq!(
+            seed.clone().with_difficulty(0),
+            Err(Error::InvalidDifficulty(0))
+        );
+        assert_eq!(
+            seed.with_difficulty(256),
+            Err(Error::InvalidDifficulty(256))
+        );
+    }
+
+    /// A toy `MiMC` style sponge, standing in for an algebraic hash.
+    struct Cube;
+
+    impl AlgebraicHash for Cube {
+        fn hash(elements: &[FieldElement]) -> FieldElement {
+            let mut state = FieldElement::from(7);
+            for element in elements {
+                state += element;
+                for round in 0..8_usize {
+                    state = state.pow(3_usize) + FieldElement::from(round);
+                }
+            }
+            state
+        }
+    }
+
+    #[test]
+    fn proof_of_work_field() {
+        let challenge = FieldChallenge::new(FieldElement::from(1234), 8).unwrap();
+        let nonce = challenge.solve::<Cube>().unwrap();
+        assert!(challenge.verify::<Cube>(&nonce));
+        let lowest = (0..1000_u64)
+            .map(FieldElement::from)
+            .find(|nonce| challenge.verify::<Cube>(nonce))
+            .unwrap();
+        assert_eq!(nonce, lowest);
+        assert!(
+            Cube::hash(&[FieldElement::from(1234), FieldElement::from(8), nonce])
+                .to_uint()
+                .trailing_zeros()
+                >= 8
+        );
+
+        let other = FieldChallenge::new(FieldElement::from(1235), 8).unwrap();
+        let other_nonce = other.solve::<Cube>().unwrap();
+        assert!(other.verify::<Cube>(&other_nonce));
+
+        let zero = FieldChallenge::new(FieldElement::from(1234), 0).unwrap();
+        assert!(zero.verify::<Cube>(&FieldElement::from(5)));
+        assert_eq!(zero.solve::<Cube>(), Err(Error::InvalidDifficulty(0)));
+        assert_eq!(
+            FieldChallenge::new(FieldElement::from(1234), 256),
+            Err(Error::InvalidDifficulty(256))
+        );
+    }
 }
@@ -1,14 +1,19 @@
 use crate::{
     algebraic_dag::AlgebraicGraph,
-    channel::{ProverChannel, RandomGenerator, Writable},
+    channel::{
+        Blake2s256, HashFunction, Keccak256, ProofSink, ProverChannel, RandomGenerator,
+        TranscriptHash, Writable,
+    },
     constraints::Constraints,
     polynomial::DensePolynomial,
-    proof_of_work, verify, Proof, TraceTable, VerifierError,
+    proof_of_work, verify, Proof, TraceError, TraceTable, VerifierError,
 };
+#[cfg(feature = "transcript-log")]
+use crate::channel::TranscriptEvent;
 use itertools::Itertools;
 use log::{info, trace};
 use rayon::prelude::*;
-use std::{fmt, prelude::v1::*, vec};
+use std::{fmt, io, mem, prelude::v1::*, vec};
 use zkp_hash::{Hash, Hashable, MaskedKeccak};
 use zkp_merkle_tree::{Error as MerkleError, Tree, VectorCommitment};
 use zkp_mmap_vec::MmapVec;
@@ -24,8 +29,12 @@ type Result<T> = std::result::Result<T, Error>;
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub enum Error {
     RootUnavailable,
+    InvalidPowDifficulty,
+    PowNotFound,
+    WriteFailed,
     MerkleFailed(MerkleError),
     VerificationFailed(VerifierError),
+    TraceFailed(TraceError),
 }
 
 impl fmt::Display for Error {
@@ -33,8 +42,12 @@ impl fmt::Display for Error {
         use Error::*;
         match *self {
             RootUnavailable => write!(f, "The prime field doesn't have a root of this order"),
+            InvalidPowDifficulty => write!(f, "The proof of work difficulty is out of range"),
+            PowNotFound => write!(f, "No proof of work nonce found within the search limit"),
+            WriteFailed => write!(f, "Writing the proof failed"),
             MerkleFailed(ref e) => std::fmt::Display::fmt(e, f),
             VerificationFailed(ref e) => std::fmt::Display::fmt(e, f),
+            TraceFailed(ref e) => std::fmt::Display::fmt(e, f),
         }
     }
 }
@@ -45,12 +58,26 @@ impl From<MerkleError> for Error {
     }
 }
 
+impl From<proof_of_work::Error> for Error {
+    fn from(err: proof_of_work::Error) -> Self {
+        match err {
+            proof_of_work::Error::InvalidDifficulty(_) => Self::InvalidPowDifficulty,
+        }
+    }
+}
+
 impl From<VerifierError> for Error {
     fn from(err: VerifierError) -> Self {
         Self::VerificationFailed(err)
     }
 }
 
+impl From<TraceError> for Error {
+    fn from(err: TraceError) -> Self {
+        Self::TraceFailed(err)
+    }
+}
+
 #[derive(Clone, Debug)]
 struct PolyLDE(Vec<MmapVec<FieldElement>>);
 
@@ -59,18 +86,14 @@ struct PolyLDE(Vec<MmapVec<FieldElement>>);
 #[allow(clippy::use_self)]
 impl VectorCommitment for PolyLDE {
     // TODO: Copy free implementation. Maybe have index as a leaf type.
-    type Leaf = Vec<U256>;
+    type Leaf = Vec<FieldElement>;
 
     fn len(&self) -> usize {
         self.0.first().map_or(0, MmapVec::len)
     }
 
     fn leaf(&self, index: usize) -> Self::Leaf {
-        let mut ret = Vec::with_capacity(self.0.len());
-        for item in &self.0 {
-            ret.push(item[index].as_montgomery().clone())
-        }
-        ret
+        self.0.iter().map(|column| column[index].clone()).collect()
     }
 
     fn leaf_hash(&self, index: usize) -> Hash {
@@ -340,14 +363,99 @@ impl VectorCommitment for FriLeaves {
 ///
 /// with merkle proofs to that layer. This process is repeated for all FRI layer
 /// commitments.
+pub fn prove(constraints: &Constraints, trace: &TraceTable) -> Result<Proof> {
+    match constraints.transcript_hash {
+        TranscriptHash::Keccak256 => {
+            prove_with_hash::<Keccak256>(constraints, trace).map(|(proof, _)| proof)
+        }
+        TranscriptHash::Blake2s256 => {
+            prove_with_hash::<Blake2s256>(constraints, trace).map(|(proof, _)| proof)
+        }
+    }
+}
+
+/// Produce a Stark proof and the transcript of the prover.
+///
+/// The proof is the same as the one returned by [`prove`]. The transcript has
+/// every write and draw of the prover in order, see [`TranscriptEvent`]. This
+/// can be compared against the transcript of another implementation.
+///
+/// [`TranscriptEvent`]: crate::TranscriptEvent
+#[cfg(feature = "transcript-log")]
+pub fn prove_with_transcript(
+    constraints: &Constraints,
+    trace: &TraceTable,
+) -> Result<(Proof, Vec<TranscriptEvent>)> {
+    match constraints.transcript_hash {
+        TranscriptHash::Keccak256 => {
+            prove_with_hash::<Keccak256>(constraints, trace)
+                .map(|(proof, channel)| (proof, channel.events().to_vec()))
+        }
+        TranscriptHash::Blake2s256 => {
+            prove_with_hash::<Blake2s256>(constraints, trace)
+                .map(|(proof, channel)| (proof, channel.events().to_vec()))
+        }
+    }
+}
+
+/// Produce a Stark proof and write it to `writer` as it is constructed.
+///
+/// The bytes are the same as those of the [`Proof`] returned by [`prove`], but
+/// they are not retained in memory. For the same reason the proof is not
+/// verified before returning. Returns the writer after flushing it.
+pub fn prove_to<W: io::Write>(
+    constraints: &Constraints,
+    trace: &TraceTable,
+    writer: W,
+) -> Result<W> {
+    match constraints.transcript_hash {
+        TranscriptHash::Keccak256 => prove_to_with_hash::<Keccak256, W>(constraints, trace, writer),
+        TranscriptHash::Blake2s256 => {
+            prove_to_with_hash::<Blake2s256, W>(constraints, trace, writer)
+        }
+    }
+}
+
+/// Returns the proof and the channel it was written to.
+fn prove_with_hash<H: HashFunction>(
+    constraints: &Constraints,
+    trace: &TraceTable,
+) -> Result<(Proof, ProverChannel<H>)> {
+    info!("Initialize channel with claim.");
+    let mut channel = constraints.channel::<H>().build_prover();
+
+    write_proof(constraints, trace, &mut channel)?;
+
+    // Verify proof
+    info!("Verify proof.");
+    let proof = Proof::from_bytes(mem::take(&mut channel.proof));
+    verify(constraints, &proof)?;
+    Ok((proof, channel))
+}
+
+fn prove_to_with_hash<H: HashFunction, W: io::Write>(
+    constraints: &Constraints,
+    trace: &TraceTable,
+    writer: W,
+) -> Result<W> {
+    info!("Initialize channel with claim.");
+    let mut proof = constraints.channel::<H>().build_streaming_prover(writer);
+
+    write_proof(constraints, trace, &mut proof)?;
+    proof.finish().map_err(|_| Error::WriteFailed)
+}
+
 // TODO: Simplify
 #[allow(clippy::cognitive_complexity)]
 // TODO: Split up
 #[allow(clippy::too_many_lines)]
-pub fn prove(constraints: &Constraints, trace: &TraceTable) -> Result<Proof> {
+fn write_proof<H: HashFunction, S: ProofSink>(
+    constraints: &Constraints,
+    trace: &TraceTable,
+    proof: &mut ProverChannel<H, S>,
+) -> Result<()> {
     // This hack allows us to avoid changing the interface to mut for the
     // claim polynomials but is ugly and should be removed.
-    let original_constraints = constraints.clone();
     let mut constraints = constraints.clone();
     // TODO: Verify input
     //  * Constraint trace length matches trace table length
@@ -368,10 +476,6 @@ pub fn prove(constraints: &Constraints, trace: &TraceTable) -> Result<Proof> {
     );
     info!("{} constraints", constraints.len(),);
 
-    info!("Initialize channel with claim.");
-    let mut proof = ProverChannel::default();
-    proof.initialize(constraints.channel_seed());
-
     // 1. Trace commitment.
     trace!("BEGIN Trace commitment");
 
@@ -404,7 +508,7 @@ pub fn prove(constraints: &Constraints, trace: &TraceTable) -> Result<Proof> {
 
     // Read constraint coefficients from the channel.
     info!("Read constraint coefficients from the channel.");
-    let constraint_coefficients = get_coefficients(&mut proof, 2 * constraints.len());
+    let constraint_coefficients = get_coefficients(proof, 2 * constraints.len());
 
     info!("Compute constraint polynomials.");
     let constraint_polynomials = get_constraint_polynomials(
@@ -441,7 +545,7 @@ pub fn prove(constraints: &Constraints, trace: &TraceTable) -> Result<Proof> {
     info!("Divide out OODS point and combine polynomials.");
     trace!("BEGIN Out of domain sampling");
     let oods_polynomial = oods_combine(
-        &mut proof,
+        proof,
         &trace_polynomials,
         &constraints.trace_arguments(),
         &constraint_polynomials,
@@ -456,7 +560,7 @@ pub fn prove(constraints: &Constraints, trace: &TraceTable) -> Result<Proof> {
     info!("Fri layers.");
     let fri_trees = perform_fri_layering(
         first_fri_layer,
-        &mut proof,
+        proof,
         &constraints.fri_layout,
         constraints.blowup,
     )?;
@@ -465,10 +569,23 @@ pub fn prove(constraints: &Constraints, trace: &TraceTable) -> Result<Proof> {
     // 5. Proof of work
     info!("Proof of work.");
     let pow_seed: proof_of_work::ChallengeSeed = proof.get_random();
-    let pow_challenge = pow_seed.with_difficulty(constraints.pow_bits);
-    let pow_response = pow_challenge.solve();
-    debug_assert!(pow_challenge.verify(pow_response));
-    proof.write(pow_response);
+    let pow_response = if constraints.pow_bits == 0 {
+        // Grinding is disabled, but the proof still contains a nonce.
+        proof_of_work::Response::from_nonce(0)
+    } else {
+        let pow_challenge = pow_seed.with_difficulty(constraints.pow_bits)?;
+        let pow_response = match constraints.pow_max_iterations {
+            Some(max_iterations) => {
+                pow_challenge
+                    .solve_bounded(max_iterations)
+                    .ok_or(Error::PowNotFound)?
+            }
+            None => pow_challenge.solve(),
+        };
+        debug_assert!(pow_challenge.verify(pow_response));
+        pow_response
+    };
+    proof.write_pow(constraints.pow_bits, pow_response);
 
     // 6. Query decommitments
     //
@@ -476,39 +593,29 @@ pub fn prove(constraints: &Constraints, trace: &TraceTable) -> Result<Proof> {
     // Fetch query indices from channel.
     info!("Fetch query indices from channel.");
     let eval_domain_size = trace.num_rows() * constraints.blowup;
-    let query_indices = get_indices(
-        constraints.num_queries,
-        64 - eval_domain_size.leading_zeros() - 1,
-        &mut proof,
-    );
+    let query_indices = get_indices(constraints.num_queries, eval_domain_size, proof);
     info!("Query indices: {:?}", query_indices);
 
     // Decommit the trace table values.
     info!("Decommit the trace table values.");
     for &index in &query_indices {
-        proof.write(tree.leaf(index));
+        proof.write_elements_grouped(&tree.leaf(index), 1);
     }
     proof.write(&tree.open(&query_indices)?);
 
     // Decommit the constraint values
     info!("Decommit the constraint values.");
     for &index in &query_indices {
-        proof.write(c_tree.leaf(index));
+        proof.write_elements_grouped(&c_tree.leaf(index), 1);
     }
     proof.write(&c_tree.open(&query_indices)?);
 
     // Decommit the FRI layer values
     info!("Decommit the FRI layer values.");
-    decommit_fri_layers_and_trees(fri_trees.as_slice(), query_indices.as_slice(), &mut proof)?;
-
-    // Verify proof
-    info!("Verify proof.");
-    // TODO: Rename channel / transcript object
-    let proof = Proof::from_bytes(proof.proof);
-    verify(&original_constraints, &proof)?;
+    decommit_fri_layers_and_trees(fri_trees.as_slice(), query_indices.as_slice(), proof)?;
 
     trace!("END Stark proof");
-    Ok(proof)
+    Ok(())
 }
 
 // Constructs a trace table on a coset domain of `size`.
@@ -528,11 +635,27 @@ fn extract_trace_coset(trace_lde: &PolyLDE, size: usize) -> TraceTable {
     trace_coset
 }
 
-fn get_indices(num: usize, bits: u32, proof: &mut ProverChannel) -> Vec<usize> {
+/// Draw `num` sorted query indices into an evaluation domain of `size`.
+///
+/// Power of two domains take four indices from each random `U256`, matching
+/// the Solidity verifier. Other domains draw each index using
+/// [`PublicCoin::get_random_index`](crate::channel::PublicCoin::get_random_index)
+/// so they are not biased towards small indices.
+fn get_indices<H: HashFunction, S: ProofSink>(
+    num: usize,
+    size: usize,
+    proof: &mut ProverChannel<H, S>,
+) -> Vec<usize> {
+    if !size.is_power_of_two() {
+        let mut query_indices: Vec<usize> =
+            (0..num).map(|_| proof.coin.get_random_index(size)).collect();
+        query_indices.sort_unstable();
+        return query_indices;
+    }
     let mut query_indices = Vec::with_capacity(num + 3);
     while query_indices.len() < num {
         let val: U256 = proof.get_random();
-        let mask = 2_usize.pow(bits) - 1;
+        let mask = size - 1;
         query_indices.push((val.clone() >> (0x100 - 0x040)).as_usize() & mask);
         query_indices.push((val.clone() >> (0x100 - 0x080)).as_usize() & mask);
         query_indices.push((val.clone() >> (0x100 - 0x0C0)).as_usize() & mask);
@@ -649,8 +772,8 @@ fn get_constraint_polynomials(
     result
 }
 
-fn oods_combine(
-    proof: &mut ProverChannel,
+fn oods_combine<H: HashFunction, S: ProofSink>(
+    proof: &mut ProverChannel<H, S>,
     trace_polynomials: &[DensePolynomial],
     trace_arguments: &[(usize, isize)],
     constraint_polynomials: &[DensePolynomial],
@@ -704,13 +827,16 @@ fn oods_combine(
 }
 
 // TODO: remove this and refactor ProverChannel.
-fn get_coefficients(proof: &mut ProverChannel, n: usize) -> Vec<FieldElement> {
-    (0..n).map(|_| proof.get_random()).collect()
+fn get_coefficients<H: HashFunction, S: ProofSink>(
+    proof: &mut ProverChannel<H, S>,
+    n: usize,
+) -> Vec<FieldElement> {
+    proof.coin.get_random_many(n)
 }
 
-fn perform_fri_layering(
+fn perform_fri_layering<H: HashFunction, S: ProofSink>(
     first_layer: MmapVec<FieldElement>,
-    proof: &mut ProverChannel,
+    proof: &mut ProverChannel<H, S>,
     fri_layout: &[usize],
     blowup: usize,
 ) -> Result<Vec<FriTree>> {
@@ -751,7 +877,7 @@ fn perform_fri_layering(
 
         // Write commitment and pull coefficient
         proof.write(tree.commitment());
-        let coefficient = proof.get_random();
+        let coefficient: FieldElement = proof.get_random();
 
         // Fold layer up to three times
         // TODO: Capture the pattern in a macro and DRY.
@@ -832,10 +958,10 @@ fn perform_fri_layering(
     Ok(fri_trees)
 }
 
-fn decommit_fri_layers_and_trees(
+fn decommit_fri_layers_and_trees<H: HashFunction, S: ProofSink>(
     fri_trees: &[FriTree],
     query_indices: &[usize],
-    proof: &mut ProverChannel,
+    proof: &mut ProverChannel<H, S>,
 ) -> Result<()> {
     let mut previous_indices: Vec<usize> = query_indices.to_vec();
 
@@ -867,7 +993,7 @@ fn decommit_fri_layers_and_trees(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{traits::tests::Recurrance, verify, Provable, Verifiable};
+    use crate::{traits::tests::Recurrance, verify, Endianness, Provable, Verifiable};
     use tiny_keccak::{Hasher, Sha3};
     use zkp_macros_decl::{field_element, hex, u256h};
     use zkp_primefield::{fft::permute_index, geometric_series::geometric_series};
@@ -963,6 +1089,189 @@ mod tests {
         verify(&constraints, &actual).unwrap();
     }
 
+    #[test]
+    fn blake2s_transcript() {
+        let recurrance = Recurrance {
+            index:         1000,
+            initial_value: field_element!("cafebabe"),
+            exponent:      1,
+        };
+        let witness = recurrance.witness();
+        let claim = recurrance.claim();
+
+        let mut constraints = claim.constraints();
+        constraints.blowup = 16;
+        constraints.pow_bits = 12;
+        constraints.num_queries = 20;
+        constraints.fri_layout = vec![3, 2];
+        let trace = claim.trace(&witness);
+        let keccak = prove(&constraints, &trace).unwrap();
+        constraints.transcript_hash = TranscriptHash::Blake2s256;
+        let blake2s = prove(&constraints, &trace).unwrap();
+        verify(&constraints, &blake2s).unwrap();
+
+        // The trace commitment is written before the first draw.
+        assert_eq!(blake2s.as_bytes()[..32], keccak.as_bytes()[..32]);
+        assert_ne!(blake2s.as_bytes()[32..64], keccak.as_bytes()[32..64]);
+        assert!(verify(&constraints, &keccak).is_err());
+    }
+
+    #[test]
+    fn custom_grinding_prefix() {
+        let recurrance = Recurrance {
+            index:         1000,
+            initial_value: field_element!("cafebabe"),
+            exponent:      1,
+        };
+        let witness = recurrance.witness();
+        let claim = recurrance.claim();
+
+        let mut constraints = claim.constraints();
+        constraints.blowup = 16;
+        constraints.pow_bits = 12;
+        constraints.num_queries = 20;
+        constraints.fri_layout = vec![3, 2];
+        let trace = claim.trace(&witness);
+        let default = prove(&constraints, &trace).unwrap();
+        let mut custom_constraints = constraints.clone();
+        custom_constraints.grinding_prefix = b"other protocol".to_vec();
+        let custom = prove(&custom_constraints, &trace).unwrap();
+        verify(&custom_constraints, &custom).unwrap();
+
+        assert_ne!(custom, default);
+        assert_eq!(
+            verify(&constraints, &custom),
+            Err(VerifierError::InvalidPoW)
+        );
+    }
+
+    #[test]
+    fn prove_bounded_pow() {
+        let recurrance = Recurrance {
+            index:         1000,
+            initial_value: field_element!("cafebabe"),
+            exponent:      1,
+        };
+        let claim = recurrance.claim();
+        let mut constraints = claim.constraints();
+        constraints.pow_bits = 12;
+        let trace = claim.trace(&recurrance.witness());
+        let unbounded = prove(&constraints, &trace).unwrap();
+
+        constraints.pow_max_iterations = Some(1 << 40);
+        assert_eq!(prove(&constraints, &trace), Ok(unbounded));
+        constraints.pow_max_iterations = Some(0);
+        assert_eq!(prove(&constraints, &trace), Err(Error::PowNotFound));
+    }
+
+    #[test]
+    fn prove_to_writer() {
+        struct FailingWriter;
+
+        impl io::Write for FailingWriter {
+            fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+                Err(io::ErrorKind::BrokenPipe.into())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let recurrance = Recurrance {
+            index:         1000,
+            initial_value: field_element!("cafebabe"),
+            exponent:      1,
+        };
+        let witness = recurrance.witness();
+        let claim = recurrance.claim();
+
+        let mut constraints = claim.constraints();
+        constraints.blowup = 16;
+        constraints.pow_bits = 12;
+        constraints.num_queries = 20;
+        constraints.fri_layout = vec![3, 2];
+        let trace = claim.trace(&witness);
+        let proof = prove(&constraints, &trace).unwrap();
+
+        let streamed = prove_to(&constraints, &trace, Vec::new()).unwrap();
+        assert_eq!(streamed, proof.as_bytes());
+
+        constraints.transcript_hash = TranscriptHash::Blake2s256;
+        let proof = prove(&constraints, &trace).unwrap();
+        let streamed = prove_to(&constraints, &trace, Vec::new()).unwrap();
+        assert_eq!(streamed, proof.as_bytes());
+
+        assert_eq!(
+            prove_to(&constraints, &trace, FailingWriter).err(),
+            Some(Error::WriteFailed)
+        );
+    }
+
+    #[cfg(feature = "transcript-log")]
+    #[test]
+    fn prove_with_transcript_events() {
+        let recurrance = Recurrance {
+            index:         1000,
+            initial_value: field_element!("cafebabe"),
+            exponent:      1,
+        };
+        let claim = recurrance.claim();
+        let mut constraints = claim.constraints();
+        constraints.pow_bits = 8;
+        let trace = claim.trace(&recurrance.witness());
+        let (proof, events) = prove_with_transcript(&constraints, &trace).unwrap();
+        assert_eq!(proof, prove(&constraints, &trace).unwrap());
+
+        // The transcript starts with the trace commitment.
+        assert_eq!(events[0], TranscriptEvent::Write {
+            kind:  "bytes",
+            bytes: proof.as_bytes()[..32].to_vec(),
+        });
+        let pow = events.iter().filter_map(|event| {
+            match event {
+                TranscriptEvent::Pow { bits, .. } => Some(*bits),
+                _ => None,
+            }
+        });
+        assert!(pow.eq(vec![8]));
+    }
+
+    #[test]
+    fn get_indices_non_power_of_two() {
+        let mut proof: ProverChannel = ProverChannel::default();
+        proof.initialize(&hex!("0123456789abcded"));
+        let mut coin = proof.coin.clone();
+        let indices = get_indices(20, 24, &mut proof);
+        let mut expected: Vec<usize> = (0..20).map(|_| coin.get_random_index(24)).collect();
+        expected.sort_unstable();
+        assert_eq!(indices, expected);
+        assert!(indices.iter().all(|&index| index < 24));
+    }
+
+    #[test]
+    fn prove_little_endian() {
+        let recurrance = Recurrance {
+            index:         1000,
+            initial_value: field_element!("cafebabe"),
+            exponent:      1,
+        };
+        let claim = recurrance.claim();
+        let mut constraints = claim.constraints();
+        let trace = claim.trace(&recurrance.witness());
+        let big = prove(&constraints, &trace).unwrap();
+        assert_eq!(verify(&constraints, &big), Ok(()));
+
+        constraints.endianness = Endianness::Little;
+        let little = prove(&constraints, &trace).unwrap();
+        assert_eq!(verify(&constraints, &little), Ok(()));
+
+        // Neither proof verifies under the other's endianness.
+        assert!(verify(&constraints, &big).is_err());
+        constraints.endianness = Endianness::Big;
+        assert!(verify(&constraints, &little).is_err());
+    }
+
     // TODO: What are we actually testing here? Should we add these as debug_assert
     // to the main implementation? Should we break up the implementation so we
     // can test the individual steps?
@@ -1024,11 +1333,11 @@ mod tests {
 
         // Checks that the groupable trait is properly grouping for &[Vec<FieldElement>]
         assert_eq!(
-            (LDEn.leaf(3243))[0].clone(),
+            LDEn.leaf(3243)[0].as_montgomery().clone(),
             u256h!("01ddd9e389a326817ad1d2a5311e1bc2cf7fa734ebdc2961085b5acfa87a58ff")
         );
         assert_eq!(
-            (LDEn.leaf(3243))[1].clone(),
+            LDEn.leaf(3243)[1].as_montgomery().clone(),
             u256h!("03dbc6c47df0606997c2cefb20c4277caf2b76bca1d31c13432f71cdd93b3718")
         );
 
@@ -1040,7 +1349,7 @@ mod tests {
             hex!("018dc61f748b1a6c440827876f30f63cb6c4c188000000000000000000000000")
         );
 
-        let mut proof = ProverChannel::default();
+        let mut proof: ProverChannel = ProverChannel::default();
         proof.initialize(&claim.seed());
         // Checks that the channel is inited properly
         assert_eq!(
@@ -1137,25 +1446,21 @@ mod tests {
         );
 
         let pow_seed: proof_of_work::ChallengeSeed = proof.get_random();
-        let pow_challenge = pow_seed.with_difficulty(constraints.pow_bits);
+        let pow_challenge = pow_seed.with_difficulty(constraints.pow_bits).unwrap();
         let pow_response = pow_challenge.solve();
         debug_assert!(pow_challenge.verify(pow_response));
         // Checks that the pow function is working [may also fail if the previous steps
         // have perturbed the channel's random]
         assert_eq!(pow_response.nonce(), 281);
-        proof.write(pow_response);
+        proof.write_pow(constraints.pow_bits, pow_response);
 
-        let query_indices = get_indices(
-            constraints.num_queries,
-            64 - eval_domain_size.leading_zeros() - 1,
-            &mut proof,
-        );
+        let query_indices = get_indices(constraints.num_queries, eval_domain_size, &mut proof);
         // Checks that the get query_indices is working
         assert_eq!(query_indices[19], 16377);
 
         // Decommit trace table
         for &index in &query_indices {
-            proof.write(tree.leaf(index))
+            proof.write_elements_grouped(&tree.leaf(index), 1);
         }
         proof.write(&tree.open(&query_indices).unwrap());
 
@@ -1167,7 +1472,7 @@ mod tests {
 
         // Decommit constraints poly
         for &index in &query_indices {
-            proof.write(c_tree.leaf(index))
+            proof.write_elements_grouped(&c_tree.leaf(index), 1);
         }
         proof.write(&c_tree.open(&query_indices).unwrap());
 
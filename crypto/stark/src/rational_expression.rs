@@ -3,13 +3,14 @@ use crate::polynomial::DensePolynomial;
 use std::{cmp::Ordering, collections::hash_map::DefaultHasher};
 use std::{
     collections::BTreeSet,
+    fmt,
     hash::{Hash, Hasher},
     iter::Sum,
     ops::{Add, Div, Mul, Sub},
     prelude::v1::*,
 };
 use zkp_macros_decl::field_element;
-use zkp_primefield::{FieldElement, Inv, One, Pow, Zero};
+use zkp_primefield::{fft::permute, Fft, FieldElement, Inv, One, Pow, Root, Zero};
 use zkp_u256::U256;
 
 // TODO: Rename to algebraic expression
@@ -30,6 +31,51 @@ pub enum RationalExpression {
     Exp(Box<RationalExpression>, usize),
 }
 
+/// A [`RationalExpression`] normalized by [`RationalExpression::canonicalize`].
+///
+/// Expressions that only differ in the order or grouping of sums and products
+/// have equal canonical forms with equal hashes.
+#[cfg(feature = "std")]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct CanonicalForm(RationalExpression);
+
+#[cfg(feature = "std")]
+impl CanonicalForm {
+    pub fn expression(&self) -> &RationalExpression {
+        &self.0
+    }
+}
+
+/// The domain `X` ranges over, for [`RationalExpression::simplify_on`].
+///
+/// The domain is the subgroup of `domain_size` roots of unity, as used for
+/// trace tables.
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct SimplifyContext {
+    domain_size: usize,
+    generator:   FieldElement,
+}
+
+impl SimplifyContext {
+    /// Returns `None` if there is no subgroup of size `domain_size`.
+    pub fn new(domain_size: usize) -> Option<Self> {
+        Some(Self {
+            domain_size,
+            generator: FieldElement::root(domain_size)?,
+        })
+    }
+
+    pub fn domain_size(&self) -> usize {
+        self.domain_size
+    }
+
+    /// The generator of the domain, i.e. the value of `X` on row one.
+    pub fn generator(&self) -> &FieldElement {
+        &self.generator
+    }
+}
+
 impl RationalExpression {
     pub fn neg(&self) -> Self {
         Self::Neg(Box::new(self.clone()))
@@ -43,6 +89,34 @@ impl RationalExpression {
         Self::Exp(Box::new(self.clone()), exponent)
     }
 
+    /// A column that repeats `values` every `values.len()` rows of a trace of
+    /// `trace_length` rows, for example round constants.
+    ///
+    /// On row `i`, where `X` is the `i`-th power of the trace generator, the
+    /// expression takes the value `values[i % values.len()]`. It is the
+    /// polynomial interpolating `values` on the roots of unity of order
+    /// `values.len()`, evaluated at `X^(trace_length / values.len())`. The
+    /// degree in `X` is therefore at most `trace_length - trace_length /
+    /// values.len()`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `values.len()` is not a power of two dividing `trace_length`.
+    pub fn periodic_column(values: &[FieldElement], trace_length: usize) -> Self {
+        let period = values.len();
+        assert!(
+            period.is_power_of_two() && trace_length % period == 0,
+            "Period must be a power of two dividing the trace length"
+        );
+        let mut coefficients = values.to_vec();
+        coefficients.ifft();
+        permute(&mut coefficients);
+        Self::Polynomial(
+            DensePolynomial::new(&coefficients),
+            Box::new(Self::X.pow(trace_length / period)),
+        )
+    }
+
     /// Apply a function bottom up on the expression.
     ///
     /// **Note.** Unlike the conventional generalization of `map` to tree
@@ -66,6 +140,228 @@ impl RationalExpression {
         f(e)
     }
 
+    /// Fold constant subexpressions and remove additions of zero and
+    /// multiplications by one. Multiplications by zero become zero. Products
+    /// of identical factors are folded into `Exp`, which evaluates by repeated
+    /// squaring.
+    ///
+    /// Inverses of zero are kept, so evaluation still fails on them unless
+    /// they are multiplied by zero.
+    pub fn simplify(&self) -> Self {
+        self.map(&Self::simplify_node)
+    }
+
+    /// Like [`simplify`](Self::simplify), but also uses that `X` ranges over
+    /// the domain of `context`.
+    ///
+    /// On a domain of size `n`, `X^n` is one, so exponents of `X` are reduced
+    /// modulo `n`. The result only evaluates the same as the original on the
+    /// domain. In particular, it does not apply to the cosets used in proving.
+    pub fn simplify_on(&self, context: &SimplifyContext) -> Self {
+        use RationalExpression::*;
+        let n = context.domain_size;
+        self.map(&|expression| {
+            match Self::simplify_node(expression) {
+                X if n == 1 => Constant(FieldElement::one()),
+                Exp(a, e) if *a == X && e >= n => {
+                    match e % n {
+                        0 => Constant(FieldElement::one()),
+                        1 => X,
+                        e => Exp(a, e),
+                    }
+                }
+                other => other,
+            }
+        })
+    }
+
+    /// A single step of [`simplify`](Self::simplify), assuming the operands
+    /// are simplified.
+    fn simplify_node(expression: Self) -> Self {
+        use RationalExpression::*;
+        match expression {
+            Polynomial(p, a) => {
+                match *a {
+                    Constant(c) => Constant(p.evaluate(&c)),
+                    a => Polynomial(p, Box::new(a)),
+                }
+            }
+            Add(a, b) => {
+                match (*a, *b) {
+                    (Constant(a), Constant(b)) => Constant(a + b),
+                    (Constant(zero), e) | (e, Constant(zero)) if zero.is_zero() => e,
+                    (a, b) => Add(Box::new(a), Box::new(b)),
+                }
+            }
+            Neg(a) => {
+                match *a {
+                    Constant(c) => Constant(-&c),
+                    a => Neg(Box::new(a)),
+                }
+            }
+            Mul(a, b) => {
+                match (*a, *b) {
+                    (Constant(a), Constant(b)) => Constant(a * b),
+                    (Constant(zero), _) | (_, Constant(zero)) if zero.is_zero() => Constant(zero),
+                    (Constant(one), e) | (e, Constant(one)) if one.is_one() => e,
+                    (Exp(a, m), Exp(b, n)) if a == b => Exp(a, m + n),
+                    (Exp(a, n), b) | (b, Exp(a, n)) if *a == b => Exp(a, n + 1),
+                    (a, b) if a == b => Exp(Box::new(a), 2),
+                    (a, b) => Mul(Box::new(a), Box::new(b)),
+                }
+            }
+            Inv(a) => {
+                match *a {
+                    Constant(c) => {
+                        match c.inv() {
+                            Some(inverse) => Constant(inverse),
+                            None => Inv(Box::new(Constant(c))),
+                        }
+                    }
+                    a => Inv(Box::new(a)),
+                }
+            }
+            Exp(a, e) => {
+                match *a {
+                    Constant(c) => Constant(c.pow(e)),
+                    a => Exp(Box::new(a), e),
+                }
+            }
+            other => other,
+        }
+    }
+
+    /// Normalize the expression so that it can be compared and hashed modulo
+    /// associativity and commutativity of addition and multiplication.
+    ///
+    /// The expression is simplified, then nested sums and products are
+    /// flattened, their constant operands folded into one and the remaining
+    /// operands sorted. No other algebraic identities are applied. In
+    /// particular products are not distributed over sums, so `a * (b + c)`
+    /// and `a * b + a * c` have different canonical forms, as do `a * a * b`
+    /// and `a * b * a` if `simplify` folded the first into `a^2 * b`.
+    #[cfg(feature = "std")]
+    pub fn canonicalize(&self) -> CanonicalForm {
+        use RationalExpression::*;
+        CanonicalForm(self.simplify().map(&|expression| {
+            let add = match expression {
+                Add(..) => true,
+                Mul(..) => false,
+                other => return other,
+            };
+            let mut operands = Vec::new();
+            expression.flatten_into(add, &mut operands);
+            let (constants, mut operands): (Vec<_>, Vec<_>) = operands
+                .into_iter()
+                .partition(|operand| matches!(operand, Constant(_)));
+            let constant = constants.into_iter().fold(
+                if add {
+                    FieldElement::zero()
+                } else {
+                    FieldElement::one()
+                },
+                |accumulator, operand| {
+                    match operand {
+                        Constant(c) if add => accumulator + c,
+                        Constant(c) => accumulator * c,
+                        _ => unreachable!(),
+                    }
+                },
+            );
+            if !add && constant.is_zero() {
+                return Constant(constant);
+            }
+            let neutral = if add {
+                constant.is_zero()
+            } else {
+                constant.is_one()
+            };
+            if !neutral || operands.is_empty() {
+                operands.push(Constant(constant));
+            }
+            operands.sort();
+            let mut operands = operands.into_iter();
+            let first = operands.next().unwrap();
+            operands.fold(first, |accumulator, operand| {
+                if add {
+                    Add(Box::new(accumulator), Box::new(operand))
+                } else {
+                    Mul(Box::new(accumulator), Box::new(operand))
+                }
+            })
+        }))
+    }
+
+    /// Collect the operands of a tree of additions (if `add`) or
+    /// multiplications (otherwise).
+    #[cfg(feature = "std")]
+    fn flatten_into(self, add: bool, operands: &mut Vec<Self>) {
+        use RationalExpression::*;
+        match self {
+            Add(a, b) if add => {
+                a.flatten_into(add, operands);
+                b.flatten_into(add, operands);
+            }
+            Mul(a, b) if !add => {
+                a.flatten_into(add, operands);
+                b.flatten_into(add, operands);
+            }
+            other => operands.push(other),
+        }
+    }
+
+    /// Symbolic partial derivative with respect to the trace value at
+    /// `column` and row `offset`.
+    ///
+    /// Other trace values and `X` are treated as constants. The result is not
+    /// simplified, use [`RationalExpression::simplify`] on it.
+    pub fn derivative_wrt_trace(&self, column: usize, offset: isize) -> Self {
+        use RationalExpression::*;
+        let derivative = |a: &Self| Box::new(a.derivative_wrt_trace(column, offset));
+        match self {
+            X | Constant(_) => Constant(FieldElement::zero()),
+            &Trace(i, j) => {
+                if (i, j) == (column, offset) {
+                    Constant(FieldElement::one())
+                } else {
+                    Constant(FieldElement::zero())
+                }
+            }
+            Polynomial(p, a) => {
+                // Chain rule with the formal derivative of `p`.
+                let coefficients = p.coefficients();
+                let mut derived = vec![FieldElement::zero(); coefficients.len()];
+                for (k, c) in coefficients.iter().enumerate().skip(1) {
+                    derived[k - 1] = c * FieldElement::from(k);
+                }
+                Mul(
+                    Box::new(Polynomial(DensePolynomial::new(&derived), a.clone())),
+                    derivative(a),
+                )
+            }
+            ClaimPolynomial(..) => panic!("ClaimPolynomial should be substituted by Polynomial"),
+            Add(a, b) => Add(derivative(a), derivative(b)),
+            Neg(a) => Neg(derivative(a)),
+            Mul(a, b) => {
+                Add(
+                    Box::new(Mul(derivative(a), b.clone())),
+                    Box::new(Mul(a.clone(), derivative(b))),
+                )
+            }
+            Inv(a) => Neg(Box::new(Mul(derivative(a), Box::new(a.inv().pow(2))))),
+            Exp(a, e) => {
+                match *e {
+                    0 => Constant(FieldElement::zero()),
+                    1 => *derivative(a),
+                    e => {
+                        let power = if e == 2 { *a.clone() } else { a.pow(e - 1) };
+                        Mul(Box::new(Constant(e.into()) * power), derivative(a))
+                    }
+                }
+            }
+        }
+    }
+
     pub fn substitute_claim(&self, claim_polynomials: &[DensePolynomial]) -> Self {
         use RationalExpression::*;
         let f = |x| {
@@ -83,6 +379,20 @@ impl RationalExpression {
         };
         self.map(&f)
     }
+
+    /// Replace every `Trace(column, offset)` node by `replacement`.
+    ///
+    /// The replacement is inserted as is, so it may itself reference
+    /// `Trace(column, offset)`.
+    pub fn substitute_trace(&self, column: usize, offset: isize, replacement: &Self) -> Self {
+        use RationalExpression::*;
+        self.map(&|expression| {
+            match expression {
+                Trace(i, j) if (i, j) == (column, offset) => replacement.clone(),
+                other => other,
+            }
+        })
+    }
 }
 
 impl From<i32> for RationalExpression {
@@ -103,6 +413,12 @@ impl From<FieldElement> for RationalExpression {
     }
 }
 
+impl From<&RationalExpression> for RationalExpression {
+    fn from(value: &RationalExpression) -> Self {
+        value.clone()
+    }
+}
+
 impl<T: Into<RationalExpression>> Add<T> for RationalExpression {
     type Output = Self;
 
@@ -139,6 +455,38 @@ impl<T: Into<RationalExpression>> Div<T> for RationalExpression {
     }
 }
 
+impl<T: Into<RationalExpression>> Add<T> for &RationalExpression {
+    type Output = RationalExpression;
+
+    fn add(self, other: T) -> RationalExpression {
+        self.clone() + other
+    }
+}
+
+impl<T: Into<RationalExpression>> Sub<T> for &RationalExpression {
+    type Output = RationalExpression;
+
+    fn sub(self, other: T) -> RationalExpression {
+        self.clone() - other
+    }
+}
+
+impl<T: Into<RationalExpression>> Mul<T> for &RationalExpression {
+    type Output = RationalExpression;
+
+    fn mul(self, other: T) -> RationalExpression {
+        self.clone() * other
+    }
+}
+
+impl<T: Into<RationalExpression>> Div<T> for &RationalExpression {
+    type Output = RationalExpression;
+
+    fn div(self, other: T) -> RationalExpression {
+        self.clone() / other
+    }
+}
+
 impl Sum<RationalExpression> for RationalExpression {
     fn sum<I>(mut iter: I) -> Self
     where
@@ -312,6 +660,7 @@ impl RationalExpression {
         }
     }
 
+    /// All `(column, offset)` pairs of `Trace` nodes in the expression.
     pub fn trace_arguments(&self) -> BTreeSet<(usize, isize)> {
         let mut arguments = BTreeSet::new();
         self.trace_arguments_impl(&mut arguments);
@@ -333,6 +682,284 @@ impl RationalExpression {
             ClaimPolynomial(..) => panic!("ClaimPolynomial should be substituted by Polynomial"),
         }
     }
+
+    /// True if the expression contains no inverses, and hence no division.
+    ///
+    /// This is a syntactic check, an inverse that cancels out still counts.
+    pub fn is_polynomial(&self) -> bool {
+        use RationalExpression::*;
+        match self {
+            X | Constant(_) | Trace(..) => true,
+            Polynomial(_, a) | ClaimPolynomial(_, _, a, _) | Neg(a) | Exp(a, _) => {
+                a.is_polynomial()
+            }
+            Add(a, b) | Mul(a, b) => a.is_polynomial() && b.is_polynomial(),
+            Inv(_) => false,
+        }
+    }
+
+    /// The denominator of the expression written as a single fraction, or
+    /// `None` if the expression [is a polynomial](Self::is_polynomial).
+    ///
+    /// Sums are brought on a common denominator by multiplying the
+    /// denominators, no common factors are cancelled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a `ClaimPolynomial` has a rational argument.
+    pub fn denominator(&self) -> Option<Self> {
+        self.fraction().1
+    }
+
+    /// Numerator and optional denominator of the expression, see
+    /// [`denominator`](Self::denominator). The numerator is a polynomial.
+    pub(crate) fn fraction(&self) -> (Self, Option<Self>) {
+        use RationalExpression::*;
+        let product = |a: Option<Self>, b: Option<Self>| {
+            match (a, b) {
+                (Some(a), Some(b)) => Some(a * b),
+                (a, None) => a,
+                (None, b) => b,
+            }
+        };
+        let scale = |a: Self, b: &Option<Self>| {
+            match b {
+                Some(b) => a * b,
+                None => a,
+            }
+        };
+        match self {
+            X | Constant(_) | Trace(..) => (self.clone(), None),
+            Polynomial(p, a) => {
+                match a.fraction() {
+                    (_, None) => (self.clone(), None),
+                    // p(n / d) = (sum_i c_i n^i d^(k - i)) / d^k
+                    (n, Some(d)) => {
+                        let degree = p.degree();
+                        let numerator = p
+                            .coefficients()
+                            .iter()
+                            .take(degree + 1)
+                            .enumerate()
+                            .map(|(i, coefficient)| {
+                                Constant(coefficient.clone()) * n.pow(i) * d.pow(degree - i)
+                            })
+                            .sum();
+                        (numerator, Some(d.pow(degree)))
+                    }
+                }
+            }
+            ClaimPolynomial(_, _, a, _) => {
+                assert!(
+                    a.is_polynomial(),
+                    "ClaimPolynomial should be substituted by Polynomial"
+                );
+                (self.clone(), None)
+            }
+            Add(a, b) => {
+                let (an, ad) = a.fraction();
+                let (bn, bd) = b.fraction();
+                (scale(an, &bd) + scale(bn, &ad), product(ad, bd))
+            }
+            Neg(a) => {
+                let (n, d) = a.fraction();
+                (n.neg(), d)
+            }
+            Mul(a, b) => {
+                let (an, ad) = a.fraction();
+                let (bn, bd) = b.fraction();
+                (an * bn, product(ad, bd))
+            }
+            Inv(a) => {
+                let (n, d) = a.fraction();
+                (d.unwrap_or_else(|| 1.into()), Some(n))
+            }
+            Exp(a, e) => {
+                let (n, d) = a.fraction();
+                (n.pow(*e), d.map(|d| d.pow(*e)))
+            }
+        }
+    }
+}
+
+/// Infix notation with parentheses only where precedence requires them, for
+/// example `(Trace(0, 1) - Trace(0, 0)^2) / (X - 1)`.
+impl fmt::Display for RationalExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_infix(f, false)
+    }
+}
+
+impl RationalExpression {
+    /// LaTeX math mode notation of the expression. Trace column `i` at row
+    /// offset `j` is written `T_{i}(\omega^{j} x)`.
+    pub fn to_latex(&self) -> String {
+        let mut result = String::new();
+        self.write_infix(&mut result, true)
+            .expect("Writing to a String can not fail");
+        result
+    }
+
+    /// Binding strength of the outermost operator, atoms bind strongest.
+    fn precedence(&self, latex: bool) -> usize {
+        use RationalExpression::*;
+        match self {
+            Add(..) => 1,
+            Mul(_, b) if latex && matches!(**b, Inv(_)) => 5,
+            Inv(_) if latex => 5,
+            Mul(..) | Inv(_) => 2,
+            Neg(_) => 3,
+            Constant(c) if matches!(small_constant(c), Some((true, _))) => 3,
+            Exp(..) => 4,
+            _ => 5,
+        }
+    }
+
+    fn write_operand(
+        &self,
+        f: &mut impl fmt::Write,
+        latex: bool,
+        min_precedence: usize,
+    ) -> fmt::Result {
+        if self.precedence(latex) < min_precedence {
+            let (open, close) = if latex {
+                ("\\left(", "\\right)")
+            } else {
+                ("(", ")")
+            };
+            write!(f, "{}", open)?;
+            self.write_infix(f, latex)?;
+            write!(f, "{}", close)
+        } else {
+            self.write_infix(f, latex)
+        }
+    }
+
+    fn write_infix(&self, f: &mut impl fmt::Write, latex: bool) -> fmt::Result {
+        use RationalExpression::*;
+        match self {
+            X => write!(f, "{}", if latex { "x" } else { "X" }),
+            Constant(c) => write_constant(f, c),
+            Trace(i, j) => {
+                if !latex {
+                    write!(f, "Trace({}, {})", i, j)
+                } else if *j == 0 {
+                    write!(f, "T_{{{}}}(x)", i)
+                } else {
+                    write!(f, "T_{{{}}}(\\omega^{{{}}} x)", i, j)
+                }
+            }
+            Polynomial(_, a) => {
+                write!(f, "P(")?;
+                a.write_infix(f, latex)?;
+                write!(f, ")")
+            }
+            ClaimPolynomial(i, _, a, name) => {
+                match (name, latex) {
+                    (Some(name), false) => write!(f, "{}(", name)?,
+                    (Some(name), true) => write!(f, "\\mathrm{{{}}}(", name)?,
+                    (None, false) => write!(f, "ClaimPolynomial{}(", i)?,
+                    (None, true) => write!(f, "C_{{{}}}(", i)?,
+                }
+                a.write_infix(f, latex)?;
+                write!(f, ")")
+            }
+            Add(a, b) => {
+                a.write_operand(f, latex, 1)?;
+                match &**b {
+                    Neg(b) => {
+                        write!(f, " - ")?;
+                        b.write_operand(f, latex, 2)
+                    }
+                    Constant(c) if matches!(small_constant(c), Some((true, _))) => {
+                        write!(f, " - ")?;
+                        Constant(-c).write_infix(f, latex)
+                    }
+                    b => {
+                        write!(f, " + ")?;
+                        b.write_operand(f, latex, 1)
+                    }
+                }
+            }
+            Neg(a) => {
+                write!(f, "-")?;
+                a.write_operand(f, latex, 3)
+            }
+            Mul(a, b) => {
+                match (&**b, latex) {
+                    (Inv(b), false) => {
+                        a.write_operand(f, latex, 2)?;
+                        write!(f, " / ")?;
+                        b.write_operand(f, latex, 3)
+                    }
+                    (Inv(b), true) => {
+                        write!(f, "\\frac{{")?;
+                        a.write_infix(f, latex)?;
+                        write!(f, "}}{{")?;
+                        b.write_infix(f, latex)?;
+                        write!(f, "}}")
+                    }
+                    (b, _) => {
+                        a.write_operand(f, latex, 2)?;
+                        write!(f, "{}", if latex { " \\cdot " } else { " * " })?;
+                        b.write_operand(f, latex, 2)
+                    }
+                }
+            }
+            Inv(a) => {
+                if latex {
+                    write!(f, "\\frac{{1}}{{")?;
+                    a.write_infix(f, latex)?;
+                    write!(f, "}}")
+                } else {
+                    write!(f, "1 / ")?;
+                    a.write_operand(f, latex, 3)
+                }
+            }
+            Exp(a, e) => {
+                a.write_operand(f, latex, 5)?;
+                if latex {
+                    write!(f, "^{{{}}}", e)
+                } else {
+                    write!(f, "^{}", e)
+                }
+            }
+        }
+    }
+}
+
+/// Constants that fit a `u64` up to sign are written in decimal, others in
+/// hexadecimal.
+fn write_constant(f: &mut impl fmt::Write, c: &FieldElement) -> fmt::Result {
+    match small_constant(c) {
+        Some((true, value)) => write!(f, "-{}", value),
+        Some((false, value)) => write!(f, "{}", value),
+        None => {
+            let value = c.to_uint();
+            write!(
+                f,
+                "0x{:016x}{:016x}{:016x}{:016x}",
+                value.limb(3),
+                value.limb(2),
+                value.limb(1),
+                value.limb(0)
+            )
+        }
+    }
+}
+
+/// The absolute value and sign of a constant, if the value fits a `u64`.
+fn small_constant(c: &FieldElement) -> Option<(bool, u64)> {
+    let small = |value: U256| {
+        if value.limb(1) == 0 && value.limb(2) == 0 && value.limb(3) == 0 {
+            Some(value.limb(0))
+        } else {
+            None
+        }
+    };
+    small(c.to_uint())
+        .map(|value| (false, value))
+        .or_else(|| small((-c).to_uint()).map(|value| (true, value)))
 }
 
 #[allow(clippy::derive_hash_xor_eq)]
@@ -412,3 +1039,427 @@ impl Ord for RationalExpression {
         get_hash(self).cmp(&get_hash(other))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use RationalExpression::{ClaimPolynomial, Constant, Polynomial, Trace, X};
+
+    fn trace(column: usize, offset: isize) -> FieldElement {
+        FieldElement::from(10 * column) + FieldElement::from(offset)
+    }
+
+    #[test]
+    fn periodic_column() {
+        let trace_length = 16;
+        let values: Vec<FieldElement> = [3, 1, 4, 1].iter().map(|&v| v.into()).collect();
+        let column = RationalExpression::periodic_column(&values, trace_length);
+        let generator = FieldElement::root(trace_length).unwrap();
+        let no_trace = |_, _| panic!("No trace values expected");
+        // Two full periods, starting on a row that is not a multiple of it.
+        for row in 3..11 {
+            assert_eq!(
+                column.evaluate(&generator.pow(row), &no_trace),
+                values[row % 4]
+            );
+        }
+        assert_eq!(column.degree(1), (12, 0));
+
+        let constant = RationalExpression::periodic_column(&[5.into()], trace_length);
+        assert_eq!(
+            constant.evaluate(&generator.pow(7_usize), &no_trace),
+            5.into()
+        );
+    }
+
+    #[test]
+    fn evaluate_nodes() {
+        let x = FieldElement::from(5);
+        let eval = |expression: RationalExpression| expression.evaluate(&x, &trace);
+        assert_eq!(eval(X), x);
+        assert_eq!(eval(Constant(3.into())), 3.into());
+        assert_eq!(eval(Trace(2, 1)), 21.into());
+        assert_eq!(eval(Trace(0, -1)), -&FieldElement::one());
+        let p = DensePolynomial::new(&[1.into(), 2.into(), 3.into(), 0.into()]);
+        assert_eq!(eval(Polynomial(p, Box::new(X))), 86.into());
+        assert_eq!(eval(X + Trace(1, 0)), 15.into());
+        assert_eq!(eval(X - 7), -&FieldElement::from(2));
+        assert_eq!(eval(X.neg()), -&x);
+        assert_eq!(eval(X * Trace(1, 1)), 55.into());
+        assert_eq!(eval(X.inv()), x.inv().unwrap());
+        assert_eq!(eval(Trace(1, 0) / X), 2.into());
+        assert_eq!(eval(X.pow(3)), 125.into());
+    }
+
+    #[test]
+    fn reference_operators() {
+        let a = Trace(0, 1);
+        let b = Trace(0, 0);
+        let expression = &a - &b * &b / 2 + &a;
+        let boxed = |e: &RationalExpression| Box::new(e.clone());
+        let square = RationalExpression::Mul(boxed(&b), boxed(&b));
+        let half = RationalExpression::Inv(boxed(&Constant(2.into())));
+        let product = RationalExpression::Mul(Box::new(square), Box::new(half));
+        let difference = RationalExpression::Add(boxed(&a), Box::new(product.neg()));
+        assert_eq!(
+            expression,
+            RationalExpression::Add(Box::new(difference), boxed(&a))
+        );
+        assert_eq!(expression, a.clone() - b.clone() * b / 2 + a);
+
+        let x = FieldElement::from(5);
+        let trace = |_: usize, j: isize| FieldElement::from(if j == 0 { 4 } else { 3 });
+        assert_eq!(
+            expression.evaluate(&x, &trace),
+            FieldElement::from(6) - FieldElement::from(8)
+        );
+    }
+
+    #[test]
+    fn degree_nested() {
+        let c = Constant(7.into());
+        let expression = (X.pow(3) + Trace(0, 0)) / (X - c.clone());
+        assert_eq!(expression.degree(1), (3, 1));
+        assert_eq!(expression.degree(2), (3, 1));
+        assert_eq!(expression.degree(15), (15, 1));
+        assert_eq!(expression.trace_degree(), (1, 0));
+
+        let product = Trace(0, 0) * Trace(1, -1) * X;
+        assert_eq!(product.degree(15), (31, 0));
+        assert_eq!(product.trace_degree(), (2, 0));
+
+        // Sums of fractions use a common denominator
+        let sum = Trace(0, 0) / (X - c.clone()) + X.pow(2) / (X.pow(4) - c);
+        assert_eq!(sum.degree(15), (19, 5));
+        assert_eq!(sum.neg().inv().degree(15), (5, 19));
+    }
+
+    #[test]
+    fn trace_arguments() {
+        let expression = (Trace(2, 1) - Trace(0, 0).pow(2)) / (X - Trace(2, 1)) + Trace(0, 1);
+        assert_eq!(
+            expression.trace_arguments().into_iter().collect::<Vec<_>>(),
+            vec![(0, 0), (0, 1), (2, 1)]
+        );
+        assert!(X.pow(3).trace_arguments().is_empty());
+    }
+
+    #[test]
+    fn polynomial_denominator() {
+        let p = DensePolynomial::new(&[1.into(), 2.into(), 3.into(), 0.into()]);
+        let polynomial = (Trace(0, 1) - Trace(0, 0).pow(2)) * X
+            + Polynomial(p.clone(), Box::new(Trace(1, 0)))
+            - 5;
+        assert!(polynomial.is_polynomial());
+        assert_eq!(polynomial.denominator(), None);
+
+        assert!(!(X / 2).is_polynomial());
+        assert_eq!((X / 2).denominator(), Some(Constant(2.into())));
+        // Division nested inside an argument counts as well
+        let nested = Polynomial(p, Box::new(Trace(0, 0) / (X - 1)));
+        assert!(!nested.is_polynomial());
+        assert_eq!(nested.denominator(), Some((X - 1).pow(2)));
+
+        let x = FieldElement::from(5);
+        let rationals = vec![
+            nested,
+            (Trace(0, 1) - Trace(0, 0)) / (X - 1) + Trace(1, 0) / (X.pow(4) - 3),
+            (X.inv() - Trace(1, 1)).inv().pow(3).neg(),
+            Trace(0, 0) * (X / Trace(1, 0)) - 7,
+        ];
+        for expression in rationals {
+            assert!(!expression.is_polynomial());
+            let denominator = expression.denominator().unwrap();
+            assert!(denominator.is_polynomial());
+            // Multiplying by the denominator gives back the numerator
+            let (numerator, _) = expression.fraction();
+            assert!(numerator.is_polynomial());
+            assert_eq!(
+                expression.evaluate(&x, &trace) * denominator.evaluate(&x, &trace),
+                numerator.evaluate(&x, &trace)
+            );
+        }
+    }
+
+    fn node_count(expression: &RationalExpression) -> usize {
+        use RationalExpression::*;
+        match expression {
+            X | Constant(_) | Trace(..) => 1,
+            Polynomial(_, a) | ClaimPolynomial(_, _, a, _) | Neg(a) | Inv(a) | Exp(a, _) => {
+                1 + node_count(a)
+            }
+            Add(a, b) | Mul(a, b) => 1 + node_count(a) + node_count(b),
+        }
+    }
+
+    #[test]
+    fn simplify_constants() {
+        let expression = (Constant(2.into()) + 3) * X.pow(2) - Constant(4.into()).pow(2) / 8;
+        let simplified = expression.simplify();
+        assert_eq!(
+            simplified,
+            Constant(5.into()) * X.pow(2) + Constant(-&FieldElement::from(2))
+        );
+        assert!(node_count(&simplified) < node_count(&expression));
+
+        assert_eq!((X + 0).simplify(), X);
+        assert_eq!((Constant(0.into()) + Trace(0, 1)).simplify(), Trace(0, 1));
+        assert_eq!((X * 1 * Trace(1, 0)).simplify(), X * Trace(1, 0));
+        assert_eq!(
+            (X.pow(5) * Constant(0.into())).simplify(),
+            Constant(0.into())
+        );
+        assert_eq!(
+            (Trace(0, 0) * (Constant(2.into()) - 2)).simplify(),
+            Constant(0.into())
+        );
+        let p = DensePolynomial::new(&[1.into(), 2.into(), 3.into(), 0.into()]);
+        assert_eq!(
+            Polynomial(p, Box::new(Constant(5.into()))).simplify(),
+            Constant(86.into())
+        );
+        // Division by zero is preserved
+        let division = X / (Constant(3.into()) - 3);
+        assert_eq!(division.simplify(), X * Constant(0.into()).inv());
+    }
+
+    #[test]
+    fn simplify_products_to_exp() {
+        let chain = (1..1024).fold(X, |product, _| product * X);
+        assert_eq!(chain.degree(1), (1024, 0));
+        let simplified = chain.simplify();
+        assert_eq!(simplified, X.pow(1024));
+        assert_eq!(simplified.degree(1), (1024, 0));
+        let x = FieldElement::from(3);
+        let trace = |_, _| FieldElement::zero();
+        assert_eq!(simplified.evaluate(&x, &trace), chain.evaluate(&x, &trace));
+
+        let t = Trace(0, 1);
+        assert_eq!((t.clone() * t.clone()).simplify(), t.pow(2));
+        assert_eq!((t.clone() * t.pow(3)).simplify(), t.pow(4));
+        assert_eq!((t.pow(2) * t.pow(5)).simplify(), t.pow(7));
+        assert_eq!((t.clone() * Trace(0, 0)).simplify(), t * Trace(0, 0));
+    }
+
+    #[test]
+    fn simplify_on_domain() {
+        let size = 16;
+        let context = SimplifyContext::new(size).unwrap();
+        assert!(SimplifyContext::new(3).is_none());
+
+        // A constraint on every fourth row, with the vanishing polynomial of
+        // the other rows folded into the numerator
+        let period = X.pow(size / 4);
+        let constraint = (Trace(0, 1) - Trace(0, 0).pow(2)) * X.pow(size) * X.pow(2 * size + 3)
+            / (period.clone() - 1)
+            + X.pow(size) * Trace(1, 0)
+            - X.pow(4 * size)
+            + period;
+        let simplified = constraint.simplify_on(&context);
+        let expected = (Trace(0, 1) - Trace(0, 0).pow(2)) * X.pow(3) / (X.pow(4) - 1) + Trace(1, 0)
+            - 1
+            + X.pow(4);
+        assert_eq!(simplified, expected.simplify());
+        // Without the domain only the constants fold
+        assert_eq!(constraint.simplify().degree(1).0, constraint.degree(1).0);
+
+        let trace = |i: usize, j: isize| FieldElement::from(3 * i + 1) + FieldElement::from(j);
+        let mut x = context.generator().clone();
+        for _ in 1..size {
+            if (X.pow(4) - 1).evaluate(&x, &trace) != FieldElement::zero() {
+                assert_eq!(
+                    simplified.evaluate(&x, &trace),
+                    constraint.evaluate(&x, &trace)
+                );
+            }
+            x *= context.generator();
+        }
+
+        // A domain of size one has `X` equal to one
+        let context = SimplifyContext::new(1).unwrap();
+        assert_eq!(
+            (X * Trace(0, 0) + X.pow(5)).simplify_on(&context),
+            Trace(0, 0) + 1
+        );
+    }
+
+    proptest!(
+        #[test]
+        fn simplify_preserves_evaluation(x: FieldElement, t: FieldElement) {
+            let c = Constant(7.into());
+            let expressions = vec![
+                (Trace(0, 1) - Trace(0, 0).pow(2)) / (X - 1) + Constant(0.into()) * X,
+                (c.clone() * 3 + 1) * (Trace(1, 0) - c.clone().pow(2)),
+                X.pow(3) * (c.clone() - 6) + (c.clone() / 7).neg() * Trace(0, -1),
+                (c.clone() + c.clone() * X).inv() * (X - X) + Constant(1.into()) / 2,
+            ];
+            let trace = |i: usize, j: isize| &t + FieldElement::from(i) * FieldElement::from(j);
+            for expression in &expressions {
+                prop_assume!((X - 1).evaluate(&x, &trace) != FieldElement::zero());
+                prop_assume!(
+                    (c.clone() + c.clone() * X).evaluate(&x, &trace) != FieldElement::zero()
+                );
+                prop_assert_eq!(
+                    expression.simplify().evaluate(&x, &trace),
+                    expression.evaluate(&x, &trace)
+                );
+            }
+        }
+    );
+
+    #[test]
+    fn canonicalize() {
+        let (a, b, c) = (Trace(0, 0), Trace(1, 0), X);
+        let equal = |left: RationalExpression, right: RationalExpression| {
+            let (left, right) = (left.canonicalize(), right.canonicalize());
+            assert_eq!(left, right);
+            assert_eq!(get_hash(left.expression()), get_hash(right.expression()));
+        };
+        equal(a.clone() + b.clone(), b.clone() + a.clone());
+        equal(a.clone() * b.clone(), b.clone() * a.clone());
+        equal(
+            (a.clone() + b.clone()) + c.clone(),
+            c.clone() + (b.clone() + a.clone()),
+        );
+        equal(
+            a.clone() * (b.clone() * c.clone()) * 3,
+            Constant(3.into()) * c.clone() * b.clone() * a.clone(),
+        );
+        equal(a.clone() + 2 + b.clone() + 3, b.clone() + 5 + a.clone());
+        equal(
+            a.clone() * 2 * b.clone() * Constant(0.into()),
+            Constant(0.into()),
+        );
+        equal(
+            a.clone() - a.clone() + 1,
+            Constant(1.into()) - a.clone() + a.clone(),
+        );
+
+        // Products are not distributed over sums
+        assert_ne!(
+            (a.clone() * (b.clone() + c.clone())).canonicalize(),
+            (a.clone() * b + a * c).canonicalize()
+        );
+    }
+
+    #[test]
+    fn substitute_trace() {
+        // Inline column 2, derived as the product of columns 0 and 1.
+        let derived = Trace(0, 0) * Trace(1, 0);
+        let expression = (Trace(2, 0) - X) * Trace(2, 1) + Trace(2, 0).pow(2);
+        let inlined = expression.substitute_trace(2, 0, &derived);
+        assert_eq!(
+            inlined,
+            (derived.clone() - X) * Trace(2, 1) + derived.clone().pow(2)
+        );
+
+        let x = FieldElement::from(5);
+        let consistent = |i: usize, j: isize| {
+            if (i, j) == (2, 0) {
+                derived.evaluate(&x, &trace)
+            } else {
+                trace(i, j)
+            }
+        };
+        assert_eq!(
+            inlined.evaluate(&x, &trace),
+            expression.evaluate(&x, &consistent)
+        );
+        assert_eq!(X.substitute_trace(2, 0, &derived), X);
+    }
+
+    #[test]
+    fn derivative_wrt_trace() {
+        let constraint = Trace(0, 1) - Trace(0, 0).pow(2);
+        assert_eq!(
+            constraint.derivative_wrt_trace(0, 1).simplify(),
+            Constant(1.into())
+        );
+        assert_eq!(
+            constraint.derivative_wrt_trace(0, 0).simplify(),
+            (Constant(2.into()) * Trace(0, 0)).neg()
+        );
+        assert_eq!(
+            constraint.derivative_wrt_trace(1, 0).simplify(),
+            Constant(0.into())
+        );
+        assert_eq!((Trace(1, 0) * X).derivative_wrt_trace(1, 0).simplify(), X);
+
+        let x = FieldElement::from(5);
+        let eval = |expression: &RationalExpression| expression.evaluate(&x, &trace);
+        // d/dt (t^3 + X * t) / (t - 1) with t = Trace(1, 0) = 10 and X = 5 is
+        // ((3 t^2 + X)(t - 1) - (t^3 + X t)) / (t - 1)^2 = 1695 / 81
+        let t = Trace(1, 0);
+        let rational = (t.pow(3) + X * t.clone()) / (t - 1);
+        let expected = FieldElement::from(1695) / FieldElement::from(81);
+        assert_eq!(eval(&rational.derivative_wrt_trace(1, 0)), expected);
+        assert_eq!(
+            eval(&rational.derivative_wrt_trace(1, 0).simplify()),
+            expected
+        );
+
+        // p(t) = 1 + 2 t + 3 t^2, p'(t) = 2 + 6 t
+        let p = DensePolynomial::new(&[1.into(), 2.into(), 3.into(), 0.into()]);
+        let composed = Polynomial(p, Box::new(Trace(1, 0) * 2));
+        assert_eq!(
+            eval(&composed.derivative_wrt_trace(1, 0)),
+            FieldElement::from(2 * (2 + 6 * 20))
+        );
+    }
+
+    #[test]
+    fn display() {
+        let c = Constant(7.into());
+        let expression = (Trace(0, 1) - Trace(0, 0).pow(2)) / (X - 1);
+        assert_eq!(
+            expression.to_string(),
+            "(Trace(0, 1) - Trace(0, 0)^2) / (X - 1)"
+        );
+        assert_eq!(
+            expression.to_latex(),
+            "\\frac{T_{0}(\\omega^{1} x) - T_{0}(x)^{2}}{x - 1}"
+        );
+        assert_eq!(
+            (X.pow(2).pow(3) + X.neg().pow(2)).to_string(),
+            "(X^2)^3 + (-X)^2"
+        );
+        assert_eq!(
+            (X.pow(2).pow(3) + X.neg().pow(2)).to_latex(),
+            "\\left(x^{2}\\right)^{3} + \\left(-x\\right)^{2}"
+        );
+        assert_eq!(
+            (X / (Trace(1, -1) * c.clone()) / (X / 2)).to_string(),
+            "X / (Trace(1, -1) * 7) / (X / 2)"
+        );
+        assert_eq!(
+            (X * (c.clone() + 1) - (X - Trace(0, 0))).to_string(),
+            "X * (7 + 1) - (X - Trace(0, 0))"
+        );
+        assert_eq!(
+            (X * (c.clone() + 1)).to_latex(),
+            "x \\cdot \\left(7 + 1\\right)"
+        );
+        assert_eq!((c.clone() - 8).neg().inv().to_string(), "1 / -(7 - 8)");
+        assert_eq!(
+            (X.pow(3) + Constant(-&FieldElement::from(3))).to_string(),
+            "X^3 - 3"
+        );
+        assert_eq!(
+            Constant(FieldElement::from(U256::from(1_u128 << 64))).to_string(),
+            "0x0000000000000000000000000000000000000000000000010000000000000000"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "divided by zero")]
+    fn evaluate_division_by_zero() {
+        let _ = (Trace(0, 0) / (X - 5)).evaluate(&5.into(), &trace);
+    }
+
+    #[test]
+    #[should_panic(expected = "ClaimPolynomial should be substituted by Polynomial")]
+    fn evaluate_claim_polynomial() {
+        let _ = ClaimPolynomial(0, 1, Box::new(X), None).evaluate(&5.into(), &trace);
+    }
+}
@@ -0,0 +1,404 @@
+//! Text format for constraint systems.
+//!
+//! Expressions are written as S-expressions, for example
+//! `(mul (add (trace 0 1) (neg x)) (inv (exp x 2)))`. Constants are written as
+//! 64 hexadecimal digits of their canonical value, so the format is lossless.
+//!
+//! The nodes are `x`, `(const <hex>)`, `(trace <column> <offset>)`,
+//! `(poly (<hex> ...) <expr>)`, `(claim <index> <degree bound> <name> <expr>)`
+//! where `<name>` is a quoted string or `nil`, `(add <expr> <expr>)`,
+//! `(neg <expr>)`, `(mul <expr> <expr>)`, `(inv <expr>)` and
+//! `(exp <expr> <exponent>)`.
+//!
+//! Claim nodes are written but not parsed. Their names are `&'static str`, and
+//! claim polynomials are substituted before constraints are evaluated, so they
+//! do not occur in constraint systems read back from text.
+use crate::{polynomial::DensePolynomial, rational_expression::RationalExpression};
+#[cfg(feature = "std")]
+use std::error;
+use std::{fmt, fmt::Write, prelude::v1::*, str::FromStr};
+use zkp_primefield::FieldElement;
+use zkp_u256::U256;
+
+/// The deepest nesting of expressions the parser accepts. This bounds the
+/// recursion, so malicious input can not overflow the stack.
+const MAX_DEPTH: usize = 512;
+
+/// Parse errors, with the byte offset in the input where they occurred.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Error {
+    UnexpectedEnd,
+    UnexpectedToken(usize),
+    InvalidNumber(usize),
+    InvalidConstant(usize),
+    TooDeep(usize),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Error::*;
+        match *self {
+            UnexpectedEnd => write!(f, "Unexpected end of input"),
+            UnexpectedToken(offset) => write!(f, "Unexpected token at offset {}", offset),
+            InvalidNumber(offset) => write!(f, "Invalid number at offset {}", offset),
+            InvalidConstant(offset) => write!(f, "Invalid field element at offset {}", offset),
+            TooDeep(offset) => write!(f, "Expression nested too deeply at offset {}", offset),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for Error {}
+
+impl RationalExpression {
+    /// Serialize to the S-expression format, see [`RationalExpression::from_sexpr`].
+    pub fn to_sexpr(&self) -> String {
+        let mut result = String::new();
+        write_sexpr(&mut result, self).expect("Writing to a String can not fail");
+        result
+    }
+
+    /// Serialize a list of expressions, one per line.
+    pub fn list_to_sexpr(expressions: &[Self]) -> String {
+        let mut result = String::new();
+        for expression in expressions {
+            write_sexpr(&mut result, expression).expect("Writing to a String can not fail");
+            result.push('\n');
+        }
+        result
+    }
+
+    /// Parse a single expression in the format produced by
+    /// [`RationalExpression::to_sexpr`].
+    ///
+    /// `ClaimPolynomial`s are rejected, see the module documentation.
+    pub fn from_sexpr(input: &str) -> Result<Self, Error> {
+        let mut parser = Parser::new(input);
+        let expression = parser.expression()?;
+        parser.end()?;
+        Ok(expression)
+    }
+
+    /// Parse a whitespace separated list of expressions, as produced by
+    /// [`RationalExpression::list_to_sexpr`].
+    pub fn list_from_sexpr(input: &str) -> Result<Vec<Self>, Error> {
+        let mut parser = Parser::new(input);
+        let mut expressions = Vec::new();
+        while parser.peek().is_some() {
+            expressions.push(parser.expression()?);
+        }
+        Ok(expressions)
+    }
+}
+
+fn write_constant(f: &mut impl Write, c: &FieldElement) -> fmt::Result {
+    for byte in &c.to_uint().to_bytes_be() {
+        write!(f, "{:02x}", byte)?;
+    }
+    Ok(())
+}
+
+fn write_sexpr(f: &mut impl Write, expression: &RationalExpression) -> fmt::Result {
+    use RationalExpression::*;
+    match expression {
+        X => write!(f, "x"),
+        Constant(c) => {
+            write!(f, "(const ")?;
+            write_constant(f, c)?;
+            write!(f, ")")
+        }
+        Trace(i, j) => write!(f, "(trace {} {})", i, j),
+        Polynomial(p, a) => {
+            write!(f, "(poly (")?;
+            for (i, c) in p.coefficients().iter().enumerate() {
+                if i > 0 {
+                    write!(f, " ")?;
+                }
+                write_constant(f, c)?;
+            }
+            write!(f, ") ")?;
+            write_sexpr(f, a)?;
+            write!(f, ")")
+        }
+        ClaimPolynomial(i, n, a, name) => {
+            write!(f, "(claim {} {} ", i, n)?;
+            match name {
+                Some(name) => {
+                    write!(f, "\"")?;
+                    for c in name.chars() {
+                        if c == '"' || c == '\\' {
+                            write!(f, "\\")?;
+                        }
+                        write!(f, "{}", c)?;
+                    }
+                    write!(f, "\" ")?;
+                }
+                None => write!(f, "nil ")?,
+            }
+            write_sexpr(f, a)?;
+            write!(f, ")")
+        }
+        Add(a, b) => write_node(f, "add", &[a, b]),
+        Neg(a) => write_node(f, "neg", &[a]),
+        Mul(a, b) => write_node(f, "mul", &[a, b]),
+        Inv(a) => write_node(f, "inv", &[a]),
+        Exp(a, e) => {
+            write!(f, "(exp ")?;
+            write_sexpr(f, a)?;
+            write!(f, " {})", e)
+        }
+    }
+}
+
+fn write_node(f: &mut impl Write, name: &str, arguments: &[&RationalExpression]) -> fmt::Result {
+    write!(f, "({}", name)?;
+    for argument in arguments {
+        write!(f, " ")?;
+        write_sexpr(f, argument)?;
+    }
+    write!(f, ")")
+}
+
+struct Parser<'a> {
+    input:  &'a str,
+    offset: usize,
+    depth:  usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Self {
+        Self {
+            input,
+            offset: 0,
+            depth: 0,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.input[self.offset..];
+        self.offset += rest.len() - rest.trim_start().len();
+    }
+
+    /// The next non-whitespace character.
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.input[self.offset..].chars().next()
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), Error> {
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.offset += c.len_utf8();
+                Ok(())
+            }
+            Some(_) => Err(Error::UnexpectedToken(self.offset)),
+            None => Err(Error::UnexpectedEnd),
+        }
+    }
+
+    fn end(&mut self) -> Result<(), Error> {
+        match self.peek() {
+            Some(_) => Err(Error::UnexpectedToken(self.offset)),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the next atom and its offset.
+    fn atom(&mut self) -> Result<(&'a str, usize), Error> {
+        let start = match self.peek() {
+            Some('(') | Some(')') => return Err(Error::UnexpectedToken(self.offset)),
+            Some(_) => self.offset,
+            None => return Err(Error::UnexpectedEnd),
+        };
+        let rest = &self.input[start..];
+        let len = rest
+            .find(|c: char| c.is_whitespace() || c == '(' || c == ')')
+            .unwrap_or(rest.len());
+        self.offset += len;
+        Ok((&rest[..len], start))
+    }
+
+    fn number<T: FromStr>(&mut self) -> Result<T, Error> {
+        let (atom, offset) = self.atom()?;
+        atom.parse().map_err(|_| Error::InvalidNumber(offset))
+    }
+
+    fn constant(&mut self) -> Result<FieldElement, Error> {
+        let (atom, offset) = self.atom()?;
+        if atom.len() != 64 {
+            return Err(Error::InvalidConstant(offset));
+        }
+        let mut bytes = [0_u8; 32];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = atom
+                .get(2 * i..2 * i + 2)
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                .ok_or(Error::InvalidConstant(offset))?;
+        }
+        let value = U256::from_bytes_be(&bytes);
+        if value >= FieldElement::MODULUS {
+            return Err(Error::InvalidConstant(offset));
+        }
+        Ok(FieldElement::from(value))
+    }
+
+    fn boxed(&mut self) -> Result<Box<RationalExpression>, Error> {
+        self.expression().map(Box::new)
+    }
+
+    fn expression(&mut self) -> Result<RationalExpression, Error> {
+        use RationalExpression::*;
+        if self.peek() != Some('(') {
+            let (atom, offset) = self.atom()?;
+            return if atom == "x" {
+                Ok(X)
+            } else {
+                Err(Error::UnexpectedToken(offset))
+            };
+        }
+        let open = self.offset;
+        self.expect('(')?;
+        if self.depth == MAX_DEPTH {
+            return Err(Error::TooDeep(open));
+        }
+        self.depth += 1;
+        let (operator, offset) = self.atom()?;
+        let expression = match operator {
+            "const" => Constant(self.constant()?),
+            "trace" => Trace(self.number()?, self.number()?),
+            "poly" => {
+                self.expect('(')?;
+                let start = self.offset;
+                let mut coefficients = Vec::new();
+                while self.peek() != Some(')') {
+                    coefficients.push(self.constant()?);
+                }
+                self.expect(')')?;
+                if !coefficients.len().is_power_of_two() {
+                    return Err(Error::InvalidNumber(start));
+                }
+                Polynomial(DensePolynomial::new(&coefficients), self.boxed()?)
+            }
+            "add" => Add(self.boxed()?, self.boxed()?),
+            "neg" => Neg(self.boxed()?),
+            "mul" => Mul(self.boxed()?, self.boxed()?),
+            "inv" => Inv(self.boxed()?),
+            "exp" => Exp(self.boxed()?, self.number()?),
+            _ => return Err(Error::UnexpectedToken(offset)),
+        };
+        self.expect(')')?;
+        self.depth -= 1;
+        Ok(expression)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::{collection::vec as prop_vec, prelude::*};
+    use RationalExpression::*;
+
+    fn arb_expression() -> impl Strategy<Value = RationalExpression> {
+        let leaf = prop_oneof![
+            Just(X),
+            FieldElement::arbitrary().prop_map(Constant),
+            (0_usize..10, -5_isize..5).prop_map(|(i, j)| Trace(i, j)),
+        ];
+        leaf.prop_recursive(4, 32, 2, |inner| {
+            prop_oneof![
+                (inner.clone(), inner.clone()).prop_map(|(a, b)| Add(Box::new(a), Box::new(b))),
+                inner.clone().prop_map(|a| Neg(Box::new(a))),
+                (inner.clone(), inner.clone()).prop_map(|(a, b)| Mul(Box::new(a), Box::new(b))),
+                inner.clone().prop_map(|a| Inv(Box::new(a))),
+                (inner.clone(), 0_usize..10).prop_map(|(a, e)| Exp(Box::new(a), e)),
+                (prop_vec(FieldElement::arbitrary(), 4), inner)
+                    .prop_map(|(c, a)| Polynomial(DensePolynomial::new(&c), Box::new(a))),
+            ]
+        })
+    }
+
+    #[test]
+    fn sexpr_format() {
+        let expression = (Trace(0, 1) - Trace(2, -1).pow(2)) / (X - 1);
+        let text = expression.to_sexpr();
+        assert_eq!(
+            text,
+            "(mul (add (trace 0 1) (neg (exp (trace 2 -1) 2))) (inv (add x (neg (const \
+             0000000000000000000000000000000000000000000000000000000000000001)))))"
+        );
+        assert_eq!(RationalExpression::from_sexpr(&text), Ok(expression));
+    }
+
+    #[test]
+    fn sexpr_errors() {
+        assert_eq!(
+            RationalExpression::from_sexpr(""),
+            Err(Error::UnexpectedEnd)
+        );
+        assert_eq!(
+            RationalExpression::from_sexpr("(add x"),
+            Err(Error::UnexpectedEnd)
+        );
+        assert_eq!(
+            RationalExpression::from_sexpr("(sub x x)"),
+            Err(Error::UnexpectedToken(1))
+        );
+        assert_eq!(
+            RationalExpression::from_sexpr("x x"),
+            Err(Error::UnexpectedToken(2))
+        );
+        assert_eq!(
+            RationalExpression::from_sexpr("(trace 0 x)"),
+            Err(Error::InvalidNumber(9))
+        );
+        assert_eq!(
+            RationalExpression::from_sexpr("(const 12)"),
+            Err(Error::InvalidConstant(7))
+        );
+        assert_eq!(
+            RationalExpression::from_sexpr(
+                "(const 0800000000000011000000000000000000000000000000000000000000000001)"
+            ),
+            Err(Error::InvalidConstant(7))
+        );
+    }
+
+    #[test]
+    fn sexpr_claim() {
+        let expression = ClaimPolynomial(1, 4, Box::new(X), Some("a \"quoted\" name"));
+        let text = expression.to_sexpr();
+        assert_eq!(text, "(claim 1 4 \"a \\\"quoted\\\" name\" x)");
+        assert_eq!(
+            RationalExpression::from_sexpr(&text),
+            Err(Error::UnexpectedToken(1))
+        );
+    }
+
+    #[test]
+    fn sexpr_depth() {
+        let nested = |depth: usize| format!("{}x{}", "(neg ".repeat(depth), ")".repeat(depth));
+        assert!(RationalExpression::from_sexpr(&nested(MAX_DEPTH)).is_ok());
+        assert_eq!(
+            RationalExpression::from_sexpr(&nested(MAX_DEPTH + 1)),
+            Err(Error::TooDeep(5 * MAX_DEPTH))
+        );
+        assert_eq!(
+            RationalExpression::from_sexpr(&nested(1_000_000)),
+            Err(Error::TooDeep(5 * MAX_DEPTH))
+        );
+    }
+
+    proptest!(
+        #[test]
+        fn sexpr_round_trip(expression in arb_expression()) {
+            let text = expression.to_sexpr();
+            prop_assert_eq!(RationalExpression::from_sexpr(&text), Ok(expression));
+        }
+
+        #[test]
+        fn sexpr_list_round_trip(expressions in prop_vec(arb_expression(), 0..5)) {
+            let text = RationalExpression::list_to_sexpr(&expressions);
+            prop_assert_eq!(RationalExpression::list_from_sexpr(&text), Ok(expressions));
+        }
+    );
+}
@@ -1,15 +1,51 @@
 use crate::{
-    channel::{RandomGenerator, Replayable, VerifierChannel},
+    channel::{
+        Endianness, Keccak256, RandomGenerator, Replayable, TranscriptHash, VerifierChannel,
+    },
     constraints::Constraints,
     proof_of_work, Proof,
 };
 use hex::encode;
-use std::{collections::BTreeMap, prelude::v1::*};
+use std::{collections::BTreeMap, fmt, prelude::v1::*};
 use zkp_hash::Hash;
 use zkp_merkle_tree::{Commitment, Error as MerkleError};
 use zkp_primefield::FieldElement;
 use zkp_u256::U256;
 
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    /// The proof does not use a Keccak256 transcript.
+    UnsupportedTranscriptHash,
+    /// The proof does not use big-endian words.
+    UnsupportedEndianness,
+    Merkle(MerkleError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Error::*;
+        match *self {
+            UnsupportedTranscriptHash => {
+                write!(
+                    f,
+                    "The Solidity verifier only supports Keccak256 transcripts"
+                )
+            }
+            UnsupportedEndianness => {
+                write!(f, "The Solidity verifier only supports big-endian proofs")
+            }
+            // This is a wrapper, so defer to the underlying types' implementation of `fmt`.
+            Merkle(ref e) => std::fmt::Display::fmt(e, f),
+        }
+    }
+}
+
+impl From<MerkleError> for Error {
+    fn from(err: MerkleError) -> Self {
+        Self::Merkle(err)
+    }
+}
+
 // This trait is a simple json encoding trait which exports the values
 // in the format expected by the ethereum call, the hand made version
 // is used instead of serde because it differ in important ways
@@ -55,21 +91,37 @@ impl<T: SoliditySeralize> SoliditySeralize for Vec<T> {
     }
 }
 
+/// Appends the proof in the JSON format expected by the Solidity verifier to
+/// `result_string`.
+///
+/// # Errors
+///
+/// Returns [`Error::UnsupportedTranscriptHash`] or
+/// [`Error::UnsupportedEndianness`] if the proof does not use a Keccak256
+/// transcript and big-endian words, see [`Constraints::transcript_hash`] and
+/// [`Constraints::endianness`]. The Solidity verifier supports no others.
 // TODO - Make this function smaller
 #[allow(clippy::too_many_lines)]
 pub fn proof_serialize(
     constraints: &Constraints,
     proof: &Proof,
     result_string: &mut String,
-) -> Result<(), MerkleError> {
+) -> Result<(), Error> {
+    if constraints.transcript_hash != TranscriptHash::Keccak256 {
+        return Err(Error::UnsupportedTranscriptHash);
+    }
+    if constraints.endianness != Endianness::Big {
+        return Err(Error::UnsupportedEndianness);
+    }
     let proof = proof.as_bytes();
     let trace_length = constraints.trace_nrows();
     let trace_cols = constraints.trace_ncolumns();
     let eval_domain_size = trace_length * constraints.blowup;
 
-    let mut channel = VerifierChannel::new(proof.to_vec());
     // TODO - Add method to seralize public input
-    channel.initialize(constraints.channel_seed());
+    let mut channel = constraints
+        .channel::<Keccak256>()
+        .build_verifier(proof.to_vec());
 
     // Get the low degree root commitment, and constraint root commitment
     // TODO: Make it work as channel.read()
@@ -297,6 +349,56 @@ mod tests {
         .unwrap();
     }
 
+    #[test]
+    fn seralize_blake2s_recurrance() {
+        let r = Recurrance {
+            index:         150,
+            initial_value: field_element!("cafebabe"),
+            exponent:      2,
+        };
+
+        let public = r.claim();
+        let private = r.witness();
+
+        let mut constraints = public.constraints();
+        constraints.transcript_hash = TranscriptHash::Blake2s256;
+
+        let trace = public.trace(&private);
+        let proof = prove(&constraints, &trace).unwrap();
+
+        let mut result_string = "".to_string();
+        assert_eq!(
+            proof_serialize(&constraints, &proof, &mut result_string),
+            Err(Error::UnsupportedTranscriptHash)
+        );
+        assert!(result_string.is_empty());
+    }
+
+    #[test]
+    fn seralize_little_endian_recurrance() {
+        let r = Recurrance {
+            index:         150,
+            initial_value: field_element!("cafebabe"),
+            exponent:      2,
+        };
+
+        let public = r.claim();
+        let private = r.witness();
+
+        let mut constraints = public.constraints();
+        constraints.endianness = Endianness::Little;
+
+        let trace = public.trace(&private);
+        let proof = prove(&constraints, &trace).unwrap();
+
+        let mut result_string = "".to_string();
+        assert_eq!(
+            proof_serialize(&constraints, &proof, &mut result_string),
+            Err(Error::UnsupportedEndianness)
+        );
+        assert!(result_string.is_empty());
+    }
+
     // Note this test is actually more like a binary which we want run so it
     // commented out, The Recurrance struct can't be exported to a binary or
     // example because it only lives in tests.
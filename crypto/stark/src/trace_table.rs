@@ -1,11 +1,47 @@
 use crate::polynomial::DensePolynomial;
 use log::trace;
+use rayon::prelude::*;
+#[cfg(feature = "std")]
+use std::error;
 use std::{
+    fmt,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    iter::StepBy,
     ops::{Index, IndexMut},
+    path::Path,
     prelude::v1::*,
+    slice,
 };
 use zkp_mmap_vec::MmapVec;
 use zkp_primefield::{fft::permute, Fft, FieldElement, Root, Zero};
+use zkp_u256::U256;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Error {
+    NoColumns,
+    /// The column with this index has a different length than the first.
+    RaggedColumns(usize),
+    /// The witness is inconsistent with the claim.
+    InvalidWitness,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Error::*;
+        match *self {
+            NoColumns => write!(f, "Trace table has no columns"),
+            RaggedColumns(j) => write!(f, "Column {} has a different length", j),
+            InvalidWitness => write!(f, "The witness is inconsistent with the claim"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for Error {}
+
+/// Read-only strided view of a single column of a [`TraceTable`].
+pub type ColumnView<'a> = StepBy<slice::Iter<'a, FieldElement>>;
 
 #[derive(Clone, PartialEq)]
 #[cfg_attr(feature = "std", derive(Debug))]
@@ -29,6 +65,84 @@ impl TraceTable {
         }
     }
 
+    /// Reads a trace table written by [`TraceTable::save`].
+    ///
+    /// The file needs to contain exactly `trace_length` rows of `num_columns`
+    /// values. The values are copied into a new table, so later changes to
+    /// the file or the table do not affect each other.
+    ///
+    /// The table is not mapped onto the file. The file stores canonical values
+    /// while the table stores them in Montgomery form, and mapping the file
+    /// would need `unsafe` code, which this crate forbids. Like every table,
+    /// the copy lives in an anonymous [`MmapVec`], so it is paged by the
+    /// operating system rather than held on the heap.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`] if the size of the table
+    /// overflows, and with [`io::ErrorKind::InvalidData`] if the file has a
+    /// different size or contains a value that is not reduced.
+    pub fn load<P: AsRef<Path>>(
+        path: P,
+        trace_length: usize,
+        num_columns: usize,
+    ) -> io::Result<Self> {
+        let size = trace_length
+            .checked_mul(num_columns)
+            .and_then(|len| len.checked_mul(32))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Trace table too large"))?;
+        let file = File::open(path)?;
+        if file.metadata()?.len() != size as u64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "File size does not match the trace table",
+            ));
+        }
+        let mut reader = BufReader::new(file);
+        let mut result = Self::new(trace_length, num_columns);
+        let mut bytes = [0_u8; 32];
+        for value in result.values.iter_mut() {
+            reader.read_exact(&mut bytes)?;
+            let uint = U256::from_bytes_be(&bytes);
+            if uint >= FieldElement::MODULUS {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Trace table value is not reduced",
+                ));
+            }
+            *value = FieldElement::from_uint(&uint);
+        }
+        Ok(result)
+    }
+
+    /// Writes the table to the file at `path`, replacing its contents.
+    ///
+    /// Values are stored row first, without a header. Each value is stored as
+    /// 32 bytes, the big-endian encoding of its canonical (not Montgomery)
+    /// representation, so the file does not depend on the platform.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        for value in self.values.iter() {
+            writer.write_all(&value.to_uint().to_bytes_be())?;
+        }
+        writer.flush()
+    }
+
+    /// Constructs a trace table from a list of equal length columns.
+    pub fn from_columns(columns: Vec<Vec<FieldElement>>) -> Result<Self, Error> {
+        let trace_length = columns.first().ok_or(Error::NoColumns)?.len();
+        if let Some(j) = columns.iter().position(|c| c.len() != trace_length) {
+            return Err(Error::RaggedColumns(j));
+        }
+        let mut result = Self::new(trace_length, columns.len());
+        for (j, column) in columns.into_iter().enumerate() {
+            for (i, value) in column.into_iter().enumerate() {
+                result[(i, j)] = value;
+            }
+        }
+        Ok(result)
+    }
+
     pub fn num_rows(&self) -> usize {
         self.trace_length
     }
@@ -37,6 +151,11 @@ impl TraceTable {
         self.num_columns
     }
 
+    /// Returns `(num_rows, num_columns)`.
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.trace_length, self.num_columns)
+    }
+
     pub fn generator(&self) -> FieldElement {
         FieldElement::root(self.trace_length).expect("No generator for trace table length.")
     }
@@ -47,9 +166,47 @@ impl TraceTable {
     }
 
     pub fn iter_column(&self, j: usize) -> impl Iterator<Item = &FieldElement> {
+        self.column(j)
+    }
+
+    /// Returns the i-th row as a slice.
+    pub fn row(&self, i: usize) -> &[FieldElement] {
+        &self[i]
+    }
+
+    /// Returns a view of the j-th column without copying.
+    ///
+    /// The table is stored row first, so this iterates with a stride of
+    /// `num_columns`.
+    pub fn column(&self, j: usize) -> ColumnView<'_> {
+        assert!(j < self.num_columns);
         self.values[j..].iter().step_by(self.num_columns)
     }
 
+    /// Fills every row in parallel with the values returned by `f(row_index)`.
+    ///
+    /// This only applies when rows can be computed independently of each
+    /// other. Traces where a row depends on the previous one (like most
+    /// iterated computations) need to be filled sequentially.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `f` returns a row of the wrong length.
+    pub fn fill_rows_par<F>(&mut self, f: F)
+    where
+        F: Fn(usize) -> Vec<FieldElement> + Sync,
+    {
+        let num_columns = self.num_columns;
+        self.values
+            .par_chunks_mut(num_columns)
+            .enumerate()
+            .for_each(|(i, row)| {
+                let values = f(i);
+                assert_eq!(values.len(), num_columns, "Row has wrong length");
+                row.clone_from_slice(&values);
+            });
+    }
+
     /// Extract the j-th column as a vector
     ///
     /// It allocates a potentially large new vector. Where possible, use
@@ -65,6 +222,17 @@ impl TraceTable {
         result
     }
 
+    /// Interpolate each column to a polynomial of degree less than
+    /// `num_rows()`.
+    ///
+    /// Row `i` of the trace is the value of the polynomial at `g^i`, where
+    /// `g = FieldElement::root(num_rows())` is the trace generator. This is
+    /// the domain that constraints are written on, as in
+    /// `Component::trace_generator`. Coefficients are in order of increasing
+    /// degree.
+    ///
+    /// The number of rows must be a power of two. This is only checked in
+    /// debug builds.
     pub fn interpolate(&self) -> Vec<DensePolynomial> {
         trace!("BEGIN Interpolate");
         let result = (0..self.num_columns())
@@ -127,3 +295,109 @@ impl IndexMut<usize> for TraceTable {
         &mut self.values[i * self.num_columns..(i + 1) * self.num_columns]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zkp_primefield::Pow;
+
+    fn table() -> TraceTable {
+        let mut table = TraceTable::new(8, 3);
+        for i in 0..8 {
+            for j in 0..3 {
+                table[(i, j)] = FieldElement::from(10 * i + j);
+            }
+        }
+        table
+    }
+
+    #[test]
+    fn views() {
+        let table = table();
+        assert_eq!(table.dimensions(), (8, 3));
+        assert_eq!(table.row(2), &[20.into(), 21.into(), 22.into()]);
+        for j in 0..3 {
+            let column = table.column(j).cloned().collect::<Vec<_>>();
+            let expected = (0..8).map(|i| table[(i, j)].clone()).collect::<Vec<_>>();
+            assert_eq!(column, expected);
+        }
+    }
+
+    #[test]
+    fn interpolate() {
+        let table = table();
+        let polynomials = table.interpolate();
+        assert_eq!(polynomials.len(), 3);
+        let generator = FieldElement::root(8).unwrap();
+        for (j, polynomial) in polynomials.iter().enumerate() {
+            assert_eq!(polynomial.len(), 8);
+            for i in 0..8 {
+                assert_eq!(polynomial.evaluate(&generator.pow(i)), table[(i, j)]);
+            }
+        }
+    }
+
+    #[test]
+    fn from_columns() {
+        let columns = (0..3)
+            .map(|j| (0..8).map(|i| FieldElement::from(10 * i + j)).collect())
+            .collect();
+        assert_eq!(TraceTable::from_columns(columns), Ok(table()));
+    }
+
+    #[test]
+    fn from_columns_errors() {
+        assert_eq!(TraceTable::from_columns(vec![]), Err(Error::NoColumns));
+        let ragged = vec![vec![1.into(); 8], vec![2.into(); 8], vec![3.into(); 7]];
+        assert_eq!(
+            TraceTable::from_columns(ragged),
+            Err(Error::RaggedColumns(2))
+        );
+    }
+
+    #[test]
+    fn fill_rows_par() {
+        let row = |i: usize| {
+            let x = FieldElement::from(i);
+            vec![x.clone(), &x * &x, &x * &x * &x]
+        };
+        let mut sequential = TraceTable::new(1024, 3);
+        for i in 0..1024 {
+            sequential[i].clone_from_slice(&row(i));
+        }
+        let mut parallel = TraceTable::new(1024, 3);
+        parallel.fill_rows_par(row);
+        assert_eq!(parallel, sequential);
+    }
+
+    #[test]
+    #[should_panic(expected = "j < self.num_columns")]
+    fn column_out_of_range() {
+        let _ = table().column(3);
+    }
+
+    #[test]
+    fn save_load() {
+        use std::{env, fs, process};
+        let path = env::temp_dir().join(format!("zkp-stark-trace-{}", process::id()));
+        table().save(&path).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().len(), 8 * 3 * 32);
+        let loaded = TraceTable::load(&path, 8, 3).unwrap();
+        assert_eq!(loaded, table());
+        assert!(loaded.column(2).eq(table().column(2)));
+
+        // Dimensions need to match the file.
+        let error = TraceTable::load(&path, 4, 3).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        let error = TraceTable::load(&path, !0_usize, 3).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+
+        // Values need to be reduced.
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[..32].copy_from_slice(&FieldElement::MODULUS.to_bytes_be());
+        fs::write(&path, bytes).unwrap();
+        let error = TraceTable::load(&path, 8, 3).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        fs::remove_file(&path).unwrap();
+    }
+}
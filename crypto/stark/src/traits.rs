@@ -1,7 +1,7 @@
 #[cfg(feature = "prover")]
 use crate::constraint_check::{check_constraints, check_specific_constraint};
 #[cfg(feature = "prover")]
-use crate::{prove, ProverError, TraceTable};
+use crate::{prove, ProverError, TraceError, TraceTable};
 use crate::{verify, Constraints, Proof, VerifierError};
 
 pub trait Verifiable {
@@ -17,9 +17,15 @@ pub trait Verifiable {
 pub trait Provable<T>: Verifiable {
     fn trace(&self, witness: T) -> TraceTable;
 
+    /// Like [`Provable::trace`], but fails on a witness that is inconsistent
+    /// with the claim. The default calls [`Provable::trace`].
+    fn try_trace(&self, witness: T) -> Result<TraceTable, TraceError> {
+        Ok(self.trace(witness))
+    }
+
     fn prove(&self, witness: T) -> Result<Proof, ProverError> {
         let constraints = self.constraints();
-        let trace = self.trace(witness);
+        let trace = self.try_trace(witness)?;
         prove(&constraints, &trace)
     }
 
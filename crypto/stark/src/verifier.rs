@@ -1,12 +1,15 @@
 use crate::{
-    channel::{RandomGenerator, Replayable, VerifierChannel},
+    channel::{
+        Blake2s256, Error as ChannelError, HashFunction, Keccak256, ProofSource, RandomGenerator,
+        Replayable, TranscriptHash, VerifierChannel,
+    },
     constraints::Constraints,
     polynomial::DensePolynomial,
-    proof_of_work, Proof,
+    Proof,
 };
 use log::trace;
 #[cfg(feature = "std")]
-use std::error;
+use std::{error, io::Read};
 use std::{collections::BTreeMap, fmt, prelude::v1::*};
 use zkp_hash::Hash;
 use zkp_merkle_tree::{Commitment, Error as MerkleError, Proof as MerkleProof};
@@ -32,6 +35,7 @@ pub enum Error {
     OodsMismatch,
     FriCalculationFailure,
     Merkle(MerkleError),
+    Channel(ChannelError),
 }
 
 impl fmt::Display for Error {
@@ -65,6 +69,7 @@ impl fmt::Display for Error {
             OodsMismatch => write!(f, "Calculated oods value doesn't match the committed one"),
             // This is a wrapper, so defer to the underlying types' implementation of `fmt`.
             Merkle(ref e) => std::fmt::Display::fmt(e, f),
+            Channel(ref e) => std::fmt::Display::fmt(e, f),
         }
     }
 }
@@ -75,6 +80,12 @@ impl From<MerkleError> for Error {
     }
 }
 
+impl From<ChannelError> for Error {
+    fn from(err: ChannelError) -> Self {
+        Self::Channel(err)
+    }
+}
+
 // False positives on the Latex math.
 #[allow(clippy::doc_markdown)]
 /// # Stark verify
@@ -173,26 +184,71 @@ impl From<MerkleError> for Error {
 /// * Evaluate the final layer
 ///
 /// <!-- TODO: ellaborate FRI verification -->
+pub fn verify(constraints: &Constraints, proof: &Proof) -> Result<()> {
+    let proof = proof.as_bytes().to_vec();
+    match constraints.transcript_hash {
+        TranscriptHash::Keccak256 => {
+            verify_channel(
+                constraints,
+                constraints.channel::<Keccak256>().build_verifier(proof),
+            )
+        }
+        TranscriptHash::Blake2s256 => {
+            verify_channel(
+                constraints,
+                constraints.channel::<Blake2s256>().build_verifier(proof),
+            )
+        }
+    }
+}
+
+/// Verify a Stark proof read from `reader` as it is verified.
+///
+/// The result is the same as for [`verify`] on the bytes of the proof, but
+/// only the bytes of the item being replayed are held in memory. The reader is
+/// read in order and to the end, to reject proofs with trailing bytes. Fails
+/// with [`ChannelError::ReadFailed`] if reading fails.
+#[cfg(feature = "std")]
+pub fn verify_from<R: Read>(constraints: &Constraints, reader: R) -> Result<()> {
+    match constraints.transcript_hash {
+        TranscriptHash::Keccak256 => {
+            verify_channel(
+                constraints,
+                constraints
+                    .channel::<Keccak256>()
+                    .build_streaming_verifier(reader),
+            )
+        }
+        TranscriptHash::Blake2s256 => {
+            verify_channel(
+                constraints,
+                constraints
+                    .channel::<Blake2s256>()
+                    .build_streaming_verifier(reader),
+            )
+        }
+    }
+}
+
 // TODO: Refactor into smaller function
 #[allow(clippy::too_many_lines)]
-pub fn verify(constraints: &Constraints, proof: &Proof) -> Result<()> {
+fn verify_channel<H: HashFunction, S: ProofSource>(
+    constraints: &Constraints,
+    mut channel: VerifierChannel<H, S>,
+) -> Result<()> {
     trace!("BEGIN Verify");
-    let proof = proof.as_bytes();
     let trace_length = constraints.trace_nrows();
     let trace_cols = constraints.trace_ncolumns();
     let eval_domain_size = trace_length * constraints.blowup;
     let eval_x = root_series(eval_domain_size).collect::<Vec<_>>();
 
-    let mut channel = VerifierChannel::new(proof.to_vec());
-    channel.initialize(constraints.channel_seed());
-
     // Get the low degree root commitment, and constraint root commitment
     // TODO: Make it work as channel.read()
-    let low_degree_extension_root: Hash = channel.replay();
+    let low_degree_extension_root: Hash = channel.try_replay()?;
     let lde_commitment = Commitment::from_size_hash(eval_domain_size, &low_degree_extension_root)?;
     let constraint_coefficients = channel.get_coefficients(2 * constraints.len());
 
-    let constraint_evaluated_root: Hash = channel.replay();
+    let constraint_evaluated_root: Hash = channel.try_replay()?;
     let constraint_commitment =
         Commitment::from_size_hash(eval_domain_size, &constraint_evaluated_root)?;
 
@@ -203,15 +259,18 @@ pub fn verify(constraints: &Constraints, proof: &Proof) -> Result<()> {
     let mut parseable_constraints = constraints.clone();
     parseable_constraints.substitute();
     let trace_arguments = parseable_constraints.trace_arguments();
-    let trace_values: Vec<FieldElement> = channel.replay_many(trace_arguments.len());
+    let trace_values: Vec<FieldElement> = (0..trace_arguments.len())
+        .map(|_| channel.try_replay_field())
+        .collect::<std::result::Result<_, _>>()?;
     let claimed_trace_map: BTreeMap<(usize, isize), FieldElement> = trace_arguments
         .into_iter()
         .zip(trace_values.iter().cloned())
         .collect();
 
     let constraints_trace_degree = constraints.degree().next_power_of_two();
-    let claimed_constraint_values: Vec<FieldElement> =
-        channel.replay_many(constraints_trace_degree);
+    let claimed_constraint_values: Vec<FieldElement> = (0..constraints_trace_degree)
+        .map(|_| channel.try_replay_field())
+        .collect::<std::result::Result<_, _>>()?;
 
     let oods_coefficients =
         channel.get_coefficients(claimed_trace_map.len() + claimed_constraint_values.len());
@@ -222,34 +281,33 @@ pub fn verify(constraints: &Constraints, proof: &Proof) -> Result<()> {
     // Get fri roots and eval points from the channel random
     for &num_folds in &constraints.fri_layout {
         fri_size >>= num_folds;
-        fri_commitments.push(Commitment::from_size_hash(fri_size, &channel.replay())?);
+        fri_commitments.push(Commitment::from_size_hash(
+            fri_size,
+            &channel.try_replay()?,
+        )?);
         eval_points.push(channel.get_random());
     }
     // Gets the last layer coeffiencts
-    let last_layer_coefficients = channel.replay_fri_layer(fri_size / constraints.blowup);
+    let last_layer_coefficients = channel.try_replay_fri_layer(fri_size / constraints.blowup)?;
 
     // Gets the proof of work from the proof.
-    let pow_seed: proof_of_work::ChallengeSeed = channel.get_random();
-    let pow_challenge = pow_seed.with_difficulty(constraints.pow_bits);
-    let pow_response: proof_of_work::Response = channel.replay();
-    if !pow_challenge.verify(pow_response) {
-        return Err(Error::InvalidPoW);
-    }
+    let _ = channel.replay_pow(constraints.pow_bits).map_err(|error| {
+        match error {
+            ChannelError::InvalidPow => Error::InvalidPoW,
+            error => error.into(),
+        }
+    })?;
 
     // Gets queries from channel
-    let queries = get_indices(
-        constraints.num_queries,
-        eval_domain_size.trailing_zeros(),
-        &mut channel,
-    );
+    let queries = get_indices(constraints.num_queries, eval_domain_size, &mut channel);
 
     // Get values and check decommitment of low degree extension
     let lde_values: Vec<(usize, Vec<FieldElement>)> = queries
         .iter()
-        .map(|&index| (index, channel.replay_fri_layer(trace_cols)))
-        .collect();
+        .map(|&index| Ok((index, channel.try_replay_fri_layer(trace_cols)?)))
+        .collect::<Result<_>>()?;
     let lde_proof_length = lde_commitment.proof_size(&queries)?;
-    let lde_hashes: Vec<Hash> = channel.replay_many(lde_proof_length);
+    let lde_hashes: Vec<Hash> = channel.try_replay_many(lde_proof_length)?;
     let lde_proof = MerkleProof::from_hashes(&lde_commitment, &queries, &lde_hashes)?;
     // Note - we could express this a merkle error instead but this adds specificity
     if lde_proof.verify(&lde_values).is_err() {
@@ -261,11 +319,11 @@ pub fn verify(constraints: &Constraints, proof: &Proof) -> Result<()> {
     for query_index in &queries {
         constraint_values.push((
             *query_index,
-            channel.replay_fri_layer(constraints_trace_degree),
+            channel.try_replay_fri_layer(constraints_trace_degree)?,
         ));
     }
     let constraint_proof_length = constraint_commitment.proof_size(&queries)?;
-    let constraint_hashes: Vec<Hash> = channel.replay_many(constraint_proof_length);
+    let constraint_hashes: Vec<Hash> = channel.try_replay_many(constraint_proof_length)?;
     let constraint_proof =
         MerkleProof::from_hashes(&constraint_commitment, &queries, &constraint_hashes)?;
     // Note - we could express this a merkle error instead but this adds specificity
@@ -318,7 +376,7 @@ pub fn verify(constraints: &Constraints, proof: &Proof) -> Result<()> {
                         )?);
                     }
                 } else {
-                    coset.push(channel.replay());
+                    coset.push(channel.try_replay_field()?);
                 }
             }
             fri_layer_values.push((*i, coset));
@@ -340,7 +398,7 @@ pub fn verify(constraints: &Constraints, proof: &Proof) -> Result<()> {
         }
 
         let merkle_proof_length = commitment.proof_size(&fri_indices)?;
-        let merkle_hashes: Vec<Hash> = channel.replay_many(merkle_proof_length);
+        let merkle_hashes: Vec<Hash> = channel.try_replay_many(merkle_proof_length)?;
         let merkle_proof = MerkleProof::from_hashes(commitment, &fri_indices, &merkle_hashes)?;
         fri_folds = layer_folds;
 
@@ -362,9 +420,12 @@ pub fn verify(constraints: &Constraints, proof: &Proof) -> Result<()> {
                 .collect();
         }
     }
-    if !channel.at_end() {
-        return Err(Error::ProofTooLong);
-    }
+    channel.finalize().map_err(|error| {
+        match error {
+            ChannelError::TrailingBytes(_) => Error::ProofTooLong,
+            error => error.into(),
+        }
+    })?;
 
     // Checks that the calculated fri folded queries are the points interpolated by
     // the decommited polynomial.
@@ -423,17 +484,28 @@ fn oods_value_from_constraint_values(
 
 // TODO: Clean up
 #[allow(clippy::cast_possible_truncation)]
-fn get_indices(num: usize, bits: u32, proof: &mut VerifierChannel) -> Vec<usize> {
+/// Draw `num` sorted query indices into an evaluation domain of `size`.
+///
+/// Must match the prover's sampling, see `prover::get_indices`.
+fn get_indices<H: HashFunction, S: ProofSource>(
+    num: usize,
+    size: usize,
+    proof: &mut VerifierChannel<H, S>,
+) -> Vec<usize> {
+    if !size.is_power_of_two() {
+        let mut query_indices: Vec<usize> =
+            (0..num).map(|_| proof.coin.get_random_index(size)).collect();
+        query_indices.sort_unstable();
+        return query_indices;
+    }
+    let mask = (size - 1) as u64;
     let mut query_indices = Vec::with_capacity(num + 3);
     while query_indices.len() < num {
         let val: U256 = proof.get_random();
-        query_indices
-            .push(((val.clone() >> (0x100 - 0x040)).limb(0) & (2_u64.pow(bits) - 1)) as usize);
-        query_indices
-            .push(((val.clone() >> (0x100 - 0x080)).limb(0) & (2_u64.pow(bits) - 1)) as usize);
-        query_indices
-            .push(((val.clone() >> (0x100 - 0x0C0)).limb(0) & (2_u64.pow(bits) - 1)) as usize);
-        query_indices.push((val.limb(0) & (2_u64.pow(bits) - 1)) as usize);
+        query_indices.push(((val.clone() >> (0x100 - 0x040)).limb(0) & mask) as usize);
+        query_indices.push(((val.clone() >> (0x100 - 0x080)).limb(0) & mask) as usize);
+        query_indices.push(((val.clone() >> (0x100 - 0x0C0)).limb(0) & mask) as usize);
+        query_indices.push((val.limb(0) & mask) as usize);
     }
     query_indices.truncate(num);
     (&mut query_indices).sort_unstable();
@@ -533,6 +605,8 @@ mod tests {
         Provable, Verifiable,
     };
     use proptest::prelude::*;
+    use std::io;
+    use zkp_macros_decl::field_element;
 
     proptest!(
         #[test]
@@ -556,5 +630,96 @@ mod tests {
 
             prop_assert!(verify(&constraints, &prove(&constraints, &trace).unwrap()).is_ok());
         }
+
+        #[test]
+        fn verify_wrong_length(r: Recurrance) {
+            let public = r.claim();
+            let private = r.witness();
+
+            let constraints = public.constraints();
+            let trace = public.trace(&private);
+            let proof = prove(&constraints, &trace).unwrap();
+
+            let mut bytes = proof.as_bytes().to_vec();
+            let _ = bytes.pop();
+            prop_assert_eq!(
+                verify(&constraints, &Proof::from_bytes(bytes)),
+                Err(Error::Channel(ChannelError::ProofTooShort))
+            );
+            let mut bytes = proof.as_bytes().to_vec();
+            bytes.push(0);
+            prop_assert_eq!(
+                verify(&constraints, &Proof::from_bytes(bytes)),
+                Err(Error::ProofTooLong)
+            );
+        }
     );
+
+    #[test]
+    fn verify_from_reader() {
+        let recurrance = Recurrance {
+            index:         100,
+            initial_value: field_element!("cafebabe"),
+            exponent:      1,
+        };
+        let claim = recurrance.claim();
+        let mut constraints = claim.constraints();
+        let trace = claim.trace(&recurrance.witness());
+        let proof = prove(&constraints, &trace).unwrap();
+        assert_eq!(verify_from(&constraints, proof.as_bytes()), Ok(()));
+
+        let bytes = proof.as_bytes();
+        assert_eq!(
+            verify_from(&constraints, &bytes[..bytes.len() - 1]),
+            Err(Error::Channel(ChannelError::ProofTooShort))
+        );
+        assert_eq!(
+            verify_from(&constraints, &[bytes, &[0]].concat()[..]),
+            Err(Error::ProofTooLong)
+        );
+
+        // A reader failing after the proof is not reported as trailing data.
+        struct Disconnected;
+        impl io::Read for Disconnected {
+            fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::Other, "disconnected"))
+            }
+        }
+        assert_eq!(
+            verify_from(&constraints, io::Read::chain(bytes, Disconnected)),
+            Err(Error::Channel(ChannelError::ReadFailed))
+        );
+
+        constraints.transcript_hash = TranscriptHash::Blake2s256;
+        let proof = prove(&constraints, &trace).unwrap();
+        assert_eq!(verify_from(&constraints, proof.as_bytes()), Ok(()));
+    }
+
+    #[test]
+    fn verify_non_canonical_oods_values() {
+        let recurrance = Recurrance {
+            index:         100,
+            initial_value: field_element!("cafebabe"),
+            exponent:      1,
+        };
+        let claim = recurrance.claim();
+        let constraints = claim.constraints();
+        let trace = claim.trace(&recurrance.witness());
+        let proof = prove(&constraints, &trace).unwrap();
+
+        // The OODS values follow the trace and constraint commitments.
+        let mut parseable_constraints = constraints.clone();
+        parseable_constraints.substitute();
+        let trace_values_start = 64;
+        let constraint_values_start =
+            trace_values_start + 32 * parseable_constraints.trace_arguments().len();
+        for &start in &[trace_values_start, constraint_values_start] {
+            let mut bytes = proof.as_bytes().to_vec();
+            bytes[start..start + 32].copy_from_slice(&[0xff; 32]);
+            assert_eq!(
+                verify(&constraints, &Proof::from_bytes(bytes)),
+                Err(Error::Channel(ChannelError::NonCanonicalFieldElement))
+            );
+        }
+    }
 }